@@ -5,10 +5,18 @@ __path__ = extend_path(__path__, __name__)
 from ._array import Array as Array
 from ._array import ArrayEvent as ArrayEvent
 from ._array import TypedArray as TypedArray
+from ._attribution import AttributionRecorder as AttributionRecorder
 from ._awareness import Awareness as Awareness
 from ._awareness import is_awareness_disconnect_message as is_awareness_disconnect_message
 from ._doc import Doc as Doc
+from ._doc import SubdocsEvent as SubdocsEvent
 from ._doc import TypedDoc as TypedDoc
+from ._exceptions import ConversionError as ConversionError
+from ._exceptions import CrdtError as CrdtError
+from ._exceptions import DecodeError as DecodeError
+from ._exceptions import IntegrationError as IntegrationError
+from ._exceptions import OffsetError as OffsetError
+from ._exceptions import TransactionError as TransactionError
 from ._map import Map as Map
 from ._map import MapEvent as MapEvent
 from ._map import TypedMap as TypedMap
@@ -18,8 +26,8 @@ from ._pycrdt import AttrRange as AttrRange
 from ._pycrdt import ContentAttribute as ContentAttribute
 from ._pycrdt import IdMap as IdMap
 from ._pycrdt import IdSet as IdSet
+from ._pycrdt import Message as Message
 from ._pycrdt import StackItem as StackItem
-from ._pycrdt import SubdocsEvent as SubdocsEvent
 from ._pycrdt import Subscription as Subscription
 from ._pycrdt import TransactionEvent as TransactionEvent
 from ._snapshot import Snapshot as Snapshot
@@ -32,6 +40,8 @@ from ._sync import YSyncMessageType as YSyncMessageType
 from ._sync import create_awareness_message as create_awareness_message
 from ._sync import create_sync_message as create_sync_message
 from ._sync import create_update_message as create_update_message
+from ._sync import decode_messages as decode_messages
+from ._sync import encode_messages as encode_messages
 from ._sync import handle_sync_message as handle_sync_message
 from ._sync import read_message as read_message
 from ._sync import write_message as write_message
@@ -39,12 +49,20 @@ from ._sync import write_var_uint as write_var_uint
 from ._text import Text as Text
 from ._text import TextEvent as TextEvent
 from ._transaction import NewTransaction as NewTransaction
+from ._transaction import PendingChanges as PendingChanges
 from ._transaction import ReadTransaction as ReadTransaction
 from ._transaction import Transaction as Transaction
 from ._undo import UndoManager as UndoManager
+from ._update import convert_update_v1_to_v2 as convert_update_v1_to_v2
+from ._update import convert_update_v2_to_v1 as convert_update_v2_to_v1
+from ._update import decode_update as decode_update
+from ._update import decode_update_v2 as decode_update_v2
 from ._update import get_state as get_state
+from ._update import get_state_v2 as get_state_v2
 from ._update import get_update as get_update
+from ._update import get_update_v2 as get_update_v2
 from ._update import merge_updates as merge_updates
+from ._update import merge_updates_v2 as merge_updates_v2
 from ._version import __version__ as __version__
 from ._xml import XmlElement as XmlElement
 from ._xml import XmlEvent as XmlEvent
@@ -6,6 +6,7 @@ from ._base import BaseDoc, BaseEvent, BaseType, Sequence, Typed, base_types, ev
 from ._pycrdt import Array as _Array
 from ._pycrdt import ArrayEvent as _ArrayEvent
 from ._pycrdt import Subscription
+from ._snapshot import Snapshot
 
 if TYPE_CHECKING:
     from ._doc import Doc
@@ -56,6 +57,7 @@ class Array(Sequence, Generic[T]):
             self._forbid_read_transaction(txn)
             if isinstance(value, BaseDoc):
                 # subdoc
+                value._parent = self.doc
                 self.integrated.insert_doc(txn._txn, index, value._doc)
             elif isinstance(value, BaseType):
                 # shared type
@@ -335,15 +337,21 @@ class Array(Sequence, Generic[T]):
         with self.doc.transaction() as txn:
             return self.integrated.to_json(txn._txn)
 
-    def to_py(self) -> list[T] | None:
+    def to_py(self, snapshot: Snapshot | None = None) -> list[T] | None:
         """
         Recursively converts the array's items to Python objects, and
         returns them in a list. If the array was not yet inserted in a document,
         returns `None` if the array was not initialized.
 
+        Args:
+            snapshot: An optional [Snapshot][pycrdt.Snapshot] to get the items as they were at
+                that time, which requires a document created with `skip_gc=True`.
+
         Returns:
             The array recursively converted to Python objects, or `None`.
         """
+        if snapshot is not None:
+            return snapshot._read(self)
         if self._integrated is None:
             py = self._prelim
             if py is None:
@@ -355,6 +363,30 @@ class Array(Sequence, Generic[T]):
                 py[idx] = val.to_py()
         return py
 
+    def diff_snapshots(
+        self, prev_snapshot: Snapshot, snapshot: Snapshot | None = None
+    ) -> list[dict[str, Any]]:
+        """
+        Compares the array between two snapshots, e.g. to show the changes between two versions.
+        The document must have been created with `skip_gc=True`.
+
+        Args:
+            prev_snapshot: The [Snapshot][pycrdt.Snapshot] of the previous version.
+            snapshot: The [Snapshot][pycrdt.Snapshot] of the new version
+                (default is the current version).
+
+        Returns:
+            The changes since the previous version, like the `delta` of an
+                [ArrayEvent][pycrdt.ArrayEvent]: a list of items to `retain`, `delete` or `insert`.
+
+        Raises:
+            ValueError: The previous snapshot must precede the snapshot.
+        """
+        if snapshot is None:
+            snapshot = Snapshot.from_doc(self.doc)
+        changes = snapshot._diff(self.doc, prev_snapshot, self)
+        return changes[0][1] if changes else []
+
     def observe(self, callback: Callable[[ArrayEvent], None]) -> Subscription:
         """
         Subscribes a callback to be called with the array event.
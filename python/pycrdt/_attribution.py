@@ -0,0 +1,62 @@
+from __future__ import annotations
+
+from typing import TYPE_CHECKING, Any, Callable
+
+from ._pycrdt import ContentAttribute, IdMap, Subscription, TransactionEvent
+
+if TYPE_CHECKING:
+    from ._doc import Doc
+
+
+class AttributionRecorder:
+    """
+    Records the attribution of the changes of a document: the IDs of the content inserted
+    and deleted by its transactions are accumulated per transaction origin, in
+    [IdMap][pycrdt.IdMap]s tagged with attributes (e.g. a user name and a timestamp).
+    """
+
+    def __init__(self, doc: Doc, attributes: Callable[[Any], list[ContentAttribute]]) -> None:
+        """
+        Args:
+            doc: The document to record the changes of.
+            attributes: A callable called with the origin of each transaction that changed the
+                document (`None` if it has no origin), returning the attributes of its changes.
+        """
+        self._doc = doc
+        self._attributes = attributes
+        self._insertions: dict[Any, IdMap] = {}
+        self._deletions: dict[Any, IdMap] = {}
+        self._subscription: Subscription | None = doc.observe(self._record)
+
+    @property
+    def insertions(self) -> dict[Any, IdMap]:
+        """
+        The attributed IDs of the inserted content, per transaction origin.
+        """
+        return self._insertions
+
+    @property
+    def deletions(self) -> dict[Any, IdMap]:
+        """
+        The attributed IDs of the deleted content, per transaction origin.
+        """
+        return self._deletions
+
+    def _record(self, event: TransactionEvent) -> None:
+        origin = event.origin
+        id_maps = IdMap.from_event(event, self._attributes(origin))
+        for recorded, id_map in zip((self._insertions, self._deletions), id_maps):
+            if not id_map:
+                continue
+            if origin in recorded:
+                recorded[origin] |= id_map
+            else:
+                recorded[origin] = id_map
+
+    def close(self) -> None:
+        """
+        Stops recording the changes of the document.
+        """
+        if self._subscription is not None:
+            self._doc.unobserve(self._subscription)
+            self._subscription = None
@@ -1,62 +1,61 @@
 from __future__ import annotations
 
-import copy
-import json
-from time import time
-from typing import Any, Callable, Literal, cast
+from typing import Any, Callable
 from uuid import uuid4
 
 from anyio import TASK_STATUS_IGNORED, create_task_group, sleep
 from anyio.abc import TaskGroup, TaskStatus
 
 from ._doc import Doc
-from ._sync import Decoder, Encoder, read_message
+from ._pycrdt import Awareness as _Awareness
+from ._pycrdt import Subscription
+from ._sync import Decoder, read_message
 
 
 class Awareness:
-    client_id: int
-    _meta: dict[int, dict[str, Any]]
-    _states: dict[int, dict[str, Any]]
-    _subscriptions: dict[str, Callable[[str, tuple[dict[str, Any], Any]], None]]
+    """
+    The awareness of a document, as defined by the
+    [y-protocols awareness protocol](https://github.com/yjs/y-protocols), whose encoded
+    updates are byte-compatible with Yjs peers.
+    """
+
+    _awareness: _Awareness
+    _subscriptions: dict[str, tuple[Subscription, Subscription]]
     _task_group: TaskGroup | None
 
-    def __init__(self, ydoc: Doc, *, outdated_timeout: int = 30000) -> None:
+    def __init__(
+        self,
+        ydoc: Doc,
+        *,
+        outdated_timeout: int = 30000,
+        timestamp: Callable[[], int] | None = None,
+    ) -> None:
         """
         Args:
             ydoc: The [Doc][pycrdt.Doc] to associate the awareness with.
             outdated_timeout: The timeout (in milliseconds) to consider a client gone.
+            timestamp: A function that returns a timestamp as an integer number of milli-seconds,
+                the system time if not provided.
         """
-        self.client_id = ydoc.client_id
+        self._awareness = _Awareness(ydoc._doc, timestamp)
         self._outdated_timeout = outdated_timeout
-        self._meta = {}
-        self._states = {}
         self._subscriptions = {}
         self._task_group = None
-        self.set_local_state({})
+
+    @property
+    def client_id(self) -> int:
+        """The local client ID."""
+        return self._awareness.client_id
 
     @property
     def meta(self) -> dict[int, dict[str, Any]]:
-        """The clients' metadata."""
-        return self._meta
+        """The clients' metadata (`clock` and `lastUpdated`)."""
+        return self._awareness.meta
 
     @property
     def states(self) -> dict[int, dict[str, Any]]:
-        """The client states."""
-        return self._states
-
-    def _emit(
-        self,
-        topic: Literal["change", "update"],
-        added: list[int],
-        updated: list[int],
-        removed: list[int],
-        origin: Any,
-    ):
-        for callback in self._subscriptions.values():
-            callback(topic, ({"added": added, "updated": updated, "removed": removed}, origin))
-
-    def _get_time(self) -> int:
-        return int(time() * 1000)
+        """The client states, excluding removed clients."""
+        return self._awareness.states
 
     async def start(self, *, task_status: TaskStatus[None] = TASK_STATUS_IGNORED) -> None:
         """
@@ -73,23 +72,7 @@ class Awareness:
     async def _start(self) -> None:
         while True:
             await sleep(self._outdated_timeout / 1000 / 10)
-            now = self._get_time()
-            if (
-                self.get_local_state() is not None
-                and self._outdated_timeout / 2 <= now - self._meta[self.client_id]["lastUpdated"]
-            ):
-                # renew local clock
-                self.set_local_state(self.get_local_state())
-            remove: list[int] = []
-            for client_id, meta in self._meta.items():
-                if (
-                    client_id != self.client_id
-                    and self._outdated_timeout <= now - meta["lastUpdated"]
-                    and client_id in self._states
-                ):
-                    remove.append(client_id)
-            if remove:
-                self.remove_awareness_states(remove, "timeout")
+            self.remove_outdated_states()
 
     async def stop(self) -> None:
         """
@@ -105,58 +88,31 @@ class Awareness:
         Returns:
             The local state, if any.
         """
-        return self._states.get(self.client_id)
+        return self._awareness.get_local_state()
 
-    def set_local_state(self, state: dict[str, Any] | None) -> None:
+    def set_local_state(self, state: dict[str, Any] | None, origin: Any = "local") -> None:
         """
         Updates the local state and meta, and sends the changes to subscribers.
 
         Args:
-            state: The new local state, if any.
+            state: The new local state, or None to mark the local client as disconnected.
+            origin: The origin of the update.
         """
-        client_id = self.client_id
-        curr_local_meta = self._meta.get(client_id)
-        clock = 0 if curr_local_meta is None else curr_local_meta["clock"] + 1
-        prev_state = self._states.get(client_id)
-        if prev_state is not None:
-            prev_state = copy.deepcopy(prev_state)
-        if state is None:
-            if client_id in self._states:
-                del self._states[client_id]
-        else:
-            self._states[client_id] = state
-        timestamp = self._get_time()
-        self._meta[client_id] = {"clock": clock, "lastUpdated": timestamp}
-        added = []
-        updated = []
-        filtered_updated = []
-        removed = []
-        if state is None:
-            removed.append(client_id)
-        elif prev_state is None:
-            if state is not None:
-                added.append(client_id)
-        else:
-            updated.append(client_id)
-            if prev_state != state:
-                filtered_updated.append(client_id)
-        if added or filtered_updated or removed:
-            self._emit("change", added, filtered_updated, removed, "local")
-        self._emit("update", added, updated, removed, "local")
-
-    def set_local_state_field(self, field: str, value: Any) -> None:
+        self._awareness.set_local_state(state, origin)
+
+    def set_local_state_field(self, field: str, value: Any, origin: Any = "local") -> None:
         """
         Sets a local state field.
 
         Args:
             field: The field of the local state to set.
             value: The value associated with the field.
+            origin: The origin of the update.
         """
         state = self.get_local_state()
         if state is not None:
-            state = copy.deepcopy(state)
             state[field] = value
-            self.set_local_state(state)
+            self.set_local_state(state, origin)
 
     def remove_awareness_states(self, client_ids: list[int], origin: Any) -> None:
         """
@@ -166,40 +122,34 @@ class Awareness:
             client_ids: The list of client IDs for which to remove the awareness states.
             origin: The origin of the update.
         """
-        removed = []
-        for client_id in client_ids:
-            if client_id in self._states:
-                del self._states[client_id]
-                if client_id == self.client_id:
-                    cur_meta = self._meta[client_id]
-                    self._meta[client_id] = {
-                        "clock": cur_meta["clock"] + 1,
-                        "lastUpdated": self._get_time(),
-                    }
-                removed.append(client_id)
-        if removed:
-            self._emit("change", [], [], removed, origin)
-            self._emit("update", [], [], removed, origin)
-
-    def encode_awareness_update(self, client_ids: list[int]) -> bytes:
+        self._awareness.remove_states(client_ids, origin)
+
+    def remove_outdated_states(self, origin: Any = "timeout") -> list[int]:
+        """
+        Renews the local state if it was not updated for half the outdated timeout,
+        and removes the states of the remote clients that were not updated for the
+        outdated timeout.
+
+        Args:
+            origin: The origin of the update.
+
+        Returns:
+            The IDs of the removed clients.
+        """
+        return self._awareness.remove_outdated_states(self._outdated_timeout, origin)
+
+    def encode_awareness_update(self, client_ids: list[int] | None = None) -> bytes:
         """
         Creates an encoded awareness update of the clients given by their IDs.
 
         Args:
-            client_ids: The list of client IDs for which to create an update.
+            client_ids: The list of client IDs for which to create an update,
+                all the clients that are not removed if not provided.
 
         Returns:
             The encoded awareness update.
         """
-        encoder = Encoder()
-        encoder.write_var_uint(len(client_ids))
-        for client_id in client_ids:
-            state = self._states.get(client_id)
-            clock = cast(int, self._meta.get(client_id, {}).get("clock"))
-            encoder.write_var_uint(client_id)
-            encoder.write_var_uint(clock)
-            encoder.write_var_string(json.dumps(state, separators=(",", ":")))
-        return encoder.to_bytes()
+        return self._awareness.encode_update(client_ids)
 
     def apply_awareness_update(self, update: bytes, origin: Any) -> None:
         """
@@ -209,65 +159,32 @@ class Awareness:
             update: The binary update.
             origin: The origin of the update.
         """
-        decoder = Decoder(update)
-        timestamp = self._get_time()
-        added = []
-        updated = []
-        filtered_updated = []
-        removed = []
-        length = decoder.read_var_uint()
-        for _ in range(length):
-            client_id = decoder.read_var_uint()
-            clock = decoder.read_var_uint()
-            state_str = decoder.read_var_string()
-            state = None if not state_str else json.loads(state_str)
-            client_meta = self._meta.get(client_id)
-            prev_state = self._states.get(client_id)
-            curr_clock = 0 if client_meta is None else client_meta["clock"]
-            if curr_clock < clock or (
-                curr_clock == clock and state is None and client_id in self._states
-            ):
-                if state is None:
-                    # Never let a remote client remove this local state.
-                    if client_id == self.client_id and self.get_local_state() is not None:
-                        # Remote client removed the local state. Do not remove state.
-                        # Broadcast a message indicating that this client still exists by increasing
-                        # the clock.
-                        clock += 1
-                    else:
-                        if client_id in self._states:
-                            del self._states[client_id]
-                else:
-                    self._states[client_id] = state
-                self._meta[client_id] = {
-                    "clock": clock,
-                    "lastUpdated": timestamp,
-                }
-                if client_meta is None and state is not None:
-                    added.append(client_id)
-                elif client_meta is not None and state is None:
-                    removed.append(client_id)
-                elif state is not None:
-                    if state != prev_state:
-                        filtered_updated.append(client_id)
-                    updated.append(client_id)
-        if added or filtered_updated or removed:
-            self._emit("change", added, filtered_updated, removed, origin)
-        if added or updated or removed:
-            self._emit("update", added, updated, removed, origin)
+        self._awareness.apply_update(update, origin)
 
     def observe(self, callback: Callable[[str, tuple[dict[str, Any], Any]], None]) -> str:
         """
         Registers the given callback to awareness changes.
 
+        The callback is called with the `"change"` topic when client states change, and with
+        the `"update"` topic when client states are updated, including when their clock is
+        renewed without any change.
+
         Args:
-            callback: The callback to call with the awareness changes.
+            callback: The callback to call with the topic, and the `added`, `updated` and
+                `removed` client IDs and the origin of the changes.
 
         Returns:
             The subscription ID that can be used to unobserve.
         """
         id = str(uuid4())
-        self._subscriptions[id] = callback
+        self._subscriptions[id] = (
+            self._awareness.observe_change(
+                lambda event, origin: callback("change", (event, origin))
+            ),
+            self._awareness.observe_update(
+                lambda event, origin: callback("update", (event, origin))
+            ),
+        )
         return id
 
     def unobserve(self, id: str) -> None:
@@ -277,7 +194,8 @@ class Awareness:
         Args:
             id: The subscription ID to unregister.
         """
-        del self._subscriptions[id]
+        for subscription in self._subscriptions.pop(id):
+            subscription.drop()
 
 
 def is_awareness_disconnect_message(message: bytes) -> bool:
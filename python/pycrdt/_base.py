@@ -19,16 +19,16 @@ from typing import (
     overload,
 )
 
-import anyio
 from anyio import BrokenResourceError, create_memory_object_stream
 from anyio.abc import TaskGroup
 from anyio.streams.memory import MemoryObjectReceiveStream, MemoryObjectSendStream
 
+from ._exceptions import TransactionError
 from ._pycrdt import Doc as _Doc
 from ._pycrdt import Subscription
 from ._pycrdt import Transaction as _Transaction
 from ._sticky_index import Assoc, StickyIndex
-from ._transaction import ReadTransaction, Transaction
+from ._transaction import PendingChanges, ReadTransaction, Transaction
 
 if TYPE_CHECKING:
     from ._doc import Doc
@@ -40,21 +40,27 @@ event_types: dict[Any, type[BaseEvent]] = {}
 
 def forbid_read_transaction(txn: Transaction):
     if isinstance(txn, ReadTransaction):
-        raise RuntimeError("Read-only transaction cannot be used to modify document structure")
+        raise TransactionError("Read-only transaction cannot be used to modify document structure")
+    if txn._aborted:
+        raise TransactionError("Aborted transaction cannot be used to modify document structure")
+
+
+def in_aborted_transaction(txn: _Transaction) -> bool:
+    # the changes of an aborted transaction were rolled back, so they are not observed
+    # (by any wrapper of the document)
+    return txn.aborting()
 
 
 class BaseDoc:
     _doc: _Doc
+    _parent: Doc | None
     _twin_doc: BaseDoc | None
-    _txn: Transaction | None
-    _exceptions: list[Exception]
-    _txn_lock: threading.Lock
-    _txn_async_lock: anyio.Lock
+    _local: threading.local
     _allow_multithreading: bool
     _Model: Any
     _subscriptions: list[Subscription]
-    _origins: dict[int, Any]
-    _task_group: TaskGroup | None
+    _validators: list[Callable[[PendingChanges], None]]
+    _origins: dict[bytes, Any]
 
     def __init__(
         self,
@@ -62,6 +68,9 @@ class BaseDoc:
         client_id: int | None = None,
         skip_gc: bool | None = None,
         guid: str | None = None,
+        offset_kind: Literal["bytes", "utf16"] | None = None,
+        should_load: bool | None = None,
+        auto_load: bool | None = None,
         doc: _Doc | None = None,
         Model=None,
         allow_multithreading: bool = False,
@@ -69,17 +78,55 @@ class BaseDoc:
     ) -> None:
         super().__init__(**data)
         if doc is None:
-            doc = _Doc(client_id, skip_gc, guid)
+            doc = _Doc(client_id, skip_gc, guid, offset_kind, should_load, auto_load)
         self._doc = doc
-        self._txn = None
-        self._exceptions = []
-        self._txn_lock = threading.Lock()
-        self._txn_async_lock = anyio.Lock()
+        self._parent = None
+        self._local = threading.local()
         self._Model = Model
         self._subscriptions = []
+        self._validators = []
         self._origins = {}
         self._allow_multithreading = allow_multithreading
-        self._task_group = None
+
+    @property
+    def _txn(self) -> Transaction | None:
+        # the current transaction is per thread, so that read-only transactions
+        # can be held concurrently in different threads
+        return getattr(self._local, "txn", None)
+
+    @_txn.setter
+    def _txn(self, value: Transaction | None) -> None:
+        self._local.txn = value
+
+    # the state of the current transaction is per thread too
+
+    @property
+    def _exceptions(self) -> list[Exception]:
+        if not hasattr(self._local, "exceptions"):
+            self._local.exceptions = []
+        return self._local.exceptions
+
+    @property
+    def _deferred(self) -> list[tuple[Callable[[Transaction], None], Any]]:
+        if not hasattr(self._local, "deferred"):
+            self._local.deferred = []
+        return self._local.deferred
+
+    @property
+    def _running_deferred(self) -> bool:
+        return getattr(self._local, "running_deferred", False)
+
+    @_running_deferred.setter
+    def _running_deferred(self, value: bool) -> None:
+        self._local.running_deferred = value
+
+    @property
+    def _task_group(self) -> TaskGroup | None:
+        return getattr(self._local, "task_group", None)
+
+    @_task_group.setter
+    def _task_group(self, value: TaskGroup | None) -> None:
+        self._local.task_group = value
 
 
 class BaseType(ABC):
@@ -123,6 +170,15 @@ class BaseType(ABC):
     @abstractmethod
     def _init(self, value: Any | None) -> None: ...
 
+    def _in_doc(self, doc: Doc) -> BaseType | None:
+        # the shared type with the same ID in another document (e.g. restored at a snapshot),
+        # or None if it doesn't exist there
+        with doc.read_transaction() as txn:
+            integrated = doc._doc.shared_type(txn._txn, self.integrated)
+        if integrated is None:
+            return None
+        return cast(Type[BaseType], base_types[type(integrated)])(_integrated=integrated, _doc=doc)
+
     def _forbid_read_transaction(self, txn: Transaction):
         forbid_read_transaction(txn)
 
@@ -145,7 +201,9 @@ class BaseType(ABC):
             if isinstance(obj, k):
                 if issubclass(v, BaseDoc):
                     # create a BaseDoc
-                    return v(doc=obj)
+                    subdoc = v(doc=obj)
+                    subdoc._parent = self.doc
+                    return subdoc
                 # create a BaseType
                 return v(_doc=self.doc, _integrated=obj)
         # that was a primitive value, just return it
@@ -304,6 +362,8 @@ def observe_callback(
     param_nb: int,
     event: Any,
 ):
+    if in_aborted_transaction(event.transaction):
+        return
     with doc._read_transaction(event.transaction) as txn:
         _event = event_types[type(event)](event, doc)
         _event.transaction = txn
@@ -320,6 +380,8 @@ def observe_deep_callback(
     param_nb: int,
     events: list[Any],
 ):
+    if in_aborted_transaction(events[0].transaction):
+        return
     with doc._read_transaction(events[0].transaction) as txn:
         for idx, event in enumerate(events):
             _event = event_types[type(event)](event, doc)
@@ -365,12 +427,23 @@ def process_event(value: Any, doc: Doc) -> Any:
             if val_type is _Doc:
                 doc_type: type[BaseDoc] = cast(Type[BaseDoc], base_types[val_type])
                 value = doc_type(doc=value)
+                value._parent = doc
             else:
                 base_type = cast(Type[BaseType], base_types[val_type])
                 value = base_type(_integrated=value, _doc=doc)
     return value
 
 
+def detached_event(event: Any, doc: Doc) -> BaseEvent:
+    # an event that is read after its transaction, which is not available anymore
+    event_type = event_types[type(event)]
+    detached = event_type.__new__(event_type)
+    for slot in event_type.__slots__:
+        value = None if slot == "transaction" else process_event(getattr(event, slot), doc)
+        setattr(detached, slot, value)
+    return detached
+
+
 class Typed:
     _: Any
 
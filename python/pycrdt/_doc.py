@@ -24,18 +24,31 @@ from anyio.streams.memory import MemoryObjectReceiveStream, MemoryObjectSendStre
 if sys.version_info >= (3, 11):
     from typing import Never
 else:  # pragma: no cover
+    from exceptiongroup import ExceptionGroup
     from typing_extensions import Never
 
-from ._base import BaseDoc, BaseType, Typed, base_types, forbid_read_transaction
+from ._base import (
+    BaseDoc,
+    BaseEvent,
+    BaseType,
+    Typed,
+    base_types,
+    detached_event,
+    forbid_read_transaction,
+)
+from ._exceptions import TransactionError
 from ._pycrdt import Doc as _Doc
-from ._pycrdt import SubdocsEvent, Subscription, TransactionEvent
+from ._pycrdt import IdSet
+from ._pycrdt import SubdocsEvent as _SubdocsEvent
+from ._pycrdt import Subscription, TransactionEvent
 from ._pycrdt import Transaction as _Transaction
+from ._revert import revert_type
 from ._snapshot import Snapshot
-from ._transaction import NewTransaction, ReadTransaction, Transaction
+from ._transaction import NewTransaction, PendingChanges, ReadTransaction, Transaction
 
 T = TypeVar("T", bound=BaseType)
 TransactionOrSubdocsEvent = TypeVar(
-    "TransactionOrSubdocsEvent", bound=TransactionEvent | SubdocsEvent
+    "TransactionOrSubdocsEvent", bound="TransactionEvent | SubdocsEvent"
 )
 
 
@@ -56,6 +69,9 @@ class Doc(BaseDoc, Generic[T]):
         client_id: int | None = None,
         skip_gc: bool | None = None,
         guid: str | None = None,
+        offset_kind: Literal["bytes", "utf16"] | None = None,
+        should_load: bool | None = None,
+        auto_load: bool | None = None,
         doc: _Doc | None = None,
         Model=None,
         allow_multithreading: bool = False,
@@ -67,12 +83,21 @@ class Doc(BaseDoc, Generic[T]):
             skip_gc: Whether to skip garbage collection on deleted collections
                 on transaction commit.
             guid: An optional globally unique identifier for the document.
+            offset_kind: How the indices and lengths of texts are counted, either in UTF-8
+                bytes (`"bytes"`, the default) or in UTF-16 code units (`"utf16"`, like Yjs).
+            should_load: Whether the document should be loaded, when used as a subdocument
+                (default is `True`).
+            auto_load: Whether peers should load the document automatically, when used as a
+                subdocument (default is `False`).
             allow_multithreading: Whether to allow the document to be used in different threads.
         """
         super().__init__(
             client_id=client_id,
             skip_gc=skip_gc,
             guid=guid,
+            offset_kind=offset_kind,
+            should_load=should_load,
+            auto_load=auto_load,
             doc=doc,
             Model=Model,
             allow_multithreading=allow_multithreading,
@@ -91,12 +116,95 @@ class Doc(BaseDoc, Generic[T]):
         """The GUID of the document."""
         return self._doc.guid()
 
+    @property
+    def offset_kind(self) -> Literal["bytes", "utf16"]:
+        """
+        How the indices and lengths of [Text][pycrdt.Text] and [XmlText][pycrdt.XmlText]
+        are counted: in UTF-8 bytes (`"bytes"`) or in UTF-16 code units (`"utf16"`).
+        """
+        return self._doc.offset_kind()
+
     @property
     def client_id(self) -> int:
         """The document client ID."""
         return self._doc.client_id()
 
-    def transaction(self, origin: Any = None) -> Transaction:
+    @property
+    def should_load(self) -> bool:
+        """Whether the document was requested to be loaded, when used as a subdocument."""
+        return self._doc.should_load()
+
+    @property
+    def auto_load(self) -> bool:
+        """Whether peers should load the document automatically, when used as a subdocument."""
+        return self._doc.auto_load()
+
+    @property
+    def parent_doc(self) -> Doc | None:
+        """The document this document is a subdocument of, if any."""
+        if self._parent is None:
+            parent = self._doc.parent_doc()
+            if parent is not None:
+                self._parent = Doc(doc=parent)
+        return self._parent
+
+    def load(self) -> None:
+        """
+        Requests the loading of this subdocument: its `should_load` flag is set, and the
+        parent document's [SubdocsEvent][pycrdt.SubdocsEvent] reports it as loaded.
+        If a transaction is in progress in the parent document, it is used.
+
+        Raises:
+            ValueError: The document is not a subdocument.
+            TransactionError: A transaction is in progress in the parent document,
+                but it is not known.
+        """
+        parent = self.parent_doc
+        if parent is None:
+            self._doc.load()
+            return
+        with parent.transaction() as txn:
+            self._doc.load(txn._txn)
+
+    def destroy(self) -> None:
+        """
+        Destroys this subdocument: it is replaced in its parent document with a new document
+        with the same GUID that is not loaded, and the parent document's
+        [SubdocsEvent][pycrdt.SubdocsEvent] reports it as removed and added back.
+        If a transaction is in progress in the parent document, it is used.
+
+        Raises:
+            TransactionError: A transaction is in progress in this document.
+        """
+        parent = self.parent_doc
+        if parent is None:
+            self._doc.destroy()
+            return
+        with parent.transaction() as txn:
+            self._doc.destroy(txn._txn)
+
+    def subdocs(self) -> list[Doc]:
+        """
+        Returns:
+            The subdocuments of this document.
+        """
+        with self.transaction() as txn:
+            return [self._subdoc(doc) for doc in self._doc.subdocs(txn._txn)]
+
+    def subdoc_guids(self) -> set[str]:
+        """
+        Returns:
+            The GUIDs of the subdocuments of this document.
+        """
+        with self.transaction() as txn:
+            return set(self._doc.subdoc_guids(txn._txn))
+
+    def _subdoc(self, doc: _Doc) -> Doc:
+        subdoc: Doc = Doc(doc=doc)
+        subdoc._parent = self
+        return subdoc
+
+    def transaction(self, origin: Any = None, *, rollback_on_error: bool = False) -> Transaction:
         """
         Creates a new transaction or gets the current one, if any.
         If an origin is passed and there is already an ongoing transaction,
@@ -110,10 +218,15 @@ class Doc(BaseDoc, Generic[T]):
         ```
 
         Args:
-            origin: An optional origin to set on this transaction.
+            origin: An optional origin to set on this transaction. It can be any hashable
+                object, which observers get back as is. Byte strings are used natively as
+                transaction origins, so that they can be recognized across processes.
+            rollback_on_error: Whether to [abort][pycrdt.Transaction.abort] the transaction
+                if an exception is raised in its context manager. It only applies to a new
+                transaction, not to the current one.
 
         Raises:
-            RuntimeError: Nested transactions must have same origin as root transaction.
+            TransactionError: Nested transactions must have same origin as root transaction.
 
         Returns:
             A new transaction or the current one.
@@ -121,13 +234,19 @@ class Doc(BaseDoc, Generic[T]):
         if self._txn is not None:
             if origin is not None:
                 if origin != self._txn.origin:
-                    raise RuntimeError(
+                    raise TransactionError(
                         "Nested transactions must have same origin as root transaction"
                     )
             return self._txn
-        return Transaction(self, origin=origin)
+        return Transaction(self, origin=origin, rollback_on_error=rollback_on_error)
 
-    def new_transaction(self, origin: Any = None, timeout: float | None = None) -> NewTransaction:
+    def new_transaction(
+        self,
+        origin: Any = None,
+        timeout: float | None = None,
+        *,
+        rollback_on_error: bool = False,
+    ) -> NewTransaction:
         """
         Creates a new transaction.
         Unlike [transaction()][pycrdt.Doc.transaction], this method will not reuse an ongoing
@@ -156,15 +275,96 @@ class Doc(BaseDoc, Generic[T]):
         Args:
             origin: An optional origin to set on this transaction.
             timeout: An optional timeout (in seconds) to acquire a new transaction.
+            rollback_on_error: Whether to [abort][pycrdt.Transaction.abort] the transaction
+                if an exception is raised in its context manager.
 
         Raises:
-            RuntimeError: Already in a transaction.
+            TransactionError: Already in a transaction.
             TimeoutError: Could not acquire transaction.
 
         Returns:
             A new transaction.
         """
-        return NewTransaction(self, origin=origin, timeout=timeout)
+        return NewTransaction(
+            self, origin=origin, timeout=timeout, rollback_on_error=rollback_on_error
+        )
+
+    def read_transaction(self) -> Transaction:
+        """
+        Creates a new read-only transaction or gets the current one, if any.
+        Unlike read-write transactions, several read-only transactions can be held at the
+        same time, e.g. in different threads of a [Doc][pycrdt.Doc.__init__] created with
+        `allow_multithreading=True`. All the methods that only read the document can be
+        used in a read-only transaction, while the ones that modify it raise a
+        `TransactionError`.
+
+        This method must be used with a context manager:
+
+        ```py
+        with doc.read_transaction():
+            ...
+        ```
+
+        Raises:
+            TransactionError: A read-write transaction is ongoing.
+
+        Returns:
+            A new read-only transaction or the current one.
+        """
+        if self._txn is not None:
+            return self._txn
+        return ReadTransaction(self)
+
+    def defer_transaction(
+        self, callback: Callable[[Transaction], None], origin: Any = None
+    ) -> None:
+        """
+        Schedules a callback to be called in a follow-up transaction, immediately after the
+        current transaction completes, or right away if there is no current transaction.
+        Observer callbacks cannot modify the document, but they can use this method to
+        maintain derived data (indexes, counters...) reactively:
+
+        ```py
+        def callback(event):
+            if event.transaction.origin != "count":
+                doc.defer_transaction(lambda txn: count.set(len(array)), origin="count")
+
+        array.observe(callback)
+        ```
+
+        Follow-up transactions are run in the order they were scheduled, and their observers
+        can schedule other follow-up transactions (which is why the callback above ignores the
+        changes of its own origin). They are not run if committing the current transaction fails,
+        e.g. if it is rejected by a [validator][pycrdt.Doc.add_validator]. The errors raised in
+        the follow-up transactions don't prevent the others from running, and are raised
+        together once they have all run, in an `ExceptionGroup`.
+
+        Args:
+            callback: The callback to call with the follow-up transaction.
+            origin: An optional origin to set on the follow-up transaction.
+        """
+        self._deferred.append((callback, origin))
+        if self._txn is None:
+            self._run_deferred()
+
+    def _run_deferred(self) -> None:
+        # the follow-up transactions scheduled by follow-up transactions are run by the same loop
+        if self._running_deferred:
+            return
+        self._running_deferred = True
+        exceptions = []
+        try:
+            while self._deferred:
+                callback, origin = self._deferred.pop(0)
+                try:
+                    with self.transaction(origin=origin) as txn:
+                        callback(txn)
+                except Exception as exception:
+                    exceptions.append(exception)
+        finally:
+            self._running_deferred = False
+        if exceptions:
+            raise ExceptionGroup("Deferred callback error", exceptions)
 
     def _read_transaction(self, _txn: _Transaction) -> ReadTransaction:
         return ReadTransaction(self, _txn)
@@ -192,14 +392,118 @@ class Doc(BaseDoc, Generic[T]):
             assert txn._txn is not None
             return self._doc.get_update(txn._txn, state)
 
+    def get_update_v2(self, state: bytes | None = None) -> bytes:
+        """
+        Args:
+            state: The optional document state from which to get the update.
+
+        Returns:
+            The v2-encoded update from the given document state (if any), or from the document
+                creation.
+        """
+        if state is None:
+            state = b"\x00"
+        with self.transaction() as txn:
+            assert txn._txn is not None
+            return self._doc.get_update_v2(txn._txn, state)
+
+    def get_changes(
+        self, state: bytes, delete_set: IdSet | None = None
+    ) -> dict[str, list[BaseEvent]]:
+        """
+        Gets the changes made since a document state, e.g. to summarize them for a peer
+        that was offline. They are the events that the deep observers of the root types of
+        a document at that state would get, when applying the [update][pycrdt.Doc.get_update]
+        since that state:
+        ```py
+        state = doc.get_state()
+        text += ", World!"
+        events = doc.get_changes(state)
+        assert events["text"][0].delta == [{"retain": 5}, {"insert": ", World!"}]
+        ```
+        The document must have been created with `skip_gc=True`. The events have no
+        transaction, and their shared types belong to a copy of the document.
+
+        Args:
+            state: The document state since which to get the changes.
+            delete_set: The content that was already deleted at that state (none by default),
+                e.g. the `delete_set` of a [Snapshot][pycrdt.Snapshot] taken at that time.
+
+        Returns:
+            A `dict` of the names of the changed root types to their events.
+        """
+        with self.transaction() as txn:
+            assert txn._txn is not None
+            copy, changes = self._doc.get_changes(txn._txn, state, delete_set)
+        return self._detached_changes(copy, changes)
+
+    def _detached_changes(
+        self, copy: _Doc, changes: dict[str, list[Any]]
+    ) -> dict[str, list[BaseEvent]]:
+        # the events replayed on a copy of the document, whose shared types belong to it
+        doc: Doc = Doc(doc=copy)
+        return {
+            name: [detached_event(event, doc) for event in events]
+            for name, events in changes.items()
+        }
+
     def apply_update(self, update: bytes) -> None:
         """
         Args:
             update: The update to apply to the document.
         """
+        self._apply_update(update, v2=False)
+
+    def apply_update_v2(self, update: bytes) -> None:
+        """
+        Args:
+            update: The v2-encoded update to apply to the document.
+        """
+        self._apply_update(update, v2=True)
+
+    @property
+    def has_pending_structs(self) -> bool:
+        """
+        Whether some applied updates could not be integrated yet, because they depend on
+        updates that have not been applied.
+        """
+        with self.transaction() as txn:
+            assert txn._txn is not None
+            return self._doc.has_pending_structs(txn._txn)
+
+    @property
+    def has_pending_delete_set(self) -> bool:
+        """
+        Whether some deletions could not be integrated yet, because they refer to
+        content that has not been received.
+        """
+        with self.transaction() as txn:
+            assert txn._txn is not None
+            return self._doc.has_pending_delete_set(txn._txn)
+
+    def get_missing_state(self) -> bytes | None:
+        """
+        Returns:
+            The state of the missing updates that pending structs depend on, if any.
+                Peers can be asked for the missing updates with it.
+        """
+        with self.transaction() as txn:
+            assert txn._txn is not None
+            return self._doc.get_missing_state(txn._txn)
+
+    def get_pending_update(self) -> bytes | None:
+        """
+        Returns:
+            The update made of the pending structs and of the pending delete set, if any.
+        """
+        with self.transaction() as txn:
+            assert txn._txn is not None
+            return self._doc.get_pending_update(txn._txn)
+
+    def _apply_update(self, update: bytes, v2: bool) -> None:
         if self._Model is not None:
             twin_doc = cast(Doc, self._twin_doc)
-            twin_doc.apply_update(update)
+            twin_doc._apply_update(update, v2)
             d = {k: twin_doc[k].to_py() for k in self._Model.model_fields}
             try:
                 self._Model(**d)
@@ -209,7 +513,10 @@ class Doc(BaseDoc, Generic[T]):
         with self.transaction() as txn:
             forbid_read_transaction(txn)
             assert txn._txn is not None
-            self._doc.apply_update(txn._txn, update)
+            if v2:
+                self._doc.apply_update_v2(txn._txn, update)
+            else:
+                self._doc.apply_update(txn._txn, update)
 
     @staticmethod
     def from_snapshot(snapshot: "Snapshot", doc: "Doc") -> "Doc":
@@ -224,6 +531,98 @@ class Doc(BaseDoc, Generic[T]):
         new_doc = _Doc.from_snapshot(snapshot._snapshot, doc._doc)
         return Doc(doc=new_doc)
 
+    def diff_snapshots(
+        self, prev_snapshot: Snapshot, snapshot: Snapshot | None = None
+    ) -> list[tuple[list[int | str], Any]]:
+        """
+        Compares the [Array][pycrdt.Array]s and [Map][pycrdt.Map]s of the document
+        between two snapshots, including nested ones:
+        ```py
+        snapshot = Snapshot.from_doc(doc)
+        array.append(1)
+        assert doc.diff_snapshots(snapshot) == [(["array"], [{"insert": [1.0]}])]
+        ```
+        The document must have been created with `skip_gc=True`.
+
+        Args:
+            prev_snapshot: The [Snapshot][pycrdt.Snapshot] of the previous version.
+            snapshot: The [Snapshot][pycrdt.Snapshot] of the new version
+                (default is the current version).
+
+        Returns:
+            A list of the changed arrays and maps since the previous version, as tuples of the
+                path to the shared type (starting with the name of its root type) and its
+                changes: the `delta` of an [ArrayEvent][pycrdt.ArrayEvent] for an array,
+                or the `keys` of a [MapEvent][pycrdt.MapEvent] for a map.
+
+        Raises:
+            ValueError: The previous snapshot must precede the snapshot.
+        """
+        if snapshot is None:
+            snapshot = Snapshot.from_doc(self)
+        return snapshot._diff(self, prev_snapshot)
+
+    def revert(self, snapshot: Snapshot, *, origin: Any = None, apply: bool = True) -> bytes:
+        """
+        Reverts the document to its content at a snapshot, by making the changes that
+        restore every root type to the content it had at that time. The history is kept,
+        and the changes are a regular update that peers can apply:
+        ```py
+        snapshot = Snapshot.from_doc(doc)
+        text += ", World!"
+        update = doc.revert(snapshot)
+        ```
+        The document must have been created with `skip_gc=True`.
+
+        Args:
+            snapshot: The [Snapshot][pycrdt.Snapshot] to revert to.
+            origin: The origin of the transaction in which the changes are made.
+            apply: Whether to apply the changes to the document, or to only return
+                the corresponding update (default is `True`).
+
+        Returns:
+            The update of the changes.
+
+        Raises:
+            TransactionError: Cannot revert in an ongoing transaction.
+            ValueError: Document must be created with skip_gc=True to be read at a snapshot.
+        """
+        if self._txn is not None:
+            raise TransactionError("Cannot revert in an ongoing transaction")
+        target = Doc(doc=snapshot._restore(self))
+        if apply:
+            doc = self
+        else:
+            doc = Doc(offset_kind=self.offset_kind)
+            doc.apply_update(self.get_update())
+        targets = {}
+        target_roots = target._read_roots(infer_types=True)
+        for key, value in self._read_roots(infer_types=True).items():
+            if value is None:
+                # the root type was not accessed and is empty, its type is the one
+                # it had at the snapshot, if it had any content then
+                value = target_roots.get(key)
+                if value is None:
+                    continue
+            if value._in_doc(target) is None:
+                # the root type didn't exist at the snapshot, it will be emptied
+                targets[key] = Doc().get(key, type=type(value))
+            else:
+                targets[key] = target.get(key, type=type(value))
+        with doc.transaction(origin=origin) as txn:
+            for key, value in targets.items():
+                revert_type(doc.get(key, type=type(value)), value)
+            assert txn._txn is not None
+            return txn._txn.pending_update()
+
+    def _restore_roots(self, before: _Doc, names: set[str]) -> None:
+        # restore the content of root types in the current transaction, from the document
+        # as it was before the transaction
+        target = Doc(doc=before)
+        for key, value in self._read_roots(infer_types=True).items():
+            if key in names and value is not None:
+                revert_type(value, target.get(key, type=type(value)))
+
     def __setitem__(self, key: str, value: T) -> None:
         """
         Sets a document root type:
@@ -307,6 +706,11 @@ class Doc(BaseDoc, Generic[T]):
 
     @property
     def _roots(self) -> dict[str, T]:
+        return self._read_roots()
+
+    def _read_roots(self, infer_types: bool = False) -> dict[str, T]:
+        # the type of a root type that was not accessed is None,
+        # unless it is inferred from its content
         with self.transaction() as txn:
             assert txn._txn is not None
             return {
@@ -315,13 +719,15 @@ class Doc(BaseDoc, Generic[T]):
                     if val is None
                     else cast(Type[T], base_types[type(val)])(_integrated=val, _doc=self)
                 )
-                for key, val in self._doc.roots(txn._txn).items()
+                for key, val in self._doc.roots(txn._txn, infer_types).items()
             }
 
     def observe(
         self,
         callback: Callable[[TransactionEvent], None]
         | Callable[[TransactionEvent], Awaitable[None]],
+        *,
+        track_changes: bool = False,
     ) -> Subscription:
         """
         Subscribes a callback to be called with the document change event.
@@ -329,6 +735,10 @@ class Doc(BaseDoc, Generic[T]):
         Args:
             callback: The callback to call with the [TransactionEvent][pycrdt.TransactionEvent].
                 If the callback is async, async transactions must be used.
+            track_changes: Whether the events should report the shared types that were changed,
+                in their `changed_types` and `changed_parent_types` attributes. This requires
+                observing all the root types deeply, so the root types received from remote
+                documents get the type inferred from their content.
 
         Returns:
             The subscription that can be used to [unobserve()][pycrdt.Doc.unobserve].
@@ -337,17 +747,79 @@ class Doc(BaseDoc, Generic[T]):
             cb = self._async_callback_to_sync(callback)
         else:
             cb = partial(observe_callback, cast(Callable[[TransactionEvent], None], callback), self)
-        subscription = self._doc.observe(cb)
+        subscription = self._doc.observe(cb, self._origins, track_changes)
         self._subscriptions.append(subscription)
         return subscription
 
+    def observe_update(
+        self, callback: Callable[[bytes, Any], None], *, v2: bool = False
+    ) -> Subscription:
+        """
+        Subscribes a callback to be called with the update of each transaction that changed
+        the document, and the origin of the transaction. Unlike [observe()][pycrdt.Doc.observe],
+        no [TransactionEvent][pycrdt.TransactionEvent] is created.
+
+        Args:
+            callback: The callback to call with the encoded update and the transaction origin.
+            v2: Whether the update should be v2-encoded.
+
+        Returns:
+            The subscription that can be used to [unobserve()][pycrdt.Doc.unobserve].
+        """
+        cb = partial(observe_callback, callback, self)
+        if v2:
+            subscription = self._doc.observe_update_v2(cb, self._origins)
+        else:
+            subscription = self._doc.observe_update(cb, self._origins)
+        self._subscriptions.append(subscription)
+        return subscription
+
+    def observe_after_transaction(self, callback: Callable[[Any], None]) -> Subscription:
+        """
+        Subscribes a callback to be called at the end of each transaction, including those that
+        did not change the document, with the origin of the transaction.
+
+        Args:
+            callback: The callback to call with the transaction origin.
+
+        Returns:
+            The subscription that can be used to [unobserve()][pycrdt.Doc.unobserve].
+        """
+        subscription = self._doc.observe_after_transaction(
+            partial(observe_callback, callback, self), self._origins
+        )
+        self._subscriptions.append(subscription)
+        return subscription
+
+    def add_validator(self, callback: Callable[[PendingChanges], None]) -> None:
+        """
+        Adds a callback to be called with the pending changes of each transaction that changed
+        the document, just before it is committed. The callback can reject the transaction by
+        raising an exception, in which case the transaction is rolled back (see
+        [Transaction.abort()][pycrdt.Transaction.abort]) and a `TransactionError` is raised
+        when exiting it, from the callback exception.
+
+        Args:
+            callback: The callback to call with the [PendingChanges][pycrdt.PendingChanges].
+        """
+        self._validators.append(callback)
+
+    def remove_validator(self, callback: Callable[[PendingChanges], None]) -> None:
+        """
+        Removes a callback that was added with [add_validator()][pycrdt.Doc.add_validator].
+
+        Args:
+            callback: The callback to remove.
+        """
+        self._validators.remove(callback)
+
     def _async_callback_to_sync(
         self,
         async_callback: Callable[[TransactionOrSubdocsEvent], Coroutine[Any, Any, Never]],
     ) -> Callable[[TransactionOrSubdocsEvent], None]:
         def callback(event: TransactionOrSubdocsEvent) -> None:
             if self._task_group is None:
-                raise RuntimeError("Async callback in non-async transaction")
+                raise TransactionError("Async callback in non-async transaction")
             self._task_group.start_soon(async_callback, event)
 
         return callback
@@ -369,7 +841,9 @@ class Doc(BaseDoc, Generic[T]):
             cb = self._async_callback_to_sync(callback)
         else:
             cb = partial(observe_callback, cast(Callable[[SubdocsEvent], None], callback), self)
-        subscription = self._doc.observe_subdocs(cb)
+        subscription = self._doc.observe_subdocs(
+            lambda event: cb(SubdocsEvent(event, self))  # type: ignore[arg-type]
+        )
         self._subscriptions.append(subscription)
         return subscription
 
@@ -474,6 +948,31 @@ class Doc(BaseDoc, Generic[T]):
             self.unobserve(self._event_subscription[subdocs])
 
 
+class SubdocsEvent:
+    """
+    Subdocuments change event, emitted during the transaction commit phase of the parent
+    document.
+
+    Attributes:
+        added (list[str]): The GUIDs of the added subdocuments.
+        removed (list[str]): The GUIDs of the removed subdocuments.
+        loaded (list[str]): The GUIDs of the subdocuments that were requested to be loaded.
+        added_docs (list[Doc]): The added subdocuments.
+        removed_docs (list[Doc]): The removed subdocuments.
+        loaded_docs (list[Doc]): The subdocuments that were requested to be loaded.
+    """
+
+    __slots__ = "added", "removed", "loaded", "added_docs", "removed_docs", "loaded_docs"
+
+    def __init__(self, event: _SubdocsEvent, doc: Doc):
+        self.added = event.added
+        self.removed = event.removed
+        self.loaded = event.loaded
+        self.added_docs = [doc._subdoc(subdoc) for subdoc in event.added_docs]
+        self.removed_docs = [doc._subdoc(subdoc) for subdoc in event.removed_docs]
+        self.loaded_docs = [doc._subdoc(subdoc) for subdoc in event.loaded_docs]
+
+
 class TypedDoc(Typed):
     """
     A container for a [Doc][pycrdt.Doc.__init__] where root shared values have types associated
@@ -514,12 +1013,12 @@ class TypedDoc(Typed):
 
 
 def observe_callback(
-    callback: Callable[[TransactionEvent], None] | Callable[[SubdocsEvent], None],
+    callback: Callable[..., None],
     doc: Doc,
-    event: Any,
+    *args: Any,
 ) -> None:
     try:
-        callback(event)
+        callback(*args)
     except Exception as exc:
         doc._exceptions.append(exc)
 
@@ -0,0 +1,37 @@
+class CrdtError(Exception):
+    """
+    The base class of all the errors raised by pycrdt when an operation on CRDT data fails.
+    """
+
+
+class DecodeError(CrdtError, ValueError):
+    """
+    Raised when encoded data (an update, a state, a snapshot, a message...) is malformed.
+    """
+
+
+class OffsetError(CrdtError, ValueError):
+    """
+    Raised when an index in a text is not on a character boundary, in the offset kind
+    of its document.
+    """
+
+
+class IntegrationError(CrdtError, RuntimeError):
+    """
+    Raised when decoded data cannot be integrated, for instance when an update cannot
+    be applied to a document.
+    """
+
+
+class TransactionError(CrdtError, RuntimeError):
+    """
+    Raised when a transaction is used in a state that does not allow the operation,
+    for instance when it was already committed, or when a document is already in a transaction.
+    """
+
+
+class ConversionError(CrdtError, TypeError):
+    """
+    Raised when a value cannot be converted from or to a CRDT value.
+    """
@@ -2,6 +2,7 @@ from __future__ import annotations
 
 from typing import (
     TYPE_CHECKING,
+    Any,
     Callable,
     Generic,
     Iterable,
@@ -14,6 +15,7 @@ from ._base import BaseDoc, BaseEvent, BaseType, Typed, base_types, event_types
 from ._pycrdt import Map as _Map
 from ._pycrdt import MapEvent as _MapEvent
 from ._pycrdt import Subscription
+from ._snapshot import Snapshot
 
 if TYPE_CHECKING:
     from ._doc import Doc
@@ -65,6 +67,7 @@ class Map(BaseType, Generic[T]):
             self._forbid_read_transaction(txn)
             if isinstance(value, BaseDoc):
                 # subdoc
+                value._parent = self.doc
                 self.integrated.insert_doc(txn._txn, key, value._doc)
             elif isinstance(value, BaseType):
                 # shared type
@@ -104,15 +107,21 @@ class Map(BaseType, Generic[T]):
         with self.doc.transaction() as txn:
             return self.integrated.to_json(txn._txn)
 
-    def to_py(self) -> dict[str, T] | None:
+    def to_py(self, snapshot: Snapshot | None = None) -> dict[str, T] | None:
         """
         Recursively converts the map's items to Python objects, and
         returns them in a `dict`. If the map was not yet inserted in a document,
         returns `None` if the map was not initialized.
 
+        Args:
+            snapshot: An optional [Snapshot][pycrdt.Snapshot] to get the items as they were at
+                that time, which requires a document created with `skip_gc=True`.
+
         Returns:
             The map recursively converted to Python objects, or `None`.
         """
+        if snapshot is not None:
+            return snapshot._read(self)
         if self._integrated is None:
             py = self._prelim
             if py is None:
@@ -124,6 +133,31 @@ class Map(BaseType, Generic[T]):
                 py[key] = val.to_py()
         return py
 
+    def diff_snapshots(
+        self, prev_snapshot: Snapshot, snapshot: Snapshot | None = None
+    ) -> dict[str, dict[str, Any]]:
+        """
+        Compares the map between two snapshots, e.g. to show the changes between two versions.
+        The document must have been created with `skip_gc=True`.
+
+        Args:
+            prev_snapshot: The [Snapshot][pycrdt.Snapshot] of the previous version.
+            snapshot: The [Snapshot][pycrdt.Snapshot] of the new version
+                (default is the current version).
+
+        Returns:
+            The changes since the previous version, like the `keys` of a
+                [MapEvent][pycrdt.MapEvent]: a `dict` of the changed keys, with the `action`
+                (`"add"`, `"update"` or `"delete"`), `oldValue` and `newValue`.
+
+        Raises:
+            ValueError: The previous snapshot must precede the snapshot.
+        """
+        if snapshot is None:
+            snapshot = Snapshot.from_doc(self.doc)
+        changes = snapshot._diff(self.doc, prev_snapshot, self)
+        return changes[0][1] if changes else {}
+
     def __delitem__(self, key: str) -> None:
         """
         Removes the item at the given key from the map:
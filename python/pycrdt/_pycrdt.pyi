@@ -1,4 +1,4 @@
-from typing import Any, Callable, Generic, Iterator, TypeVar
+from typing import Any, Callable, Generic, Iterator, Literal, TypeVar
 
 class Snapshot:
     """A snapshot of a document's state at a given point in time."""
@@ -14,10 +14,56 @@ class Snapshot:
     def encode(self) -> bytes:
         """Encode the snapshot to its binary representation."""
 
+    @staticmethod
+    def decode_v2(data: bytes) -> "Snapshot":
+        """Decode a snapshot from its v2 binary representation."""
+
+    @staticmethod
+    def from_state(state: bytes, delete_set: IdSet) -> "Snapshot":
+        """Create a snapshot from an encoded state vector and a delete set."""
+
+    def encode_v2(self) -> bytes:
+        """Encode the snapshot to its v2 binary representation."""
+
+    def state_vector(self) -> bytes:
+        """The encoded state vector of the snapshot."""
+
+    def delete_set(self) -> IdSet:
+        """The content deleted at the time of the snapshot."""
+
+    def is_visible(self, client: int, clock: int) -> bool:
+        """Whether the item with the given ID is visible in the snapshot."""
+
+    def restore(self, txn: Transaction) -> Doc:
+        """Restore the state of the document at the snapshot in a new document."""
+
+    def read(self, txn: Transaction, shared: Any) -> Any:
+        """
+        The content of an array or a map at the snapshot, or the string representation of an
+        XML node, read from the document in place.
+        """
+
+    def diff(
+        self, txn: Transaction, prev: Snapshot, shared: Any | None = None
+    ) -> tuple[Doc, list[tuple[list[int | str], Any]]]:
+        """
+        The changes of the arrays and maps of the document from a previous snapshot to this
+        snapshot, as a document restored at this snapshot and a list of (path, changes) tuples.
+        If a shared type is given, only its changes are returned.
+        """
+
 class Doc:
     """Shared document."""
 
-    def __init__(self, client_id: int | None, skip_gc: bool | None, guid: str | None) -> None:
+    def __init__(
+        self,
+        client_id: int | None,
+        skip_gc: bool | None,
+        guid: str | None,
+        offset_kind: Literal["bytes", "utf16"] | None = None,
+        should_load: bool | None = None,
+        auto_load: bool | None = None,
+    ) -> None:
         """Create a new document with an optional global client ID.
         If no client ID is passed, a random one will be generated."""
 
@@ -31,12 +77,48 @@ class Doc:
     def guid(self) -> str:
         """Returns the document globally unique identifier."""
 
+    def offset_kind(self) -> Literal["bytes", "utf16"]:
+        """Returns how the indices and lengths of texts are counted."""
+
+    def should_load(self) -> bool:
+        """Returns whether the document was requested to be loaded."""
+
+    def auto_load(self) -> bool:
+        """Returns whether peers should load the document automatically."""
+
+    def parent_doc(self) -> Doc | None:
+        """Returns the document this document is a subdocument of, if any."""
+
+    def load(self, parent_txn: Transaction | None = None) -> None:
+        """Request the loading of this document, notifying the parent document."""
+
+    def destroy(self, parent_txn: Transaction | None = None) -> None:
+        """Destroy this document, replacing it with an unloaded one in the parent document."""
+
+    def subdocs(self, txn: Transaction) -> list[Doc]:
+        """Get the subdocuments of this document."""
+
+    def subdoc_guids(self, txn: Transaction) -> list[str]:
+        """Get the GUIDs of the subdocuments of this document."""
+
     def create_transaction(self) -> Transaction:
         """Create a document transaction."""
 
-    def create_transaction_with_origin(self, origin: Any) -> Transaction:
+    def create_read_transaction(self) -> Transaction:
+        """Create a read-only document transaction."""
+
+    def create_transaction_with_origin(self, origin: bytes) -> Transaction:
         """Create a document transaction with an origin."""
 
+    def commit_aborted(self, txn: Transaction) -> None:
+        """Commit a rolled back transaction, only calling the observers of the document
+        updates with its update."""
+
+    def acquire_transaction(
+        self, origin: bytes | None = None, read_only: bool = False
+    ) -> TransactionAcquisition:
+        """Queue for a transaction, acquired when the ongoing transactions are released."""
+
     def get_or_insert_text(self, txn: Transaction, name: str) -> Text:
         """Create a text root type on this document, or get an existing one."""
 
@@ -55,16 +137,72 @@ class Doc:
     def get_update(self, txn: Transaction, state: bytes) -> bytes:
         """Get the update from the given state to the current state."""
 
+    def get_update_v2(self, txn: Transaction, state: bytes) -> bytes:
+        """Get the v2-encoded update from the given state to the current state."""
+
+    def get_changes(
+        self, txn: Transaction, state: bytes, delete_set: IdSet | None = None
+    ) -> tuple[Doc, dict[str, list[Any]]]:
+        """
+        Get the events of the changes made since the given state, and the document they refer to.
+        """
+
     def apply_update(self, txn: Transaction, update: bytes) -> None:
         """Apply the update to the document."""
 
-    def roots(self, txn: Transaction) -> dict[str, Text | Array | Map]:
-        """Get top-level (root) shared types available in current document."""
+    def apply_update_v2(self, txn: Transaction, update: bytes) -> None:
+        """Apply the v2-encoded update to the document."""
 
-    def observe(self, callback: Callable[[TransactionEvent], None]) -> Subscription:
+    def has_pending_structs(self, txn: Transaction) -> bool:
+        """Whether some structs are waiting for missing updates to be integrated."""
+
+    def has_pending_delete_set(self, txn: Transaction) -> bool:
+        """Whether some deletions are waiting for missing updates to be integrated."""
+
+    def get_missing_state(self, txn: Transaction) -> bytes | None:
+        """Get the state of the missing updates that pending structs depend on."""
+
+    def get_pending_update(self, txn: Transaction) -> bytes | None:
+        """Get the update made of the pending structs and of the pending delete set."""
+
+    def roots(
+        self, txn: Transaction, infer_types: bool = False
+    ) -> dict[str, Text | Array | Map | XmlFragment | None]:
+        """Get top-level (root) shared types available in current document.
+        The type of a root type that was not accessed is inferred from its content
+        if `infer_types` is set, otherwise it is `None`."""
+
+    def shared_type(self, txn: Transaction, shared: Any) -> Any | None:
+        """The shared type with the same ID as the given one (which can belong to another
+        document), if it exists and was not deleted."""
+
+    def observe(
+        self,
+        callback: Callable[[TransactionEvent], None],
+        origins: dict[bytes, Any] | None = None,
+        track_changes: bool = False,
+    ) -> Subscription:
         """Subscribes a callback to be called with the shared document change event.
         Returns a subscription that can be used to unsubscribe."""
 
+    def observe_update(
+        self, callback: Callable[[bytes, Any], None], origins: dict[bytes, Any] | None = None
+    ) -> Subscription:
+        """Subscribes a callback to be called with the v1-encoded update and the origin
+        of each transaction that changed the document."""
+
+    def observe_update_v2(
+        self, callback: Callable[[bytes, Any], None], origins: dict[bytes, Any] | None = None
+    ) -> Subscription:
+        """Subscribes a callback to be called with the v2-encoded update and the origin
+        of each transaction that changed the document."""
+
+    def observe_after_transaction(
+        self, callback: Callable[[Any], None], origins: dict[bytes, Any] | None = None
+    ) -> Subscription:
+        """Subscribes a callback to be called with the origin of each transaction,
+        at its end."""
+
     def observe_subdocs(self, callback: Callable[[SubdocsEvent], None]) -> Subscription:
         """Subscribes a callback to be called with the shared document subdoc change event.
         Returns a subscription that can be used to unsubscribe."""
@@ -84,25 +222,105 @@ class Transaction:
     def commit(self) -> None:
         """Commit the document changes."""
 
-    @property
-    def origin(self) -> Any:
+    def abort(self) -> None:
+        """Delete the content inserted in the transaction."""
+
+    def restore_before(self) -> Doc | None:
+        """The document as it was before the transaction, restored in a new document,
+        if the transaction deleted content that existed before it."""
+
+    def pending_events(self) -> tuple[Doc, dict[str, list[Any]]]:
+        """The events that the deep observers of the root types will get for the changes
+        made so far in the transaction, by root type name, with the document they are replayed on."""
+
+    def origin(self) -> bytes | None:
         """The origin of the transaction."""
 
+    def has_changes(self) -> bool:
+        """Whether content was inserted or deleted in the transaction."""
+
+    def aborting(self) -> bool:
+        """Whether the transaction is an aborted transaction being committed."""
+
+    def pending_update(self) -> bytes:
+        """The update of the changes made so far in the transaction."""
+
+    def pending_delete_set(self) -> IdSet:
+        """The content deleted so far in the transaction."""
+
+    def pending_changed_types(self) -> set[tuple[str | int, ...]]:
+        """The paths of the shared types changed so far in the transaction."""
+
+class TransactionAcquisition:
+    """Pending acquisition of a document transaction"""
+
+    def wait(self, timeout: float | None = None) -> Transaction | None:
+        """Block until the transaction is acquired, or cancel and return None on timeout."""
+
+    def poll(self, wake: Callable[[], None]) -> Transaction | None:
+        """Try to acquire the transaction, or call wake when it should be polled again."""
+
+    def cancel(self) -> None:
+        """Leave the queue, if the transaction was not acquired yet."""
+
 class TransactionEvent:
     """
     Event generated by the [observe][pycrdt.Doc.observe] method,
     emitted during the transaction commit phase.
     """
 
+    @property
+    def origin(self) -> Any:
+        """The origin of the transaction, as passed when creating it."""
+
+    @property
+    def changed_types(self) -> dict[tuple[str | int, ...], list[str | range]] | None:
+        """The shared types changed in the transaction, as a dict mapping their paths
+        (starting with the name of their root type) to their changed keys and index ranges
+        (deletions being empty ranges where the content was removed), if changes are tracked."""
+
+    @property
+    def changed_parent_types(self) -> set[tuple[str | int, ...]] | None:
+        """The paths of the shared types changed in the transaction and of all their parent
+        types, if changes are tracked."""
+
     @property
     def update(self) -> bytes:
         """The emitted binary update."""
 
+    @property
+    def update_v2(self) -> bytes:
+        """The emitted binary update, v2-encoded."""
+
 class SubdocsEvent:
     """
     Event generated by the [observe_subdocs][pycrdt.Doc.observe_subdocs] method,
     emitted during the transaction commit phase."""
 
+    @property
+    def added(self) -> list[str]:
+        """The GUIDs of the added subdocuments."""
+
+    @property
+    def removed(self) -> list[str]:
+        """The GUIDs of the removed subdocuments."""
+
+    @property
+    def loaded(self) -> list[str]:
+        """The GUIDs of the loaded subdocuments."""
+
+    @property
+    def added_docs(self) -> list[Doc]:
+        """The added subdocuments."""
+
+    @property
+    def removed_docs(self) -> list[Doc]:
+        """The removed subdocuments."""
+
+    @property
+    def loaded_docs(self) -> list[Doc]:
+        """The loaded subdocuments."""
+
 class TextEvent:
     """Event generated by `Text.observe` method. Emitted during transaction commit
     phase."""
@@ -155,8 +373,28 @@ class Text:
     def get_string(self, txn: Transaction) -> str:
         """Returns a text representation of the current shared text."""
 
-    def diff(self, txn: Transaction) -> list[tuple[Any, dict[str, Any] | None]]:
-        """Returns a sequence of formatted chunks."""
+    def diff(
+        self, txn: Transaction, snapshot: Snapshot | None = None
+    ) -> list[tuple[Any, dict[str, Any] | None]]:
+        """Returns a sequence of formatted chunks, optionally as they were at a snapshot."""
+
+    def diff_snapshots(
+        self, txn: Transaction, prev_snapshot: Snapshot, snapshot: Snapshot | None = None
+    ) -> list[tuple[Any, dict[str, Any] | None, dict[str, Any] | None]]:
+        """Returns a sequence of formatted chunks as they were at a snapshot (the current one
+        by default), including the content removed since a previous snapshot, with the change
+        of each chunk since then."""
+
+    def diff_attributed(
+        self,
+        txn: Transaction,
+        insertions: IdMap,
+        deletions: IdMap | None = None,
+        snapshot: Snapshot | None = None,
+    ) -> list[tuple[Any, dict[str, Any] | None, dict[str, list[ContentAttribute]] | None]]:
+        """Returns a sequence of formatted chunks, optionally as they were at a snapshot, with
+        the attributes of their insertion (and of their deletion for the deleted content in
+        `deletions`, rendered as tombstones), split where these attributes change."""
 
     def observe(self, callback: Callable[[TextEvent], None]) -> Subscription:
         """Subscribes a callback to be called with the shared text change event.
@@ -345,8 +583,28 @@ class XmlText:
     def format(self, txn: Transaction, index: int, len: int, attrs: Iterator[tuple[str, Any]]):
         """Adds attributes to a section of text."""
 
-    def diff(self, txn: Transaction) -> list[tuple[Any, dict[str, Any] | None]]:
-        """Returns a sequence of formatted chunks."""
+    def diff(
+        self, txn: Transaction, snapshot: Snapshot | None = None
+    ) -> list[tuple[Any, dict[str, Any] | None]]:
+        """Returns a sequence of formatted chunks, optionally as they were at a snapshot."""
+
+    def diff_snapshots(
+        self, txn: Transaction, prev_snapshot: Snapshot, snapshot: Snapshot | None = None
+    ) -> list[tuple[Any, dict[str, Any] | None, dict[str, Any] | None]]:
+        """Returns a sequence of formatted chunks as they were at a snapshot (the current one
+        by default), including the content removed since a previous snapshot, with the change
+        of each chunk since then."""
+
+    def diff_attributed(
+        self,
+        txn: Transaction,
+        insertions: IdMap,
+        deletions: IdMap | None = None,
+        snapshot: Snapshot | None = None,
+    ) -> list[tuple[Any, dict[str, Any] | None, dict[str, list[ContentAttribute]] | None]]:
+        """Returns a sequence of formatted chunks, optionally as they were at a snapshot, with
+        the attributes of their insertion (and of their deletion for the deleted content in
+        `deletions`, rendered as tombstones), split where these attributes change."""
 
     def observe(self, callback: Callable[[XmlEvent], None]) -> Subscription:
         """Subscribes a callback to be called with the XML change event.
@@ -372,10 +630,10 @@ class UndoManager:
     def expand_scope(self, doc: Doc, scope: Text | Array | Map) -> None:
         """Extends a list of shared types tracked by current undo manager by a given scope."""
 
-    def include_origin(self, origin: int) -> None:
+    def include_origin(self, origin: bytes) -> None:
         """Extends a list of origins tracked by current undo manager by a given origin."""
 
-    def exclude_origin(self, origin: int) -> None:
+    def exclude_origin(self, origin: bytes) -> None:
         """Removes an origin from the list of origins tracked by current undo manager."""
 
     def can_undo(self) -> bool:
@@ -521,6 +779,20 @@ class IdMap:
     def from_set(id_set: IdSet, attributes: list[ContentAttribute]) -> IdMap:
         """Build an IdMap from an IdSet, attaching `attributes` to every range."""
 
+    @staticmethod
+    def from_update(
+        update: bytes, attributes: list[ContentAttribute], *, v2: bool = False
+    ) -> tuple[IdMap, IdMap]:
+        """Build the maps of the IDs inserted and deleted by an update, attaching `attributes`
+        to every range. The update is v1-encoded, unless `v2` is set."""
+
+    @staticmethod
+    def from_event(
+        event: TransactionEvent, attributes: list[ContentAttribute]
+    ) -> tuple[IdMap, IdMap]:
+        """Build the maps of the IDs inserted and deleted by the transaction of an event,
+        attaching `attributes` to every range."""
+
     def encode(self) -> bytes:
         """Encode the map to bytes."""
 
@@ -599,6 +871,104 @@ class StackItem(Generic[MetaT]):
                 If None, keeps the first item's metadata.
         """
 
+class Message:
+    """A y-sync protocol message, as exchanged between Yjs peers."""
+
+    @staticmethod
+    def sync_step1(state: bytes) -> Message:
+        """Create a SYNC_STEP1 message carrying the given document state."""
+
+    @staticmethod
+    def sync_step2(update: bytes) -> Message:
+        """Create a SYNC_STEP2 message carrying the given update."""
+
+    @staticmethod
+    def sync_update(update: bytes) -> Message:
+        """Create a SYNC_UPDATE message carrying the given update."""
+
+    @staticmethod
+    def awareness(update: bytes) -> Message:
+        """Create an AWARENESS message carrying the given awareness update."""
+
+    @staticmethod
+    def auth(reason: str | None = None) -> Message:
+        """Create an AUTH message, denying permission if a `reason` is given."""
+
+    @staticmethod
+    def query_awareness() -> Message:
+        """Create a QUERY_AWARENESS message."""
+
+    @staticmethod
+    def custom(tag: int, data: bytes) -> Message:
+        """Create a custom message with the given `tag` (greater than 3) and payload."""
+
+    @property
+    def message_type(self) -> int:
+        """The [message type][pycrdt.YMessageType], or the tag of a custom message."""
+
+    @property
+    def sync_message_type(self) -> int | None:
+        """The [sync message type][pycrdt.YSyncMessageType], for SYNC messages only."""
+
+    @property
+    def payload(self) -> bytes | None:
+        """The state for SYNC_STEP1, the update for SYNC_STEP2 and SYNC_UPDATE,
+        the awareness update for AWARENESS, and the data for custom messages."""
+
+    @property
+    def reason(self) -> str | None:
+        """The reason why permission was denied, for AUTH messages only."""
+
+    def encode(self) -> bytes:
+        """Encode the message to bytes."""
+
+class Awareness:
+    """The awareness of a document, as defined by the y-protocols awareness protocol."""
+
+    def __init__(self, doc: Doc, timestamp: Callable[[], int] | None = None) -> None:
+        """Create an awareness with an empty local state, using `timestamp` (in milli-seconds)
+        as the clock, or the system time."""
+
+    @property
+    def client_id(self) -> int:
+        """The local client ID."""
+
+    @property
+    def states(self) -> dict[int, Any]:
+        """The states of all the clients that are not removed, indexed by client ID."""
+
+    @property
+    def meta(self) -> dict[int, dict[str, int]]:
+        """The `clock` and `lastUpdated` metadata of all the known clients, indexed by client ID."""
+
+    def get_local_state(self) -> Any:
+        """Returns the local state, if any."""
+
+    def set_local_state(self, state: Any, origin: Any = None) -> None:
+        """Set the local state, or mark the local client as disconnected if `state` is None."""
+
+    def encode_update(self, client_ids: list[int] | None = None) -> bytes:
+        """Encode the states of the given clients, or of all the clients that are not removed."""
+
+    def apply_update(self, update: bytes, origin: Any = None) -> None:
+        """Apply an encoded awareness update."""
+
+    def remove_states(self, client_ids: list[int], origin: Any = None) -> None:
+        """Remove the states of the given clients."""
+
+    def remove_outdated_states(self, outdated_timeout: int, origin: Any = None) -> list[int]:
+        """Renew the local state if it was last updated more than half `outdated_timeout`
+        (in milliseconds) ago, and remove the remote clients that were not updated for
+        `outdated_timeout`. Returns the IDs of the removed clients."""
+
+    def observe_change(self, callback: Callable[[dict[str, list[int]], Any], None]) -> Subscription:
+        """Subscribes a callback to be called with the added, updated and removed client IDs,
+        and the origin, when client states change."""
+
+    def observe_update(self, callback: Callable[[dict[str, list[int]], Any], None]) -> Subscription:
+        """Subscribes a callback to be called with the added, updated and removed client IDs,
+        and the origin, when client states are updated, including clock renewals."""
+
 class StickyIndex:
     def get_offset(self, txn: Transaction) -> int: ...
     def encode(self) -> bytes: ...
@@ -608,5 +978,15 @@ class StickyIndex:
 def merge_updates(updates: tuple[bytes, ...]) -> bytes: ...
 def get_state(update: bytes) -> bytes: ...
 def get_update(update: bytes, state: bytes) -> bytes: ...
+def merge_updates_v2(updates: tuple[bytes, ...]) -> bytes: ...
+def get_state_v2(update: bytes) -> bytes: ...
+def get_update_v2(update: bytes, state: bytes) -> bytes: ...
+def convert_update_v1_to_v2(update: bytes) -> bytes: ...
+def convert_update_v2_to_v1(update: bytes) -> bytes: ...
+def decode_update(update: bytes) -> dict[str, Any]: ...
+def decode_update_v2(update: bytes) -> dict[str, Any]: ...
+def decode_messages(data: bytes) -> list[Message]: ...
+def decode_sync_message(data: bytes) -> Message: ...
+def encode_messages(messages: list[Message]) -> bytes: ...
 def decode_sticky_index(data: bytes) -> StickyIndex: ...
 def get_sticky_index_from_json_string(data: str) -> StickyIndex: ...
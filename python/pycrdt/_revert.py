@@ -0,0 +1,183 @@
+from __future__ import annotations
+
+from typing import Any
+
+from ._array import Array
+from ._base import BaseDoc, BaseType
+from ._map import Map
+from ._text import Text, _len
+from ._xml import XmlElement, XmlFragment, XmlText
+
+Unit = tuple[Any, dict[str, Any]]
+
+
+def revert_type(current: BaseType, target: BaseType) -> None:
+    """
+    Edits a shared type so that its content becomes the content of another shared
+    type of the same kind (which can belong to another document), only changing
+    what differs between the two.
+    """
+    if isinstance(current, (Text, XmlText)):
+        _revert_text(current, target)
+    if isinstance(current, (XmlElement, XmlText)):
+        _revert_attributes(current, target)
+    if isinstance(current, (XmlFragment, XmlElement)):
+        _revert_children(current, target)
+    elif isinstance(current, Array):
+        _revert_array(current, target)
+    elif isinstance(current, Map):
+        _revert_map(current, target)
+
+
+def _same(value: Any, other: Any) -> bool:
+    # whether two values have the same content, shared types being compared
+    # by their string representation (formatting is reverted recursively)
+    if isinstance(value, BaseDoc) or isinstance(other, BaseDoc):
+        return (
+            isinstance(value, BaseDoc)
+            and isinstance(other, BaseDoc)
+            and value._doc.guid() == other._doc.guid()
+        )
+    if isinstance(value, BaseType) or isinstance(other, BaseType):
+        return type(value) is type(other) and str(value) == str(other)
+    return type(value) is type(other) and value == other
+
+
+def _empty(value: Any) -> Any:
+    # an empty preliminary copy of a shared type, to be integrated and then reverted
+    if isinstance(value, BaseDoc):
+        return type(value)(guid=value._doc.guid())
+    if isinstance(value, XmlElement):
+        return XmlElement(value.tag)
+    return type(value)()
+
+
+def _revert_value(current: Any, target: Any) -> None:
+    if isinstance(current, BaseType):
+        revert_type(current, target)
+
+
+def _common_ends(current: list[Any], target: list[Any], same) -> tuple[int, int]:
+    # the length of the common prefix and of the common suffix (not overlapping)
+    prefix = 0
+    max_prefix = min(len(current), len(target))
+    while prefix < max_prefix and same(current[prefix], target[prefix]):
+        prefix += 1
+    suffix = 0
+    max_suffix = max_prefix - prefix
+    while suffix < max_suffix and same(current[-1 - suffix], target[-1 - suffix]):
+        suffix += 1
+    return prefix, suffix
+
+
+def _text_units(chunks: list[tuple[Any, dict[str, Any] | None]]) -> list[Unit]:
+    units: list[Unit] = []
+    for value, attrs in chunks:
+        if isinstance(value, str):
+            units.extend((char, attrs or {}) for char in value)
+        else:
+            units.append((value, attrs or {}))
+    return units
+
+
+def _revert_text(current: Text | XmlText, target: Text | XmlText) -> None:
+    current_units = _text_units(current.diff())
+    target_units = _text_units(target.diff())
+    prefix, suffix = _common_ends(
+        current_units,
+        target_units,
+        lambda unit, other: unit[1] == other[1] and _same(unit[0], other[0]),
+    )
+    offset_kind = current.doc.offset_kind
+    index = sum(_len(value, offset_kind) for value, _ in current_units[:prefix])
+    length = sum(
+        _len(value, offset_kind) for value, _ in current_units[prefix : len(current_units) - suffix]
+    )
+    if length > 0:
+        del current[index : index + length]
+    # the attributes are always passed, so that the inserted content doesn't inherit
+    # the formatting of the content around it
+    pending = ""
+    pending_attrs: dict[str, Any] = {}
+    for value, attrs in target_units[prefix : len(target_units) - suffix]:
+        if pending and (not isinstance(value, str) or attrs != pending_attrs):
+            current.insert(index, pending, pending_attrs)
+            index += _len(pending, offset_kind)
+            pending = ""
+        if isinstance(value, str):
+            pending += value
+            pending_attrs = attrs
+        elif isinstance(value, BaseType):
+            embed = _empty(value)
+            current.insert_embed(index, embed, attrs)
+            revert_type(embed, value)
+            index += 1
+        else:
+            current.insert_embed(index, value, attrs)
+            index += 1
+    if pending:
+        current.insert(index, pending, pending_attrs)
+    # the embedded shared types that were kept may still differ in their formatting
+    kept = current_units[:prefix] + current_units[len(current_units) - suffix :]
+    targets = target_units[:prefix] + target_units[len(target_units) - suffix :]
+    for (value, _), (other, _) in zip(kept, targets):
+        _revert_value(value, other)
+
+
+def _revert_array(current: Array, target: Array) -> None:
+    current_items = list(current)
+    target_items = list(target)
+    prefix, suffix = _common_ends(current_items, target_items, _same)
+    if len(current_items) - suffix > prefix:
+        del current[prefix : len(current_items) - suffix]
+    for index, value in enumerate(target_items[prefix : len(target_items) - suffix], prefix):
+        if isinstance(value, (BaseType, BaseDoc)):
+            current.insert(index, _empty(value))
+            _revert_value(current[index], value)
+        else:
+            current.insert(index, value)
+    kept = current_items[:prefix] + current_items[len(current_items) - suffix :]
+    targets = target_items[:prefix] + target_items[len(target_items) - suffix :]
+    for value, other in zip(kept, targets):
+        _revert_value(value, other)
+
+
+def _revert_map(current: Map, target: Map) -> None:
+    target_items = dict(target.items())
+    for key in [key for key in current.keys() if key not in target_items]:
+        del current[key]
+    for key, value in target_items.items():
+        current_value = current.get(key)
+        if key in current and _same(current_value, value):
+            _revert_value(current_value, value)
+        elif isinstance(value, (BaseType, BaseDoc)):
+            current[key] = _empty(value)
+            _revert_value(current[key], value)
+        else:
+            current[key] = value
+
+
+def _revert_attributes(current: XmlElement | XmlText, target: XmlElement | XmlText) -> None:
+    target_attributes = dict(target.attributes)
+    for key, _ in list(current.attributes):
+        if key not in target_attributes:
+            del current.attributes[key]
+    for key, value in target_attributes.items():
+        if current.attributes.get(key) != value:
+            current.attributes[key] = value
+
+
+def _revert_children(
+    current: XmlFragment | XmlElement, target: XmlFragment | XmlElement
+) -> None:
+    current_children = list(current.children)
+    target_children = list(target.children)
+    prefix, suffix = _common_ends(current_children, target_children, _same)
+    if len(current_children) - suffix > prefix:
+        del current.children[prefix : len(current_children) - suffix]
+    for index, child in enumerate(target_children[prefix : len(target_children) - suffix], prefix):
+        revert_type(current.children.insert(index, _empty(child)), child)
+    kept = current_children[:prefix] + current_children[len(current_children) - suffix :]
+    targets = target_children[:prefix] + target_children[len(target_children) - suffix :]
+    for child, other in zip(kept, targets):
+        revert_type(child, other)
@@ -1,8 +1,11 @@
 from __future__ import annotations
 
 from dataclasses import dataclass
-from typing import TYPE_CHECKING
+from typing import TYPE_CHECKING, Any
 
+from ._base import BaseType, process_event
+from ._pycrdt import Doc as _Doc
+from ._pycrdt import IdSet
 from ._pycrdt import Snapshot as _Snapshot
 
 if TYPE_CHECKING:
@@ -14,6 +17,7 @@ class Snapshot:
     """
     A snapshot of a document's state at a given point in time.
     Can be encoded to bytes for storage or transmission, and decoded back.
+    Snapshots can be compared and hashed, e.g. to be used as version markers.
     """
 
     _snapshot: _Snapshot
@@ -46,6 +50,37 @@ class Snapshot:
         snap = _Snapshot.decode(data)
         return cls(snap)
 
+    @classmethod
+    def decode_v2(cls, data: bytes) -> Snapshot:
+        """
+        Decode a snapshot from its v2 binary representation.
+
+        Args:
+            data: The bytes to decode into a snapshot.
+        Returns:
+            The decoded snapshot.
+        Raises:
+            ValueError: If the bytes are not a valid snapshot.
+        """
+        snap = _Snapshot.decode_v2(data)
+        return cls(snap)
+
+    @classmethod
+    def from_state(cls, state: bytes, delete_set: IdSet) -> Snapshot:
+        """
+        Create a snapshot from a document state and the content that was deleted.
+
+        Args:
+            state: The document state (see [Doc.get_state()][pycrdt.Doc.get_state]).
+            delete_set: The deleted content.
+        Returns:
+            The snapshot.
+        Raises:
+            ValueError: If the state is not valid.
+        """
+        snap = _Snapshot.from_state(state, delete_set)
+        return cls(snap)
+
     def encode(self) -> bytes:
         """
         Encode the snapshot to its binary representation.
@@ -54,3 +89,80 @@ class Snapshot:
             The bytes representing the snapshot.
         """
         return self._snapshot.encode()
+
+    def encode_v2(self) -> bytes:
+        """
+        Encode the snapshot to its v2 binary representation.
+
+        Returns:
+            The bytes representing the snapshot.
+        """
+        return self._snapshot.encode_v2()
+
+    @property
+    def state(self) -> bytes:
+        """
+        The document state at the time of the snapshot, as returned by
+        [Doc.get_state()][pycrdt.Doc.get_state].
+        """
+        return self._snapshot.state_vector()
+
+    @property
+    def delete_set(self) -> IdSet:
+        """
+        The content that was deleted at the time of the snapshot.
+        """
+        return self._snapshot.delete_set()
+
+    def is_visible(self, client_id: int, clock: int) -> bool:
+        """
+        Checks whether an item existed and was not deleted at the time of the snapshot.
+
+        Args:
+            client_id: The client ID of the item.
+            clock: The clock of the item.
+        Returns:
+            True if the item is visible in the snapshot.
+        """
+        return self._snapshot.is_visible(client_id, clock)
+
+    def __hash__(self) -> int:
+        return hash(self._snapshot)
+
+    def _restore(self, doc: Doc) -> _Doc:
+        # the document as it was at the snapshot, in a new document
+        with doc.read_transaction() as txn:
+            assert txn._txn is not None
+            return self._snapshot.restore(txn._txn)
+
+    def _read(self, shared: BaseType) -> Any:
+        # the content of an array or a map at the snapshot, or the string of an XML node,
+        # read in place, the subdocuments being the ones of the document
+        with shared.doc.transaction() as txn:
+            content = self._snapshot.read(txn._txn, shared.integrated)
+        return _wrap_docs(shared, content)
+
+    def _diff(
+        self, doc: Doc, prev_snapshot: Snapshot, shared: BaseType | None = None
+    ) -> list[tuple[list[str | int], Any]]:
+        # the changes of the arrays and maps from the previous snapshot to this one,
+        # the shared types in the changes belonging to the document at this snapshot
+        with doc.read_transaction() as txn:
+            assert txn._txn is not None
+            restored, changes = self._snapshot.diff(
+                txn._txn,
+                prev_snapshot._snapshot,
+                None if shared is None else shared.integrated,
+            )
+        restored_doc = type(doc)(doc=restored)
+        return [(path, process_event(change, restored_doc)) for path, change in changes]
+
+
+def _wrap_docs(shared: BaseType, value: Any) -> Any:
+    if isinstance(value, list):
+        return [_wrap_docs(shared, v) for v in value]
+    if isinstance(value, dict):
+        return {k: _wrap_docs(shared, v) for k, v in value.items()}
+    if isinstance(value, _Doc):
+        return shared._maybe_as_type_or_doc(value)
+    return value
@@ -123,6 +123,9 @@ class StickyIndex:
 
         Returns:
             The decoded sticky index.
+
+        Raises:
+            DecodeError: The data is not a valid sticky index.
         """
         self = cls(decode_sticky_index(data), sequence)
         return self
@@ -140,6 +143,9 @@ class StickyIndex:
 
         Returns:
             The deserialized sticky index.
+
+        Raises:
+            DecodeError: The data is not a valid sticky index.
         """
         self = cls(get_sticky_index_from_json_string(json.dumps(data)), sequence)
         return self
@@ -4,6 +4,10 @@ from enum import IntEnum
 from typing import Iterator
 
 from ._doc import Doc
+from ._pycrdt import Message
+from ._pycrdt import decode_messages as _decode_messages
+from ._pycrdt import decode_sync_message as _decode_sync_message
+from ._pycrdt import encode_messages as _encode_messages
 
 
 class YMessageType(IntEnum):
@@ -13,10 +17,14 @@ class YMessageType(IntEnum):
     Attributes:
         SYNC: A message type used for synchronizing documents.
         AWARENESS: A message type used for the awareness protocol.
+        AUTH: A message type used for the authentication protocol.
+        QUERY_AWARENESS: A message type used to request the awareness states.
     """
 
     SYNC = 0
     AWARENESS = 1
+    AUTH = 2
+    QUERY_AWARENESS = 3
 
 
 class YSyncMessageType(IntEnum):
@@ -280,27 +288,51 @@ def handle_sync_message(message: bytes, ydoc: Doc) -> bytes | None:
         The [SYNC_STEP2][pycrdt.YSyncMessageType] reply message, if the message
         was a [SYNC_STEP1][pycrdt.YSyncMessageType].
     """
-    message_type = message[0]
-    msg = message[1:]
+    msg = _decode_sync_message(message)
+    payload = msg.payload
+    assert payload is not None
 
-    if message_type == YSyncMessageType.SYNC_STEP1:
-        state = read_message(msg)
-        update = ydoc.get_update(state)
-        reply = create_sync_step2_message(update)
-        return reply
+    if msg.sync_message_type == YSyncMessageType.SYNC_STEP1:
+        update = ydoc.get_update(payload)
+        return Message.sync_step2(update).encode()
 
-    if message_type in (
-        YSyncMessageType.SYNC_STEP2,
-        YSyncMessageType.SYNC_UPDATE,
-    ):
-        update = read_message(msg)
-        # Ignore empty updates
-        if update != b"\x00\x00":
-            ydoc.apply_update(update)
+    # Ignore empty updates
+    if payload != b"\x00\x00":
+        ydoc.apply_update(payload)
 
     return None
 
 
+def decode_messages(data: bytes) -> list[Message]:
+    """
+    Decodes all the messages contained in a byte stream, e.g. a buffer received
+    from a peer.
+
+    Args:
+        data: The byte stream from which to read the messages.
+
+    Raises:
+        ValueError: The byte stream is not a valid sequence of messages.
+
+    Returns:
+        The decoded [messages][pycrdt.Message].
+    """
+    return _decode_messages(data)
+
+
+def encode_messages(*messages: Message) -> bytes:
+    """
+    Encodes messages one after another in a byte stream.
+
+    Args:
+        messages: The [messages][pycrdt.Message] to encode.
+
+    Returns:
+        The byte stream containing the messages.
+    """
+    return _encode_messages(list(messages))
+
+
 def create_sync_message(ydoc: Doc) -> bytes:
     """
     Creates a [SYNC_STEP1][pycrdt.YSyncMessageType] message that
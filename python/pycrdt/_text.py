@@ -1,6 +1,6 @@
 from __future__ import annotations
 
-from typing import TYPE_CHECKING, Any, Callable, Iterator, cast
+from typing import TYPE_CHECKING, Any, Callable, Iterator, Literal, cast
 
 from ._base import BaseEvent, BaseType, Sequence, base_types, event_types
 from ._pycrdt import Subscription
@@ -9,6 +9,9 @@ from ._pycrdt import TextEvent as _TextEvent
 
 if TYPE_CHECKING:
     from ._doc import Doc
+    from ._pycrdt import ContentAttribute, IdMap
+    from ._snapshot import Snapshot
+    from ._xml import XmlText
 
 
 class Text(Sequence):
@@ -102,13 +105,19 @@ class Text(Sequence):
         with self.doc.transaction() as txn:
             return self.integrated.get_string(txn._txn)
 
-    def to_py(self) -> str | None:
+    def to_py(self, snapshot: Snapshot | None = None) -> str | None:
         """
+        Args:
+            snapshot: An optional [Snapshot][pycrdt.Snapshot] to get the text as it was at
+                that time, which requires a document created with `skip_gc=True`.
+
         Returns:
             The text as a Python `str`.
         """
         if self._integrated is None:
             return self._prelim
+        if snapshot is not None:
+            return "".join(chunk for chunk, _ in self.diff(snapshot) if isinstance(chunk, str))
         return str(self)
 
     def __iadd__(self, value: str) -> Text:
@@ -163,6 +172,7 @@ class Text(Sequence):
             key: The index or the slice of the characters to remove.
 
         Raises:
+            IndexError: Text index out of range.
             RuntimeError: Step not supported.
             RuntimeError: Negative start not supported.
             RuntimeError: Negative stop not supported.
@@ -170,7 +180,8 @@ class Text(Sequence):
         with self.doc.transaction() as txn:
             self._forbid_read_transaction(txn)
             if isinstance(key, int):
-                self.integrated.remove_range(txn._txn, key, 1)
+                offset, char = _char_at(self, key)
+                self.integrated.remove_range(txn._txn, offset, _len(char, self.doc.offset_kind))
             elif isinstance(key, slice):
                 start, stop = self._check_slice(key)
                 length = stop - start
@@ -187,11 +198,33 @@ class Text(Sequence):
         assert text[:5] == "Hello"
         ```
 
+        The index or slice is counted in the [offset kind][pycrdt.Doc.offset_kind] of the
+        document, like the length of the text and the indices of the other methods. An index
+        gets the whole character it falls in, which can span several offsets.
+
         Returns:
             The characters at the given index or slice.
+
+        Raises:
+            IndexError: Text index out of range.
+            RuntimeError: Step not supported.
+            RuntimeError: Slice not on character boundaries.
         """
-        value = str(self)
-        return value[key]
+        if isinstance(key, int):
+            return _char_at(self, key)[1]
+        elif not isinstance(key, slice):
+            raise RuntimeError(f"Index not supported: {key}")
+        if key.step is not None:
+            raise RuntimeError("Step not supported")
+        if self.doc.offset_kind == "utf16":
+            encoding, width = "utf-16-le", 2
+        else:
+            encoding, width = "utf-8", 1
+        start, stop = (None if index is None else index * width for index in (key.start, key.stop))
+        try:
+            return str(self).encode(encoding)[start:stop].decode(encoding)
+        except UnicodeDecodeError:
+            raise RuntimeError(f"Slice not on character boundaries: {key}") from None
 
     def __setitem__(self, key: int | slice, value: str) -> None:
         """
@@ -202,11 +235,15 @@ class Text(Sequence):
         assert text == "Hello, Brian!"
         ```
 
+        Like when getting and deleting, an index is the whole character it falls in, which is
+        replaced with a single character.
+
         Args:
             key: The index or slice of the characters to replace.
             value: The new characters to set.
 
         Raises:
+            IndexError: Text index out of range.
             RuntimeError: Step not supported.
             RuntimeError: Negative start not supported.
             RuntimeError: Negative stop not supported.
@@ -220,8 +257,9 @@ class Text(Sequence):
                     raise RuntimeError(
                         f"Single item assigned value must have a length of 1, not {value_len}"
                     )
-                del self[key]
-                self.integrated.insert(txn._txn, key, value)
+                offset, char = _char_at(self, key)
+                self.integrated.remove_range(txn._txn, offset, _len(char, self.doc.offset_kind))
+                self.integrated.insert(txn._txn, offset, value)
             elif isinstance(key, slice):
                 start, stop = self._check_slice(key)
                 length = stop - start
@@ -291,19 +329,97 @@ class Text(Sequence):
             if length > 0:
                 self.integrated.format(txn._txn, start, length, iter(attrs.items()))
 
-    def diff(self) -> list[tuple[Any, dict[str, Any] | None]]:
+    def diff(self, snapshot: Snapshot | None = None) -> list[tuple[Any, dict[str, Any] | None]]:
         """
+        Args:
+            snapshot: An optional [Snapshot][pycrdt.Snapshot] to get the chunks as they were at
+                that time, which requires a document created with `skip_gc=True`.
+
         Returns:
             A list of formatted chunks that the current text corresponds to.
                 Each list item is a tuple containing the chunk's content and formatting attributes.
                 The content is usually the text as a string, but may be other data for embedded
                 objects. Embedded shared types are returned as their pycrdt type (e.g. an
                 [Array][pycrdt.Array], [Map][pycrdt.Map] or [Text][pycrdt.Text]).
+
+        Raises:
+            ValueError: Document must be created with skip_gc=True to be read at a snapshot.
         """
         with self.doc.transaction() as txn:
             return [
                 (self._maybe_as_type_or_doc(value), attrs)
-                for value, attrs in self.integrated.diff(txn._txn)
+                for value, attrs in self.integrated.diff(
+                    txn._txn, None if snapshot is None else snapshot._snapshot
+                )
+            ]
+
+    def diff_snapshots(
+        self, prev_snapshot: Snapshot, snapshot: Snapshot | None = None
+    ) -> list[tuple[Any, dict[str, Any] | None, dict[str, Any] | None]]:
+        """
+        Compares the text between two snapshots, e.g. to show the changes between two versions.
+        The document must have been created with `skip_gc=True`.
+
+        Args:
+            prev_snapshot: The [Snapshot][pycrdt.Snapshot] of the previous version.
+            snapshot: The [Snapshot][pycrdt.Snapshot] of the new version
+                (default is the current version).
+
+        Returns:
+            A list of formatted chunks, including the content removed since the previous version.
+                Each list item is a tuple containing the chunk's content, formatting attributes,
+                and change since the previous version: `None` if unchanged, or a `dict` with the
+                `type` of change (`"added"` or `"removed"`), and the `client` ID and `clock` of
+                the content that was added or removed.
+
+        Raises:
+            ValueError: Document must be created with skip_gc=True to be read at a snapshot.
+        """
+        with self.doc.transaction() as txn:
+            return [
+                (self._maybe_as_type_or_doc(value), attrs, change)
+                for value, attrs, change in self.integrated.diff_snapshots(
+                    txn._txn,
+                    prev_snapshot._snapshot,
+                    None if snapshot is None else snapshot._snapshot,
+                )
+            ]
+
+    def diff_attributed(
+        self,
+        insertions: IdMap,
+        deletions: IdMap | None = None,
+        snapshot: Snapshot | None = None,
+    ) -> list[tuple[Any, dict[str, Any] | None, dict[str, list[ContentAttribute]] | None]]:
+        """
+        Returns the formatted chunks of the text along with their attribution, e.g. to show who
+        wrote each part of the text. The chunks are split where the attributes attached to the
+        IDs of their content change.
+
+        Args:
+            insertions: The [IdMap][pycrdt.IdMap] of the attributes of the inserted content.
+            deletions: An optional [IdMap][pycrdt.IdMap] of the attributes of the deleted content.
+                If given, the deleted content that it covers is included as tombstones,
+                which requires a document created with `skip_gc=True`.
+            snapshot: An optional [Snapshot][pycrdt.Snapshot] to get the chunks as they were at
+                that time, which requires a document created with `skip_gc=True`.
+
+        Returns:
+            A list of formatted chunks. Each list item is a tuple containing the chunk's content,
+                formatting attributes, and attribution: a `dict` with the list of
+                [ContentAttribute][pycrdt.ContentAttribute] of its `insert`, and of its `delete`
+                for tombstones. The attribution is `None` for embedded values other than shared
+                types, which cannot be attributed.
+        """
+        with self.doc.transaction() as txn:
+            return [
+                (self._maybe_as_type_or_doc(value), attrs, attribution)
+                for value, attrs, attribution in self.integrated.diff_attributed(
+                    txn._txn,
+                    insertions,
+                    deletions,
+                    None if snapshot is None else snapshot._snapshot,
+                )
             ]
 
     def observe(self, callback: Callable[[TextEvent], None]) -> Subscription:
@@ -316,6 +432,33 @@ class Text(Sequence):
         return super().observe(cast(Callable[[BaseEvent], None], callback))
 
 
+def _len(value: Any, offset_kind: Literal["bytes", "utf16"]) -> int:
+    # the length of text content, as counted by the document (an embed has a length of 1)
+    if not isinstance(value, str):
+        return 1
+    if offset_kind == "utf16":
+        return len(value.encode("utf-16-le")) // 2
+    return len(value.encode())
+
+
+def _char_at(text: Text | XmlText, index: int) -> tuple[int, Any]:
+    # the offset of the character (or embed) that an index falls in, and the character,
+    # the index being counted in the offset kind of the document
+    length = len(text)
+    if index < 0:
+        index += length
+    if not 0 <= index < length:
+        raise IndexError("Text index out of range")
+    offset = 0
+    for chunk, _ in text.diff():
+        for char in chunk if isinstance(chunk, str) else [chunk]:
+            char_len = _len(char, text.doc.offset_kind)
+            if index < offset + char_len:
+                return offset, char
+            offset += char_len
+    raise IndexError("Text index out of range")
+
+
 class TextEvent(BaseEvent):
     """
     A text change event.
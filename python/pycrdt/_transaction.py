@@ -1,13 +1,16 @@
 from __future__ import annotations
 
+import asyncio
 import sys
-from functools import partial
 from types import TracebackType
-from typing import TYPE_CHECKING, Any
+from typing import TYPE_CHECKING, Any, Callable
 
-from anyio import create_task_group, to_thread
+from anyio import Event, create_task_group, move_on_after
 
+from ._exceptions import TransactionError
+from ._pycrdt import IdSet
 from ._pycrdt import Transaction as _Transaction
+from ._pycrdt import TransactionAcquisition as _TransactionAcquisition
 
 if sys.version_info >= (3, 11):
     from typing import Self
@@ -16,6 +19,7 @@ else:  # pragma: nocover
     from typing_extensions import Self
 
 if TYPE_CHECKING:
+    from ._base import BaseEvent
     from ._doc import Doc
 
 
@@ -32,8 +36,11 @@ class Transaction:
     _doc: Doc
     _txn: _Transaction | None
     _leases: int
-    _origin_hash: int | None
+    _origin: bytes | None
     _timeout: float
+    _rollback_on_error: bool
+    _aborted: bool
+    _deferred_from: int
 
     def __init__(
         self,
@@ -42,30 +49,37 @@ class Transaction:
         *,
         origin: Any = None,
         timeout: float | None = None,
+        rollback_on_error: bool = False,
     ) -> None:
         self._doc = doc
         self._txn = _txn
         self._leases = 0
+        self._deferred_from = 0
+        self._rollback_on_error = rollback_on_error
+        self._aborted = False
         if origin is None:
-            self._origin_hash = None
+            self._origin = None
         else:
-            self._origin_hash = hash_origin(origin)
-            doc._origins[self._origin_hash] = origin
+            self._origin = encode_origin(origin)
+            doc._origins[self._origin] = origin
         self._timeout = -1 if timeout is None else timeout
 
-    def __enter__(self, _acquire_transaction: bool = True) -> Self:
-        self._leases += 1
+    def __enter__(self) -> Self:
         if self._txn is None:
-            if (
-                self._doc._allow_multithreading
-                and _acquire_transaction
-                and not self._doc._txn_lock.acquire(timeout=self._timeout)
-            ):
-                raise TimeoutError("Could not acquire transaction")
-            if self._origin_hash is not None:
-                self._txn = self._doc._doc.create_transaction_with_origin(self._origin_hash)
+            if self._doc._allow_multithreading:
+                # wait for the transactions of other threads to be released
+                acquisition = self._doc._doc.acquire_transaction(self._origin)
+                self._txn = acquisition.wait(self._timeout)
+                if self._txn is None:
+                    raise TimeoutError("Could not acquire transaction")
+            elif self._origin is not None:
+                self._txn = self._doc._doc.create_transaction_with_origin(self._origin)
             else:
                 self._txn = self._doc._doc.create_transaction()
+        if self._leases == 0:
+            # the follow-up transactions scheduled before are not the ones of this transaction
+            self._deferred_from = len(self._doc._deferred)
+        self._leases += 1
         self._doc._txn = self
         return self
 
@@ -82,27 +96,38 @@ class Transaction:
             assert self._txn is not None
             try:
                 if not isinstance(self, ReadTransaction):
+                    rejection = None
+                    if exc_type is not None and self._rollback_on_error and not self._aborted:
+                        self.abort()
+                    elif not self._aborted:
+                        rejection = self._validate()
                     self._txn.commit()
-                    origin_hash = self._txn.origin()
-                    if origin_hash is not None:
-                        del self._doc._origins[origin_hash]
-                    if self._doc._allow_multithreading:
-                        self._doc._txn_lock.release()
+                    origin = self._txn.origin()
+                    if origin is not None:
+                        self._doc._origins.pop(origin, None)
                     if self._doc._exceptions:
                         exceptions = tuple(self._doc._exceptions)
                         self._doc._exceptions.clear()
                         raise ExceptionGroup("Observer callback error", exceptions)
+                    if rejection is not None:
+                        raise TransactionError("Transaction rejected by validator") from rejection
+            except BaseException:
+                # the follow-up transactions of a failed transaction are not run
+                del self._doc._deferred[self._deferred_from :]
+                raise
             finally:
                 self._txn.drop()
                 self._txn = None
                 self._doc._txn = None
+            if not isinstance(self, ReadTransaction):
+                self._doc._run_deferred()
 
-    async def __aenter__(self, _acquire_transaction: bool = True) -> Self:
+    async def __aenter__(self) -> Self:
         if self._leases == 0:
             self._doc._task_group = await create_task_group().__aenter__()
         elif self._doc._task_group is None:
-            raise RuntimeError("Already in a non-async transaction")
-        return self.__enter__(_acquire_transaction)
+            raise TransactionError("Already in a non-async transaction")
+        return self.__enter__()
 
     async def __aexit__(
         self,
@@ -121,19 +146,77 @@ class Transaction:
     @property
     def origin(self) -> Any:
         """
-        The origin of the transaction.
+        The origin of the transaction, as it was passed when creating the transaction.
+        If the transaction was not created by this document (e.g. by an undo manager),
+        the origin bytes are returned.
 
         Raises:
-            RuntimeError: No current transaction.
+            TransactionError: No current transaction.
         """
         if self._txn is None:
-            raise RuntimeError("No current transaction")
+            raise TransactionError("No current transaction")
+
+        origin = self._txn.origin()
+        if origin is None:
+            return None
+
+        return self._doc._origins.get(origin, origin)
 
-        origin_hash = self._txn.origin()
-        if origin_hash is None:
+    def _validate(self) -> Exception | None:
+        # run the validators before committing, and roll back the transaction
+        # if one of them rejects it
+        assert self._txn is not None
+        if not self._doc._validators or not self._txn.has_changes():
             return None
+        changes = PendingChanges(self)
+        for validator in list(self._doc._validators):
+            # validators can read the document in the transaction, but must not release it
+            self._leases += 1
+            try:
+                validator(changes)
+            except Exception as exception:
+                self.abort()
+                return exception
+            finally:
+                self._leases -= 1
+        return None
+
+    def abort(self) -> None:
+        """
+        Rolls back the changes made so far in the transaction: the content it inserted,
+        locally or by the updates applied in it, is deleted, and the content it deleted
+        is inserted back. The observers of the shared types are not called for an aborted
+        transaction. Its update (with the rolled back content) must still reach the other
+        documents for them to stay in sync, so it is sent right away to the observers of
+        the document (see [Doc.observe()][pycrdt.Doc.observe]), whose events report no
+        changed types.
+
+        The deleted content cannot be brought back as such, it is inserted back as a copy:
+        the shared types that were deleted in the transaction, and the references to
+        them, are not restored, they must be retrieved again from their parent type.
+
+        The transaction cannot be used to modify the document anymore.
 
-        return self._doc._origins[origin_hash]
+        Raises:
+            TransactionError: No current transaction.
+        """
+        if self._txn is None:
+            raise TransactionError("No current transaction")
+        if self._aborted:
+            return
+
+        # the document is read while rolling back, but the transaction must not be released
+        self._leases += 1
+        try:
+            before = self._txn.restore_before()
+            roots = {path[0] for path in self._txn.pending_changed_types()}
+            self._txn.abort()
+            if before is not None:
+                self._doc._restore_roots(before, roots)
+        finally:
+            self._leases -= 1
+        self._aborted = True
+        self._doc._doc.commit_aborted(self._txn)
 
 
 class NewTransaction(Transaction):
@@ -150,36 +233,153 @@ class NewTransaction(Transaction):
     ```
     """
 
-    async def __aenter__(self) -> Self:  # type: ignore[override]
-        if self._doc._allow_multithreading:
-            if not await to_thread.run_sync(
-                partial(self._doc._txn_lock.acquire, timeout=self._timeout), abandon_on_cancel=True
-            ):
+    async def __aenter__(self) -> Self:
+        if self._txn is None:
+            acquisition = self._doc._doc.acquire_transaction(self._origin)
+            self._txn = await wait_transaction(acquisition, self._timeout)
+            if self._txn is None:
                 raise TimeoutError("Could not acquire transaction")
-        else:
-            await self._doc._txn_async_lock.acquire()
-        return await super().__aenter__(_acquire_transaction=False)
-
-    async def __aexit__(
-        self,
-        exc_type: type[BaseException] | None,
-        exc_val: BaseException | None,
-        exc_tb: TracebackType | None,
-    ) -> bool | None:
-        res = await super().__aexit__(exc_type, exc_val, exc_tb)
-        if not self._doc._allow_multithreading:
-            self._doc._txn_async_lock.release()
-        return res
+        return await super().__aenter__()
 
 
 class ReadTransaction(Transaction):
     """
     A read-only transaction that cannot be used to mutate a document.
+    Several read-only transactions can be held at the same time, e.g. in different threads
+    (see [Doc.read_transaction()][pycrdt.Doc.read_transaction]):
+    ```py
+    with doc.read_transaction():
+        ...
+    ```
     """
 
+    def __enter__(self) -> Self:
+        if self._txn is None:
+            if self._doc._allow_multithreading:
+                # wait for a read-write transaction of another thread to be released
+                acquisition = self._doc._doc.acquire_transaction(read_only=True)
+                self._txn = acquisition.wait(self._timeout)
+                if self._txn is None:
+                    raise TimeoutError("Could not acquire transaction")
+            else:
+                self._txn = self._doc._doc.create_read_transaction()
+        self._leases += 1
+        self._doc._txn = self
+        return self
+
+
+class PendingChanges:
+    """
+    The changes made in a transaction that is about to be committed,
+    passed to the validators (see [Doc.add_validator()][pycrdt.Doc.add_validator]).
+    The document can also be read, since the transaction is still current.
+    """
+
+    _events: dict[str, list[BaseEvent]] | None
+
+    def __init__(self, transaction: Transaction) -> None:
+        self._transaction = transaction
+        self._events = None
+
+    @property
+    def transaction(self) -> Transaction:
+        """The transaction about to be committed."""
+        return self._transaction
+
+    @property
+    def origin(self) -> Any:
+        """The origin of the transaction, as it was passed when creating the transaction."""
+        return self._transaction.origin
+
+    @property
+    def update(self) -> bytes:
+        """The update of the changes, as it would be sent to other documents."""
+        assert self._transaction._txn is not None
+        return self._transaction._txn.pending_update()
+
+    @property
+    def delete_set(self) -> IdSet:
+        """The content deleted in the transaction."""
+        assert self._transaction._txn is not None
+        return self._transaction._txn.pending_delete_set()
+
+    @property
+    def changed_types(self) -> set[tuple[str | int, ...]]:
+        """
+        The paths of the shared types that were changed, starting with the name of their
+        root type. Shared types that were created and deleted in the transaction are not included.
+        """
+        assert self._transaction._txn is not None
+        return self._transaction._txn.pending_changed_types()
+
+    @property
+    def events(self) -> dict[str, list[BaseEvent]]:
+        """
+        The delta of the changes, as the events that the deep observers of the root types will
+        be called with, by root type name. The events have no transaction, and their shared
+        types belong to a copy of the document as it was before the transaction.
+        """
+        if self._events is None:
+            assert self._transaction._txn is not None
+            copy, changes = self._transaction._txn.pending_events()
+            self._events = self._transaction._doc._detached_changes(copy, changes)
+        return self._events
+
+
+async def wait_transaction(
+    acquisition: _TransactionAcquisition, timeout: float
+) -> _Transaction | None:
+    """
+    Waits for a transaction to be acquired, yielding to the event loop meanwhile.
+    Returns `None` if the timeout (in seconds, negative for no timeout) expired.
+    """
+    try:
+        with move_on_after(None if timeout < 0 else timeout):
+            while True:
+                event = Event()
+                txn = acquisition.poll(threadsafe_setter(event))
+                if txn is not None:
+                    return txn
+                await event.wait()
+        return None
+    finally:
+        # leave the queue if cancelled
+        acquisition.cancel()
+
+
+def threadsafe_setter(event: Event) -> Callable[[], None]:
+    """
+    Returns a function that sets the event from any thread, in the current event loop.
+    """
+    try:
+        call_soon = asyncio.get_running_loop().call_soon_threadsafe
+    except RuntimeError:
+        from trio.lowlevel import current_trio_token
+
+        call_soon = current_trio_token().run_sync_soon
+
+    def set_event() -> None:
+        try:
+            call_soon(event.set)
+        except RuntimeError:  # pragma: nocover
+            # the event loop is closed
+            pass
+
+    return set_event
+
 
 def hash_origin(origin: Any) -> int:
     try:
         return hash(origin)
     except Exception:
         raise TypeError("Origin must be hashable")
+
+
+def encode_origin(origin: Any) -> bytes:
+    """
+    Encodes a transaction origin: byte strings are used as is, so that they can be
+    recognized in other processes, other objects are encoded from their hash.
+    """
+    if isinstance(origin, bytes):
+        return origin
+    return hash_origin(origin).to_bytes(16, "big", signed=True)
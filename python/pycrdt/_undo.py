@@ -1,7 +1,7 @@
 from __future__ import annotations
 
 from time import time_ns
-from typing import Any, Callable
+from typing import TYPE_CHECKING, Any, Callable
 
 from ._base import BaseType
 from ._pycrdt import (
@@ -10,7 +10,10 @@ from ._pycrdt import (
 from ._pycrdt import (
     UndoManager as _UndoManager,
 )
-from ._transaction import hash_origin
+from ._transaction import encode_origin
+
+if TYPE_CHECKING:
+    from ._doc import Doc
 
 
 def timestamp() -> int:
@@ -42,6 +45,7 @@ class UndoManager:
             undo_stack: Pre-filled undo stack items.
             redo_stack: Pre-filled redo stack items.
         """
+        self._doc: Doc | None = None
         self._undo_manager = _UndoManager(
             capture_timeout_millis,
             timestamp,
@@ -61,6 +65,7 @@ class UndoManager:
         """
         method = getattr(self._undo_manager, f"expand_scope_{scope.type_name}")
         method(scope.doc._doc, scope._integrated)
+        self._doc = scope.doc
 
     def include_origin(self, origin: Any) -> None:
         """
@@ -69,7 +74,7 @@ class UndoManager:
         Args:
             origin: The origin to include.
         """
-        self._undo_manager.include_origin(hash_origin(origin))
+        self._undo_manager.include_origin(encode_origin(origin))
 
     def exclude_origin(self, origin: Any) -> None:
         """
@@ -78,7 +83,7 @@ class UndoManager:
         Args:
             origin: The origin to exclude.
         """
-        self._undo_manager.exclude_origin(hash_origin(origin))
+        self._undo_manager.exclude_origin(encode_origin(origin))
 
     def can_undo(self) -> bool:
         """
@@ -94,7 +99,9 @@ class UndoManager:
         Returns:
             True if some changes were undone.
         """
-        return self._undo_manager.undo()
+        res = self._undo_manager.undo()
+        self._run_deferred()
+        return res
 
     def can_redo(self) -> bool:
         """
@@ -110,7 +117,15 @@ class UndoManager:
         Returns:
             True if some changes were redone.
         """
-        return self._undo_manager.redo()
+        res = self._undo_manager.redo()
+        self._run_deferred()
+        return res
+
+    def _run_deferred(self) -> None:
+        # the undo manager uses its own transaction, after which
+        # the follow-up transactions scheduled by observers are run
+        if self._doc is not None and self._doc._txn is None:
+            self._doc._run_deferred()
 
     def clear(self) -> None:
         """
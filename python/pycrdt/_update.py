@@ -1,6 +1,17 @@
+from __future__ import annotations
+
+from typing import Any
+
+from ._pycrdt import convert_update_v1_to_v2 as _convert_update_v1_to_v2
+from ._pycrdt import convert_update_v2_to_v1 as _convert_update_v2_to_v1
+from ._pycrdt import decode_update as _decode_update
+from ._pycrdt import decode_update_v2 as _decode_update_v2
 from ._pycrdt import get_state as _get_state
+from ._pycrdt import get_state_v2 as _get_state_v2
 from ._pycrdt import get_update as _get_update
+from ._pycrdt import get_update_v2 as _get_update_v2
 from ._pycrdt import merge_updates as _merge_updates
+from ._pycrdt import merge_updates_v2 as _merge_updates_v2
 
 
 def get_state(update: bytes) -> bytes:
@@ -42,3 +53,118 @@ def merge_updates(*updates: bytes) -> bytes:
         The merged updates.
     """
     return _merge_updates(updates)
+
+
+def get_state_v2(update: bytes) -> bytes:
+    """
+    Returns a state from a v2-encoded update.
+    The state is encoded in the v1 format, which is the only one used for states.
+
+    Args:
+        update: The v2-encoded update from which to get the state.
+
+    Returns:
+        The state corresponding to the update.
+    """
+    return _get_state_v2(update)
+
+
+def get_update_v2(update: bytes, state: bytes) -> bytes:
+    """
+    Returns a v2-encoded update consisting of all changes from a given v2-encoded update
+    which have not been seen in the given state.
+
+    Args:
+        update: The v2-encoded update from which to get all missing changes in the given state.
+        state: The state from which to get missing changes that are in the given update.
+
+    Returns:
+        The changes from the given update not present in the given state, v2-encoded.
+    """
+    return _get_update_v2(update, state)
+
+
+def merge_updates_v2(*updates: bytes) -> bytes:
+    """
+    Returns a v2-encoded update consisting of a combination of all given v2-encoded updates.
+
+    Args:
+        updates: The v2-encoded updates to merge.
+
+    Returns:
+        The merged updates, v2-encoded.
+    """
+    return _merge_updates_v2(updates)
+
+
+def convert_update_v1_to_v2(update: bytes) -> bytes:
+    """
+    Converts a v1-encoded update to the v2 encoding.
+
+    Args:
+        update: The v1-encoded update.
+
+    Returns:
+        The same update, v2-encoded.
+    """
+    return _convert_update_v1_to_v2(update)
+
+
+def convert_update_v2_to_v1(update: bytes) -> bytes:
+    """
+    Converts a v2-encoded update to the v1 encoding.
+
+    Args:
+        update: The v2-encoded update.
+
+    Returns:
+        The same update, v1-encoded.
+    """
+    return _convert_update_v2_to_v1(update)
+
+
+def decode_update(update: bytes) -> dict[str, Any]:
+    """
+    Decodes an update into its blocks and delete set, for inspection.
+    This is the equivalent of Yjs' `decodeUpdate`.
+
+    Each block of `structs` is a `dict` with the following keys:
+
+    - `type`: `"item"`, `"gc"` or `"skip"`.
+    - `client`, `clock`: the ID of the block's first element.
+    - `length`: the number of elements in the block.
+
+    Items also have the following keys:
+
+    - `content`: the content kind (`"any"`, `"binary"`, `"deleted"`, `"doc"`, `"embed"`,
+      `"format"`, `"json"`, `"string"` or `"type"`).
+    - `value`: the content itself (the type name for `"type"`, the GUID for `"doc"`,
+      a `(key, value)` tuple for `"format"`).
+    - `origin`, `right_origin`: the `(client, clock)` IDs of the left and right
+      neighbors at insertion time, if any.
+    - `parent`: the root type name or the `(client, clock)` ID of the parent, or None
+      if it is the parent of the origins.
+    - `parent_sub`: the key in the parent map, if any.
+
+    Args:
+        update: The update to decode.
+
+    Returns:
+        A `dict` with the list of blocks as `structs`, and the delete set as `delete_set`,
+            mapping client IDs to lists of `(clock, length)` ranges.
+    """
+    return _decode_update(update)
+
+
+def decode_update_v2(update: bytes) -> dict[str, Any]:
+    """
+    Decodes a v2-encoded update into its blocks and delete set, for inspection.
+    See [decode_update()][pycrdt.decode_update] for the returned structure.
+
+    Args:
+        update: The v2-encoded update to decode.
+
+    Returns:
+        A `dict` with the list of blocks as `structs`, and the delete set as `delete_set`.
+    """
+    return _decode_update_v2(update)
@@ -1,6 +1,6 @@
 use pyo3::prelude::*;
 use pyo3::IntoPyObjectExt;
-use pyo3::exceptions::{PyValueError, PyTypeError};
+use pyo3::exceptions::PyValueError;
 use pyo3::types::{PyList, PyString};
 use yrs::{
     Any,
@@ -18,6 +18,8 @@ use yrs::types::ToJson;
 use yrs::types::text::TextPrelim;
 use yrs::types::array::{ArrayPrelim, ArrayEvent as _ArrayEvent};
 use yrs::types::map::MapPrelim;
+use crate::exceptions::ConversionError;
+use crate::transaction::TransactionAccess;
 use crate::transaction::Transaction;
 use crate::subscription::Subscription;
 use crate::type_conversions::{events_into_py, py_to_any, ToPython};
@@ -42,18 +44,17 @@ impl Array {
 #[pymethods]
 impl Array {
     fn len(&self, txn: &mut Transaction)  -> PyResult<u32> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let len = self.array.len(t);
         Ok(len)
     }
 
     fn insert(&self, txn: &mut Transaction, index: u32, value: &Bound<'_, PyAny>) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
-        match py_to_any(value) {
-            Any::Undefined => Err(PyTypeError::new_err("Type not supported")),
+        let mut t = _t.txn_mut()?;
+        match py_to_any(value)? {
+            Any::Undefined => Err(ConversionError::new_err("Type not supported")),
             v => {
                 self.array.insert(&mut t, index, v);
                 Ok(())
@@ -63,7 +64,7 @@ impl Array {
 
     fn insert_text_prelim(&self, txn: &mut Transaction, index: u32) -> PyResult<Text> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated = self.array.insert(&mut t, index, TextPrelim::new(""));
         let shared = Text::from(integrated);
         Ok(shared)
@@ -71,7 +72,7 @@ impl Array {
 
     fn insert_array_prelim(&self, txn: &mut Transaction, index: u32) -> PyResult<Array> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated = self.array.insert(&mut t, index, ArrayPrelim::default());
         let shared = Array::from(integrated);
         Ok(shared)
@@ -79,7 +80,7 @@ impl Array {
 
     fn insert_map_prelim(&self, txn: &mut Transaction, index: u32) -> PyResult<Map> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated = self.array.insert(&mut t, index, MapPrelim::default());
         let shared = Map::from(integrated);
         Ok(shared)
@@ -87,24 +88,25 @@ impl Array {
 
     fn insert_xmlfragment_prelim(&self, txn: &mut Transaction, index: u32) -> PyResult<XmlFragment> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated = self.array.insert(&mut t, index, XmlFragmentPrelim::default());
         let shared = XmlFragment::from(integrated);
         Ok(shared)
     }
 
     fn insert_xmlelement_prelim(&self, _txn: &mut Transaction, _index: u32) -> PyResult<Py<PyAny>> {
-        Err(PyTypeError::new_err("Cannot insert an XmlElement into an array - insert it into an XmlFragment and insert that into the array"))
+        Err(ConversionError::new_err("Cannot insert an XmlElement into an array - insert it into an XmlFragment and insert that into the array"))
     }
 
     fn insert_xmltext_prelim(&self, _txn: &mut Transaction, _index: u32) -> PyResult<Py<PyAny>> {
-        Err(PyTypeError::new_err("Cannot insert an XmlText into an array - insert it into an XmlFragment and insert that into the array"))
+        Err(ConversionError::new_err("Cannot insert an XmlText into an array - insert it into an XmlFragment and insert that into the array"))
     }
 
     fn insert_doc(&self, txn: &mut Transaction, index: u32, doc: &Bound<'_, PyAny>) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
-        let d1: Doc = doc.extract().unwrap();
+        let mut t = _t.txn_mut()?;
+        let d1: Doc = doc.extract()
+            .map_err(|_| ConversionError::new_err("Expected Doc object"))?;
         let d2: _Doc = d1.doc;
         let doc_ref = self.array.insert(&mut t, index, d2);
         doc_ref.load(t);
@@ -113,15 +115,14 @@ impl Array {
 
     fn remove_range(&self, txn: &mut Transaction, index: u32, len: u32) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         self.array.remove_range(&mut t, index, len);
         Ok(())
     }
 
     fn get<'py>(&self, py: Python<'py>, txn: &mut Transaction, index: u32) -> PyResult<Bound<'py, PyAny>> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let v = self.array.get(t, index);
         if v == None {
             Err(PyValueError::new_err("Index error"))
@@ -130,18 +131,17 @@ impl Array {
         }
     }
 
-    fn to_json<'py>(&mut self, py: Python<'py>, txn: &mut Transaction) -> Bound<'py, PyString> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+    fn to_json<'py>(&mut self, py: Python<'py>, txn: &mut Transaction) -> PyResult<Bound<'py, PyString>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let mut s = String::new();
         self.array.to_json(t).to_json(&mut s);
-        PyString::new(py, s.as_str())
+        Ok(PyString::new(py, s.as_str()))
     }
 
     fn sticky_index<'py>(&self, py: Python<'py>, txn: &mut Transaction, index: u32, assoc: i8) -> PyResult<Py<StickyIndex>> {
         let mut _t = txn.transaction();
-        let t = _t.as_mut().unwrap().as_mut();
+        let t = _t.txn_mut()?;
         let _assoc: Assoc;
         match assoc {
             0 => _assoc = Assoc::After,
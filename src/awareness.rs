@@ -0,0 +1,247 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBytes, PyDict};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use yrs::ClientID;
+use yrs::sync::{Awareness as _Awareness, AwarenessUpdate, Clock};
+use yrs::sync::awareness::{AwarenessUpdateEntry, Event};
+use yrs::sync::time::SystemClock;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use crate::doc::Doc;
+use crate::exceptions::{DecodeError, IntegrationError};
+use crate::subscription::Subscription;
+use crate::type_conversions::{json_to_py, py_to_json_string};
+use crate::undo::PythonClock;
+
+/// The awareness of a document, as defined by the y-protocols awareness protocol.
+#[pyclass(unsendable)]
+pub struct Awareness {
+    awareness: _Awareness,
+    timestamp: Option<Py<PyAny>>,
+    origin: Rc<RefCell<Option<Py<PyAny>>>>,
+}
+
+impl Awareness {
+    fn now(&self, py: Python<'_>) -> u64 {
+        match &self.timestamp {
+            Some(timestamp) => PythonClock { timestamp: timestamp.clone_ref(py) }.now(),
+            None => SystemClock.now(),
+        }
+    }
+
+    /// Run `f` with `origin` made available to the observers it triggers.
+    fn with_origin<T>(&mut self, origin: Option<Py<PyAny>>, f: impl FnOnce(&mut _Awareness) -> T) -> T {
+        let prev = self.origin.replace(origin);
+        let result = f(&mut self.awareness);
+        self.origin.replace(prev);
+        result
+    }
+
+    fn subscribe(&self, f: Py<PyAny>) -> impl FnMut(&_Awareness, &Event, Option<&yrs::Origin>) + 'static {
+        let origin = self.origin.clone();
+        move |_, event, _| {
+            Python::attach(|py| {
+                let event = event_into_py(py, event);
+                let origin = origin.borrow().as_ref().map(|origin| origin.clone_ref(py));
+                if let Err(err) = f.call1(py, (event, origin)) {
+                    err.restore(py)
+                }
+            })
+        }
+    }
+
+    /// Remove the state of remote clients the way a peer would: by applying a `null` state at
+    /// their current clock, so that a single event is emitted for all of them. The local client
+    /// is skipped, as its clock would be increased instead.
+    fn remove_remote_states(&mut self, client_ids: &[u64]) -> PyResult<()> {
+        let states: HashMap<u64, _> = self.awareness
+            .iter()
+            .map(|(client_id, state)| (client_id.get(), state))
+            .collect();
+        let mut clients = HashMap::new();
+        let local_client_id = self.awareness.client_id().get();
+        for client_id in client_ids.iter().filter(|client_id| **client_id != local_client_id) {
+            if let Some(state) = states.get(client_id) && state.data.is_some() {
+                let entry = AwarenessUpdateEntry { clock: state.clock, json: "null".into() };
+                clients.insert(ClientID::new(*client_id), entry);
+            }
+        }
+        if !clients.is_empty() {
+            self.awareness
+                .apply_update(AwarenessUpdate { clients })
+                .map_err(|e| IntegrationError::new_err(format!("Cannot remove states: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+fn event_into_py<'py>(py: Python<'py>, event: &Event) -> Bound<'py, PyDict> {
+    let ids = |client_ids: &[ClientID]| client_ids.iter().map(|client_id| client_id.get()).collect::<Vec<u64>>();
+    let result = PyDict::new(py);
+    result.set_item("added", ids(event.added())).unwrap();
+    result.set_item("updated", ids(event.updated())).unwrap();
+    result.set_item("removed", ids(event.removed())).unwrap();
+    result
+}
+
+#[pymethods]
+impl Awareness {
+    #[new]
+    #[pyo3(signature = (doc, timestamp=None))]
+    fn new(py: Python<'_>, doc: &Doc, timestamp: Option<Py<PyAny>>) -> PyResult<Self> {
+        let mut awareness = match &timestamp {
+            Some(timestamp) => _Awareness::with_clock(doc.doc.clone(), PythonClock { timestamp: timestamp.clone_ref(py) }),
+            None => _Awareness::with_clock(doc.doc.clone(), SystemClock),
+        };
+        // like y-protocols, start with an empty local state at clock 0
+        let mut clients = HashMap::new();
+        clients.insert(awareness.client_id(), AwarenessUpdateEntry { clock: 0, json: "{}".into() });
+        awareness
+            .apply_update(AwarenessUpdate { clients })
+            .map_err(|e| IntegrationError::new_err(format!("Cannot set local state: {}", e)))?;
+        Ok(Awareness { awareness, timestamp, origin: Rc::new(RefCell::new(None)) })
+    }
+
+    #[getter]
+    fn client_id(&self) -> u64 {
+        self.awareness.client_id().get()
+    }
+
+    /// The states of all the clients that are not removed, indexed by client ID.
+    #[getter]
+    fn states<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let result = PyDict::new(py);
+        for (client_id, state) in self.awareness.iter() {
+            if let Some(data) = state.data {
+                result.set_item(client_id.get(), json_to_py(py, &data)?)?;
+            }
+        }
+        Ok(result)
+    }
+
+    /// The `clock` and `lastUpdated` metadata of all the known clients, indexed by client ID.
+    #[getter]
+    fn meta<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let result = PyDict::new(py);
+        for (client_id, state) in self.awareness.iter() {
+            let meta = PyDict::new(py);
+            meta.set_item("clock", state.clock)?;
+            meta.set_item("lastUpdated", state.last_updated)?;
+            result.set_item(client_id.get(), meta)?;
+        }
+        Ok(result)
+    }
+
+    fn get_local_state<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.awareness
+            .local_state_raw()
+            .map(|json| json_to_py(py, &json))
+            .transpose()
+    }
+
+    #[pyo3(signature = (state, origin=None))]
+    fn set_local_state(&mut self, state: &Bound<'_, PyAny>, origin: Option<Py<PyAny>>) -> PyResult<()> {
+        if state.is_none() {
+            self.with_origin(origin, |awareness| awareness.clean_local_state());
+        } else {
+            let json = py_to_json_string(state)?;
+            self.with_origin(origin, |awareness| awareness.set_local_state_raw(json));
+        }
+        Ok(())
+    }
+
+    /// Encode the states of the given clients, or of all the clients that are not removed.
+    #[pyo3(signature = (client_ids=None))]
+    fn encode_update<'py>(&self, py: Python<'py>, client_ids: Option<Vec<u64>>) -> PyResult<Bound<'py, PyBytes>> {
+        let update = match client_ids {
+            Some(client_ids) => self.awareness.update_with_clients(client_ids.into_iter().map(ClientID::new)),
+            None => self.awareness.update(),
+        };
+        let update = update.map_err(|e| PyValueError::new_err(format!("Cannot encode update: {}", e)))?;
+        Ok(PyBytes::new(py, &update.encode_v1()))
+    }
+
+    #[pyo3(signature = (update, origin=None))]
+    fn apply_update(&mut self, update: &Bound<'_, PyBytes>, origin: Option<Py<PyAny>>) -> PyResult<()> {
+        let mut update = AwarenessUpdate::decode_v1(update.as_bytes())
+            .map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))?;
+        let client_id = self.awareness.client_id();
+        // a remote client cannot remove the local state: apply it with an increased clock
+        // instead, so that update observers broadcast that this client still exists
+        if let Some(entry) = update.clients.get_mut(&client_id)
+            && entry.json.as_ref() == "null"
+            && let Some(data) = self.awareness.local_state_raw()
+            && let Some((clock, _)) = self.awareness.meta(client_id)
+            && clock <= entry.clock
+        {
+            *entry = AwarenessUpdateEntry { clock: entry.clock.saturating_add(1), json: data };
+        }
+        self.with_origin(origin, |awareness| awareness.apply_update(update))
+            .map_err(|e| IntegrationError::new_err(format!("Cannot apply update: {}", e)))
+    }
+
+    #[pyo3(signature = (client_ids, origin=None))]
+    fn remove_states(&mut self, client_ids: Vec<u64>, origin: Option<Py<PyAny>>) -> PyResult<()> {
+        let local_client_id = self.client_id();
+        let prev = self.origin.replace(origin);
+        let result = self.remove_remote_states(&client_ids);
+        // the local client is marked as disconnected, which increases its clock once
+        if client_ids.contains(&local_client_id) && self.awareness.local_state_raw().is_some() {
+            self.awareness.clean_local_state();
+        }
+        self.origin.replace(prev);
+        result
+    }
+
+    /// Renew the local state if it was last updated more than half `outdated_timeout` (in
+    /// milliseconds) ago, and remove the remote clients that were not updated for
+    /// `outdated_timeout`. Returns the IDs of the removed clients.
+    #[pyo3(signature = (outdated_timeout, origin=None))]
+    fn remove_outdated_states(&mut self, py: Python<'_>, outdated_timeout: u64, origin: Option<Py<PyAny>>) -> PyResult<Vec<u64>> {
+        let now = self.now(py);
+        let local_client_id = self.client_id();
+        let mut outdated = Vec::new();
+        let mut renew = None;
+        for (client_id, state) in self.awareness.iter() {
+            let Some(data) = state.data else {
+                continue;
+            };
+            let elapsed = now.saturating_sub(state.last_updated);
+            if client_id.get() == local_client_id {
+                if outdated_timeout / 2 <= elapsed {
+                    renew = Some(data);
+                }
+            } else if outdated_timeout <= elapsed {
+                outdated.push(client_id.get());
+            }
+        }
+        // the renewal and the removals are reported with the same origin
+        let prev = self.origin.replace(origin);
+        if let Some(data) = renew {
+            self.awareness.set_local_state_raw(data);
+        }
+        let result = if outdated.is_empty() { Ok(()) } else { self.remove_remote_states(&outdated) };
+        self.origin.replace(prev);
+        result?;
+        Ok(outdated)
+    }
+
+    /// Observe the changes of the client states, called with the added, updated (whose state
+    /// actually changed) and removed client IDs, and the origin.
+    pub fn observe_change(&mut self, py: Python<'_>, f: Py<PyAny>) -> PyResult<Py<Subscription>> {
+        let sub = self.awareness.on_change(self.subscribe(f));
+        let s: Py<Subscription> = Py::new(py, Subscription::from(sub))?;
+        Ok(s)
+    }
+
+    /// Observe the updates of the client states, called with the added, updated (including
+    /// clock renewals) and removed client IDs, and the origin.
+    pub fn observe_update(&mut self, py: Python<'_>, f: Py<PyAny>) -> PyResult<Py<Subscription>> {
+        let sub = self.awareness.on_update(self.subscribe(f));
+        let s: Py<Subscription> = Py::new(py, Subscription::from(sub))?;
+        Ok(s)
+    }
+}
@@ -0,0 +1,322 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyRange, PySet, PyTuple};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex, OnceLock};
+use yrs::branch::{Branch, BranchID, BranchPtr};
+use yrs::encoding::read::Error;
+use yrs::types::{Change, DeepObservable, Delta, Event, Events, Path, PathSegment, TypeRef};
+use yrs::{
+    Any, Assoc, Doc, Map, MapRef, OffsetKind, Origin, Out, ReadTxn, StickyIndex, Subscription,
+    Transact, TransactionMut, WriteTxn,
+};
+use crate::doc::{root_type_ref, store_key};
+use crate::exceptions::TransactionError;
+use crate::update::update_items;
+
+/// A key, or a range of indices, of a shared type that was changed in a transaction.
+#[derive(Clone, Debug)]
+pub enum Changed {
+    Key(Arc<str>),
+    Range(u32, u32),
+}
+
+/// The shared types changed in a transaction, by path from their root type.
+pub type Changes = Vec<(Path, Vec<Changed>)>;
+
+/// Records the shared types changed in the transactions of a document by observing all of its
+/// root types deeply, which is the only way to get their changed keys and indices. The root
+/// types are only observed while the observers of a transaction are called.
+pub struct ChangedTypes {
+    changes: Rc<RefCell<Changes>>,
+    store: usize,
+    _observe: Subscription,
+    _unobserve: Subscription,
+}
+
+/// The documents whose changed types are recorded, by the address of their store, with their
+/// number of recorders.
+fn recorded() -> &'static Mutex<HashMap<usize, usize>> {
+    static RECORDED: OnceLock<Mutex<HashMap<usize, usize>>> = OnceLock::new();
+    RECORDED.get_or_init(Mutex::default)
+}
+
+impl ChangedTypes {
+    pub fn new(doc: &Doc) -> PyResult<Self> {
+        let changes: Rc<RefCell<Changes>> = Rc::default();
+        let key = Origin::from(Rc::as_ptr(&changes) as usize);
+        let recorder = Rc::downgrade(&changes);
+        let observer_key = key.clone();
+        // root types can be created in any transaction, so they are (re-)observed before the
+        // observers of each transaction are called
+        let observe = doc
+            .observe_before_observer_calls(move |txn| {
+                for (name, root) in txn.root_refs() {
+                    if let Some(branch) = root.try_branch() {
+                        let name: Arc<str> = name.into();
+                        let recorder = recorder.clone();
+                        let f = move |txn: &TransactionMut, events: &Events| {
+                            record(&recorder, &name, txn, events)
+                        };
+                        BranchPtr::from(branch).observe_deep_with(observer_key.clone(), f);
+                    }
+                }
+            })
+            .map_err(|_| TransactionError::new_err("Cannot observe a document in a transaction"))?;
+        // the root types are unobserved once their observers were called, so that they don't
+        // accumulate deep observers that outlive the recorder
+        let unobserve = doc
+            .observe_after_transaction(move |txn| {
+                for (_, root) in txn.root_refs() {
+                    if let Some(branch) = root.try_branch() {
+                        RootType(BranchPtr::from(branch)).unobserve_deep(key.clone());
+                    }
+                }
+            })
+            .map_err(|_| TransactionError::new_err("Cannot observe a document in a transaction"))?;
+        let store = doc.try_transact()
+            .map(|txn| store_key(&txn))
+            .map_err(|_| TransactionError::new_err("Cannot observe a document in a transaction"))?;
+        *recorded().lock().unwrap().entry(store).or_default() += 1;
+        Ok(ChangedTypes { changes, store, _observe: observe, _unobserve: unobserve })
+    }
+
+    /// Take the changes recorded since the last call.
+    pub fn take(&self) -> Changes {
+        std::mem::take(&mut self.changes.borrow_mut())
+    }
+}
+
+impl Drop for ChangedTypes {
+    fn drop(&mut self) {
+        let mut recorded = recorded().lock().unwrap();
+        if let Some(count) = recorded.get_mut(&self.store) {
+            *count -= 1;
+            if *count == 0 {
+                recorded.remove(&self.store);
+            }
+        }
+    }
+}
+
+/// Give their inferred type to the root types that were received from a remote document but not
+/// accessed yet, if the changed types of the document are recorded: yrs has no events for a root
+/// type without a type, so its changes could not be recorded otherwise.
+pub fn type_remote_roots(txn: &mut TransactionMut) {
+    if !recorded().lock().unwrap().contains_key(&store_key(txn)) {
+        return;
+    }
+    let untyped: Vec<(Arc<str>, TypeRef)> = txn
+        .root_refs()
+        .filter(|(_, root)| matches!(root, Out::UndefinedRef(_)))
+        .filter_map(|(name, root)| Some((name.into(), root_type_ref(txn, &root)?)))
+        .collect();
+    for (name, type_ref) in untyped {
+        match type_ref {
+            TypeRef::Text => { txn.get_or_insert_text(name); }
+            TypeRef::Array => { txn.get_or_insert_array(name); }
+            TypeRef::Map => { txn.get_or_insert_map(name); }
+            TypeRef::XmlFragment => { txn.get_or_insert_xml_fragment(name); }
+            _ => {}
+        }
+    }
+}
+
+/// A root type, which only exposes the deep observers of its branch.
+struct RootType(BranchPtr);
+
+impl AsRef<Branch> for RootType {
+    fn as_ref(&self) -> &Branch {
+        self.0.as_ref()
+    }
+}
+
+impl DeepObservable for RootType {}
+
+fn record(recorder: &Weak<RefCell<Changes>>, root: &Arc<str>, txn: &TransactionMut, events: &Events) {
+    let Some(changes) = recorder.upgrade() else {
+        return;
+    };
+    let offset_kind = txn.doc().offset_kind();
+    let mut changes = changes.borrow_mut();
+    for event in events.iter() {
+        let mut path = event.path();
+        path.push_front(PathSegment::Key(root.clone()));
+        let mut changed = Vec::new();
+        match event {
+            Event::Text(e) => delta_ranges(e.delta(txn), offset_kind, &mut changed),
+            Event::Array(e) => change_ranges(e.delta(txn), &mut changed),
+            Event::Map(e) => changed.extend(e.keys(txn).keys().cloned().map(Changed::Key)),
+            Event::XmlFragment(e) => {
+                changed.extend(e.keys(txn).keys().cloned().map(Changed::Key));
+                change_ranges(e.delta(txn), &mut changed);
+            }
+            Event::XmlText(e) => {
+                changed.extend(e.keys(txn).keys().cloned().map(Changed::Key));
+                delta_ranges(e.delta(txn), offset_kind, &mut changed);
+            }
+        }
+        changes.push((path, changed));
+    }
+}
+
+/// Deletions are recorded as empty ranges at the index where the content was removed.
+fn change_ranges(delta: &[Change], changed: &mut Vec<Changed>) {
+    let mut index = 0;
+    for change in delta {
+        match change {
+            Change::Added(values) => {
+                let len = values.len() as u32;
+                changed.push(Changed::Range(index, index + len));
+                index += len;
+            }
+            Change::Removed(_) => changed.push(Changed::Range(index, index)),
+            Change::Retain(len) => index += len,
+        }
+    }
+}
+
+fn delta_ranges(delta: &[Delta], offset_kind: OffsetKind, changed: &mut Vec<Changed>) {
+    let mut index = 0;
+    for d in delta {
+        match d {
+            Delta::Inserted(value, _) => {
+                let len = match value {
+                    Out::Any(Any::String(s)) => match offset_kind {
+                        OffsetKind::Bytes => s.len() as u32,
+                        OffsetKind::Utf16 => s.encode_utf16().count() as u32,
+                    },
+                    _ => 1,
+                };
+                changed.push(Changed::Range(index, index + len));
+                index += len;
+            }
+            Delta::Deleted(_) => changed.push(Changed::Range(index, index)),
+            Delta::Retain(len, attrs) => {
+                if attrs.is_some() {
+                    changed.push(Changed::Range(index, index + len));
+                }
+                index += len;
+            }
+        }
+    }
+}
+
+/// The paths of the shared types changed so far in a transaction that is not committed yet, and
+/// cannot be observed. They are the parents of the items that were inserted or deleted.
+pub fn pending_changed_types(txn: &TransactionMut) -> Result<Vec<Path>, Error> {
+    let mut items = update_items(&txn.encode_update_v1())?;
+    let deleted = txn.delete_set().diff(txn.insert_set());
+    if !deleted.is_empty() {
+        // the items that existed before the transaction are not in its update, so they are
+        // read from the document, starting at the first deleted one of each client
+        let mut state_vector = txn.state_vector();
+        for (client, ranges) in deleted.iter() {
+            if let Some(range) = ranges.iter().next() {
+                state_vector.set_min(*client, range.start);
+            }
+        }
+        let existing = update_items(&txn.encode_diff_v1(&state_vector))?;
+        items.extend(existing.into_iter().filter(|(id, len)| {
+            deleted.iter().any(|(client, ranges)| {
+                *client == id.client
+                    && ranges.iter().any(|range| range.start < id.clock + len && id.clock < range.end)
+            })
+        }));
+    }
+    let mut branches = HashSet::new();
+    for (id, _) in items {
+        if let Some(offset) = StickyIndex::from_id(id, Assoc::After).get_offset(txn) {
+            branches.insert(offset.branch);
+        }
+    }
+    Ok(branches.into_iter().filter_map(|branch| branch_path(txn, branch)).collect())
+}
+
+/// The path of a shared type from its root type, if it was not deleted.
+fn branch_path<T: ReadTxn>(txn: &T, mut branch: BranchPtr) -> Option<Path> {
+    let mut path = Path::new();
+    loop {
+        if branch.is_deleted() {
+            return None;
+        }
+        match branch.id() {
+            BranchID::Root(name) => {
+                path.push_front(PathSegment::Key(name));
+                return Some(path);
+            }
+            BranchID::Nested(id) => {
+                let offset = StickyIndex::from_id(id, Assoc::After).get_offset(txn)?;
+                let parent = offset.branch;
+                let segment = if let TypeRef::Map = parent.type_ref() {
+                    let map = MapRef::from(parent);
+                    let (key, _) = map.iter(txn).find(|(_, value)| out_branch(value) == Some(branch))?;
+                    PathSegment::Key(key.into())
+                } else {
+                    PathSegment::Index(offset.index)
+                };
+                path.push_front(segment);
+                branch = parent;
+            }
+        }
+    }
+}
+
+fn out_branch(value: &Out) -> Option<BranchPtr> {
+    match value {
+        Out::YText(v) => Some(BranchPtr::from(AsRef::<Branch>::as_ref(v))),
+        Out::YArray(v) => Some(BranchPtr::from(AsRef::<Branch>::as_ref(v))),
+        Out::YMap(v) => Some(BranchPtr::from(AsRef::<Branch>::as_ref(v))),
+        Out::YXmlElement(v) => Some(BranchPtr::from(AsRef::<Branch>::as_ref(v))),
+        Out::YXmlFragment(v) => Some(BranchPtr::from(AsRef::<Branch>::as_ref(v))),
+        Out::YXmlText(v) => Some(BranchPtr::from(AsRef::<Branch>::as_ref(v))),
+        _ => None,
+    }
+}
+
+/// The paths of changed types, as a set of tuples.
+pub fn paths_into_py<'py>(py: Python<'py>, paths: &[Path]) -> PyResult<Bound<'py, PySet>> {
+    let result = PySet::empty(py)?;
+    for path in paths {
+        result.add(path_into_py(py, path))?;
+    }
+    Ok(result)
+}
+
+fn path_into_py<'py>(py: Python<'py>, path: &Path) -> Bound<'py, PyTuple> {
+    let segments = path.iter().map(|segment| match segment {
+        PathSegment::Key(key) => key.as_ref().into_pyobject(py).unwrap().into_any(),
+        PathSegment::Index(index) => index.into_pyobject(py).unwrap().into_any(),
+    });
+    PyTuple::new(py, segments).unwrap()
+}
+
+/// The changed types, as a dict mapping their paths to their changed keys and index ranges.
+pub fn changed_types_into_py<'py>(py: Python<'py>, changes: &Changes) -> PyResult<Bound<'py, PyDict>> {
+    let result = PyDict::new(py);
+    for (path, changed) in changes {
+        let items = PyList::empty(py);
+        for change in changed {
+            match change {
+                Changed::Key(key) => items.append(key.as_ref())?,
+                Changed::Range(start, end) => items.append(PyRange::new(py, *start as isize, *end as isize)?)?,
+            }
+        }
+        result.set_item(path_into_py(py, path), items)?;
+    }
+    Ok(result)
+}
+
+/// The paths of the changed types and of all their parent types.
+pub fn changed_parent_types_into_py<'py>(py: Python<'py>, changes: &Changes) -> PyResult<Bound<'py, PySet>> {
+    let result = PySet::empty(py)?;
+    for (path, _) in changes {
+        let mut parent = path.clone();
+        while !parent.is_empty() {
+            result.add(path_into_py(py, &parent))?;
+            parent.pop_back();
+        }
+    }
+    Ok(result)
+}
@@ -1,20 +1,29 @@
 use pyo3::prelude::*;
 use pyo3::IntoPyObjectExt;
-use pyo3::exceptions::{PyRuntimeError, PyValueError};
-use pyo3::types::{PyBool, PyBytes, PyDict, PyInt, PyList, PyString};
-use std::sync::Arc;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyInt, PyList, PySet, PyString};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
 use yrs::{
-    ClientID, Doc as _Doc, Options, ReadTxn, StateVector, SubdocsEvent as _SubdocsEvent, Transact, TransactionCleanupEvent, TransactionMut, Update, WriteTxn
+    merge_updates_v1, Array as _Array, ArrayRef, ClientID, Doc as _Doc, GetString, Map as _Map, MapRef, OffsetKind, Options, Out, ReadTxn, TextRef, XmlElementRef, XmlFragmentRef, XmlTextRef, StateVector, Store, SubdocsEvent as _SubdocsEvent, Transact, TransactionCleanupEvent, TransactionMut, Update, WriteTxn, ID, IdSet as _IdSet
 };
-use yrs::updates::encoder::{Encode, Encoder};
+use yrs::updates::encoder::Encode;
 use yrs::updates::decoder::Decode;
+use crate::changed_types::{changed_parent_types_into_py, changed_types_into_py, type_remote_roots, ChangedTypes, Changes};
+use crate::exceptions::{DecodeError, IntegrationError, TransactionError};
+use crate::transaction::TransactionAccess;
 use crate::text::Text;
 use crate::array::Array;
 use crate::map::Map;
-use crate::transaction::Transaction;
+use crate::transaction::{Detached, Transaction, TransactionAcquisition};
 use crate::subscription::Subscription;
-use crate::type_conversions::ToPython;
-use crate::xml::XmlFragment;
+use crate::update::decode_v2;
+use crate::snapshot::{encode_state_from_snapshot, replay_events};
+use crate::type_conversions::{event_into_py, ToPython};
+use crate::undo::IdSet;
+use crate::xml::{XmlElement, XmlFragment, XmlText};
+use yrs::branch::{Branch, BranchPtr};
+use yrs::types::TypeRef;
 
 
 #[pyclass(from_py_object)]
@@ -28,26 +37,23 @@ impl Doc {
         Doc { doc }
     }
     /// Internal: create a new Doc from a Snapshot and an original Doc
-    pub fn _from_snapshot_impl(original: &Self, snapshot: &crate::snapshot::Snapshot) -> Self {
+    pub fn _from_snapshot_impl(original: &Self, snapshot: &crate::snapshot::Snapshot) -> PyResult<Self> {
         // Create a new Doc with the same options as the original
         let mut options = yrs::Options::default();
         options.client_id = original.doc.client_id();
         options.skip_gc = original.doc.skip_gc();
+        options.offset_kind = original.doc.offset_kind();
         if let Some(collection_id) = original.doc.collection_id() {
             options.collection_id = Some(collection_id);
         }
         options.guid = original.doc.guid();
         let new_doc = yrs::Doc::with_options(options);
         // Encode the update from the snapshot
-        let mut encoder = yrs::updates::encoder::EncoderV1::new();
-        {
-            let txn = original.doc.transact();
-            txn.encode_state_from_snapshot(&snapshot.snapshot, &mut encoder).unwrap();
-        }
-        let update = yrs::Update::decode_v1(&encoder.to_vec()).unwrap();
+        let update = encode_state_from_snapshot(&original.doc.transact(), &snapshot.snapshot)?;
         {
             let mut txn = new_doc.transact_mut();
-            txn.apply_update(update).unwrap();
+            txn.apply_update(update)
+                .map_err(|e| IntegrationError::new_err(format!("Cannot apply update: {}", e)))?;
         }
         // Ensure root types are present in the restored doc (recreate them if needed)
         // Copy root type names and types from the original doc
@@ -62,17 +68,21 @@ impl Doc {
             }
         }
         drop(txn_orig);
-        Doc { doc: new_doc }
+        Ok(Doc::from(new_doc))
     }
 }
 
 #[pymethods]
 impl Doc {
     #[new]
+    #[pyo3(signature = (client_id, skip_gc, guid, offset_kind=None, should_load=None, auto_load=None))]
     fn new(
         client_id: &Bound<'_, PyAny>,
         skip_gc: &Bound<'_, PyAny>,
         guid: &Bound<'_, PyAny>,
+        offset_kind: Option<&Bound<'_, PyAny>>,
+        should_load: Option<bool>,
+        auto_load: Option<bool>,
     ) -> PyResult<Self> {
         let mut options = Options::default();
         if !client_id.is_none() {
@@ -100,14 +110,32 @@ impl Doc {
                 .map_err(|_| PyValueError::new_err("guid must be a valid string"))?;
             options.guid = Arc::<str>::from(guid);
         }
+        if let Some(offset_kind) = offset_kind && !offset_kind.is_none() {
+            let offset_kind: String = offset_kind
+                .cast::<PyString>()
+                .map_err(|_| PyValueError::new_err("offset_kind must be a string"))?
+                .extract()
+                .map_err(|_| PyValueError::new_err("offset_kind must be a valid string"))?;
+            options.offset_kind = match offset_kind.as_str() {
+                "bytes" => OffsetKind::Bytes,
+                "utf16" => OffsetKind::Utf16,
+                _ => return Err(PyValueError::new_err("offset_kind must be \"bytes\" or \"utf16\"")),
+            };
+        }
+        if let Some(should_load) = should_load {
+            options.should_load = should_load;
+        }
+        if let Some(auto_load) = auto_load {
+            options.auto_load = auto_load;
+        }
         let doc = _Doc::with_options(options);
-        Ok(Doc { doc })
+        Ok(Doc::from(doc))
     }
 
     #[staticmethod]
     #[pyo3(name = "from_snapshot")]
     pub fn from_snapshot(py: Python<'_>, snapshot: PyRef<'_, crate::snapshot::Snapshot>, doc: PyRef<'_, Doc>) -> PyResult<Py<Doc>> {
-        let restored = Doc::_from_snapshot_impl(&doc, &snapshot);
+        let restored = Doc::_from_snapshot_impl(&doc, &snapshot)?;
         Py::new(py, restored)
     }
 
@@ -115,13 +143,90 @@ impl Doc {
         self.doc.guid().to_string()
     }
 
+    /// How the indices and lengths of texts are counted: in UTF-8 bytes or in UTF-16 code units.
+    fn offset_kind(&self) -> &'static str {
+        match self.doc.offset_kind() {
+            OffsetKind::Bytes => "bytes",
+            OffsetKind::Utf16 => "utf16",
+        }
+    }
+
     fn client_id(&mut self) -> u64 {
         self.doc.client_id().get()
     }
 
+    fn should_load(&self) -> bool {
+        self.doc.should_load()
+    }
+
+    fn auto_load(&self) -> bool {
+        self.doc.auto_load()
+    }
+
+    /// The document this document was inserted in as a subdocument, if any.
+    fn parent_doc(&self) -> PyResult<Option<Doc>> {
+        let txn = self.doc
+            .try_transact()
+            .map_err(|_| TransactionError::new_err("Already in a transaction"))?;
+        Ok(txn.parent_doc().map(Doc::from))
+    }
+
+    /// Request the loading of this document. If it is a subdocument, the parent document is
+    /// notified in `parent_txn`, or in a new transaction.
+    #[pyo3(signature = (parent_txn=None))]
+    fn load(&self, parent_txn: Option<&mut Transaction>) -> PyResult<()> {
+        if let Some(parent_txn) = parent_txn {
+            let mut _t = parent_txn.transaction();
+            self.doc.load(_t.txn_mut()?);
+        } else if let Some(parent) = self.parent_doc()? {
+            let mut txn = parent.doc
+                .try_transact_mut()
+                .map_err(|_| TransactionError::new_err("Parent document is already in a transaction"))?;
+            self.doc.load(&mut txn);
+        } else {
+            return Err(PyValueError::new_err("Only a subdocument can be loaded"));
+        }
+        Ok(())
+    }
+
+    /// Destroy this document. If it is a subdocument, it is replaced in the parent document
+    /// (in `parent_txn`, or in a new transaction) with a new one that is not loaded.
+    #[pyo3(signature = (parent_txn=None))]
+    fn destroy(&self, parent_txn: Option<&mut Transaction>) -> PyResult<()> {
+        // yrs blocks until it can open a transaction, and a read-only one is enough to check
+        // that it could (without committing anything)
+        if self.doc.try_transact().is_err() {
+            return Err(TransactionError::new_err("Already in a transaction"));
+        }
+        if let Some(parent_txn) = parent_txn {
+            let mut _t = parent_txn.transaction();
+            self.doc.destroy(Some(_t.txn_mut()?));
+        } else if let Some(parent) = self.parent_doc()? {
+            let mut txn = parent.doc
+                .try_transact_mut()
+                .map_err(|_| TransactionError::new_err("Parent document is already in a transaction"))?;
+            self.doc.destroy(Some(&mut txn));
+        } else {
+            self.doc.destroy(None);
+        }
+        Ok(())
+    }
+
+    fn subdocs(&self, txn: &Transaction) -> PyResult<Vec<Doc>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        Ok(t.subdocs().map(|doc| Doc::from(doc.clone())).collect())
+    }
+
+    fn subdoc_guids(&self, txn: &Transaction) -> PyResult<Vec<String>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        Ok(t.subdoc_guids().map(|guid| guid.to_string()).collect())
+    }
+
     fn get_or_insert_text(&mut self, py: Python<'_>, txn: &mut Transaction, name: &str) -> PyResult<Py<Text>> {
         let mut _t = txn.transaction();
-        let t = _t.as_mut().unwrap().as_mut();
+        let t = _t.txn_mut()?;
         let text = t.get_or_insert_text(name);
         let pytext: Py<Text> = Py::new(py, Text::from(text))?;
         Ok(pytext)
@@ -129,7 +234,7 @@ impl Doc {
 
     fn get_or_insert_array(&mut self, py: Python<'_>, txn: &mut Transaction, name: &str) -> PyResult<Py<Array>> {
         let mut _t = txn.transaction();
-        let t = _t.as_mut().unwrap().as_mut();
+        let t = _t.txn_mut()?;
         let shared = t.get_or_insert_array(name);
         let pyshared: Py<Array > = Py::new(py, Array::from(shared))?;
         Ok(pyshared)
@@ -137,16 +242,16 @@ impl Doc {
 
     fn get_or_insert_map(&mut self, py: Python<'_>, txn: &mut Transaction, name: &str) -> PyResult<Py<Map>> {
         let mut _t = txn.transaction();
-        let t = _t.as_mut().unwrap().as_mut();
+        let t = _t.txn_mut()?;
         let shared = t.get_or_insert_map(name);
         let pyshared: Py<Map> = Py::new(py, Map::from(shared))?;
         Ok(pyshared)
     }
 
-    fn get_or_insert_xml_fragment(&mut self, txn: &mut Transaction, name: &str) -> XmlFragment {
+    fn get_or_insert_xml_fragment(&mut self, txn: &mut Transaction, name: &str) -> PyResult<XmlFragment> {
         let mut _t = txn.transaction();
-        let t = _t.as_mut().unwrap().as_mut();
-        t.get_or_insert_xml_fragment(name).into()
+        let t = _t.txn_mut()?;
+        Ok(t.get_or_insert_xml_fragment(name).into())
     }
 
     fn create_transaction(&self, py: Python<'_>) -> PyResult<Py<Transaction>> {
@@ -154,60 +259,233 @@ impl Doc {
             let t: Py<Transaction> = Py::new(py, Transaction::from(txn))?;
             return Ok(t);
         }
-        Err(PyRuntimeError::new_err("Already in a transaction"))
+        Err(TransactionError::new_err("Already in a transaction"))
     }
 
-    fn create_transaction_with_origin(&self, py: Python<'_>, origin: i128) -> PyResult<Py<Transaction>> {
+    fn create_transaction_with_origin(&self, py: Python<'_>, origin: &[u8]) -> PyResult<Py<Transaction>> {
         if let Ok(txn) = self.doc.try_transact_mut_with(origin) {
             let t: Py<Transaction> = Py::new(py, Transaction::from(txn))?;
             return Ok(t);
         }
-        Err(PyRuntimeError::new_err("Already in a transaction"))
+        Err(TransactionError::new_err("Already in a transaction"))
     }
 
-    fn get_state(&self, txn: &Transaction) -> Py<PyAny> {
-        let mut _t = txn.transaction();
-        let t = _t.as_mut().unwrap().as_mut();
+    fn create_read_transaction(&self, py: Python<'_>) -> PyResult<Py<Transaction>> {
+        if let Ok(txn) = self.doc.try_transact() {
+            let t: Py<Transaction> = Py::new(py, Transaction::from(txn))?;
+            return Ok(t);
+        }
+        Err(TransactionError::new_err("Already in a transaction"))
+    }
+
+    /// Commit a transaction whose changes were rolled back. Its update (with the rolled back
+    /// content) is sent right away to the observers of the document updates, for the other
+    /// documents to stay in sync, but the other observers of the document are not called.
+    fn commit_aborted(&self, py: Python<'_>, txn: &mut Transaction) -> PyResult<()> {
+        let mut t0 = txn.transaction();
+        let t = t0.txn_mut()?;
+        let key = store_key(t);
+        aborting().lock().unwrap().insert(key);
+        t.commit();
+        aborting().lock().unwrap().remove(&key);
+        if let Some(err) = PyErr::take(py) {
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Queue for a transaction, which is acquired when the ongoing transactions are released.
+    #[pyo3(signature = (origin=None, read_only=false))]
+    fn acquire_transaction(&self, origin: Option<&[u8]>, read_only: bool) -> TransactionAcquisition {
+        TransactionAcquisition::new(&self.doc, origin, read_only)
+    }
+
+    fn get_state(&self, txn: &Transaction) -> PyResult<Py<PyAny>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let state = t.state_vector().encode_v1();
-        Python::attach(|py| PyBytes::new(py, &state).into())
+        Ok(Python::attach(|py| PyBytes::new(py, &state).into()))
+    }
+
+    fn get_update(&self, py: Python<'_>, txn: &Transaction, state: &Bound<'_, PyBytes>) -> PyResult<Py<PyAny>> {
+        let t0 = txn.transaction();
+        let mut t = Detached::new(t0.txn_ref()?);
+        let state = state.as_bytes();
+        let update = py.detach(|| {
+            let state_vector = StateVector::decode_v1(state)?;
+            Ok(t.txn().encode_diff_v1(&state_vector))
+        }).map_err(|_: yrs::encoding::read::Error| DecodeError::new_err("Cannot decode state"))?;
+        Ok(PyBytes::new(py, &update).into())
+    }
+
+    fn get_update_v2(&self, py: Python<'_>, txn: &Transaction, state: &Bound<'_, PyBytes>) -> PyResult<Py<PyAny>> {
+        let t0 = txn.transaction();
+        let mut t = Detached::new(t0.txn_ref()?);
+        let state = state.as_bytes();
+        let update = py.detach(|| {
+            let state_vector = StateVector::decode_v1(state)?;
+            Ok(t.txn().encode_diff_v2(&state_vector))
+        }).map_err(|_: yrs::encoding::read::Error| DecodeError::new_err("Cannot decode state"))?;
+        Ok(PyBytes::new(py, &update).into())
+    }
+
+    /// The events of the changes made since the given state, as a document at that state would
+    /// observe them when applying the update since that state. The document at that state has
+    /// the given deleted content (none by default). Returns the document after the changes,
+    /// which the events refer to, and a dict of the names of the changed root types to their
+    /// events.
+    #[pyo3(signature = (txn, state, delete_set=None))]
+    fn get_changes<'py>(
+        &self,
+        py: Python<'py>,
+        txn: &mut Transaction,
+        state: &Bound<'_, PyBytes>,
+        delete_set: Option<&IdSet>,
+    ) -> PyResult<(Doc, Bound<'py, PyDict>)> {
+        let state_vector = StateVector::decode_v1(state.as_bytes())
+            .map_err(|_| DecodeError::new_err("Cannot decode state"))?;
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        let update = Update::decode_v1(&t.encode_diff_v1(&state_vector))
+            .map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))?;
+        let delete_set = delete_set.map(|ds| ds.inner().clone()).unwrap_or_default();
+        let snapshot = yrs::Snapshot::new(state_vector, delete_set);
+        changes_into_py(py, t, &snapshot, update)
     }
 
-    fn get_update(&self, txn: &Transaction, state: &Bound<'_, PyBytes>) -> PyResult<Py<PyAny>> {
+    fn apply_update(&mut self, py: Python<'_>, txn: &mut Transaction, update: &Bound<'_, PyBytes>) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let t = _t.as_mut().unwrap().as_mut();
-        let state: &[u8] = state.extract()?;
-        let Ok(state_vector) = StateVector::decode_v1(&state) else { return Err(PyValueError::new_err("Cannot decode state")) };
-        let update = t.encode_diff_v1(&state_vector);
-        let bytes: Py<PyAny> = Python::attach(|py| PyBytes::new(py, &update).into());
-        Ok(bytes)
+        let mut t = Detached::new(_t.txn_mut()?);
+        let update = update.as_bytes();
+        py.detach(|| {
+            let u = Update::decode_v1(update)
+                .map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))?;
+            t.txn().apply_update(u)
+                .map_err(|e| IntegrationError::new_err(format!("Cannot apply update: {}", e)))?;
+            type_remote_roots(t.txn());
+            Ok(())
+        })
     }
 
-    fn apply_update(&mut self, txn: &mut Transaction, update: &Bound<'_, PyBytes>) -> PyResult<()> {
-        let u = Update::decode_v1(update.as_bytes())
-            .map_err(|e| PyValueError::new_err(format!("Cannot decode update: {}", e)))?;
+    fn apply_update_v2(&mut self, py: Python<'_>, txn: &mut Transaction, update: &Bound<'_, PyBytes>) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let t = _t.as_mut().unwrap().as_mut();
-        t.apply_update(u)
-            .map_err(|e| PyRuntimeError::new_err(format!("Cannot apply update: {}", e)))
+        let mut t = Detached::new(_t.txn_mut()?);
+        let update = update.as_bytes();
+        py.detach(|| {
+            let u = decode_v2(update)
+                .map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))?;
+            t.txn().apply_update(u)
+                .map_err(|e| IntegrationError::new_err(format!("Cannot apply update: {}", e)))?;
+            type_remote_roots(t.txn());
+            Ok(())
+        })
     }
 
-    fn roots(&self, py: Python<'_>, txn: &mut Transaction) -> Py<PyAny> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+    fn has_pending_structs(&self, txn: &Transaction) -> PyResult<bool> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        Ok(t.store().pending_update().is_some())
+    }
+
+    fn has_pending_delete_set(&self, txn: &Transaction) -> PyResult<bool> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        Ok(t.store().pending_ds().is_some())
+    }
+
+    /// The state vector of the missing updates that pending structs depend on.
+    fn get_missing_state(&self, py: Python<'_>, txn: &Transaction) -> PyResult<Option<Py<PyAny>>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        let missing = t.store().pending_update().map(|pending| PyBytes::new(py, &pending.missing.encode_v1()).into());
+        Ok(missing)
+    }
+
+    /// The update made of the pending structs and of the pending delete set.
+    fn get_pending_update(&self, py: Python<'_>, txn: &Transaction) -> PyResult<Option<Py<PyAny>>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        let store = t.store();
+        let mut updates = Vec::new();
+        if let Some(pending) = store.pending_update() {
+            updates.push(pending.update.encode_v1());
+        }
+        if let Some(pending_ds) = store.pending_ds() {
+            // an update without any struct, only followed by its delete set
+            let mut update = vec![0];
+            update.extend(pending_ds.encode_v1());
+            updates.push(update);
+        }
+        let update = match updates.len() {
+            0 => return Ok(None),
+            1 => updates.pop().unwrap(),
+            _ => merge_updates_v1(&updates)
+                .map_err(|e| IntegrationError::new_err(format!("Cannot merge pending updates: {}", e)))?,
+        };
+        Ok(Some(PyBytes::new(py, &update).into()))
+    }
+
+    /// The root types of the document. The type of a root type that was not accessed is not known,
+    /// if `infer_types` is set it is inferred from its content, if it has any.
+    #[pyo3(signature = (txn, infer_types=false))]
+    fn roots(&self, py: Python<'_>, txn: &mut Transaction, infer_types: bool) -> PyResult<Py<PyAny>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let result = PyDict::new(py);
         for (k, v) in t.root_refs() {
-            result.set_item(k, v.into_py(py)).unwrap();
+            let v = match v {
+                Out::UndefinedRef(branch) if infer_types => root_type_ref(t, &v)
+                    .and_then(|type_ref| typed_ref(branch, &type_ref))
+                    .unwrap_or(v),
+                v => v,
+            };
+            result.set_item(k, v.into_py(py))?;
         }
-        result.into()
+        Ok(result.into())
     }
 
-    pub fn observe(&mut self, py: Python<'_>, f: Py<PyAny>) -> PyResult<Py<Subscription>> {
+    /// The shared type with the same ID as the given shared type (which can belong to another
+    /// document), if it exists in this document and was not deleted.
+    fn shared_type(&self, py: Python<'_>, txn: &mut Transaction, shared: &Bound<'_, PyAny>) -> PyResult<Option<Py<PyAny>>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        let shared = shared_branch(shared)?;
+        let Some(branch) = shared.id().get_branch(t) else {
+            return Ok(None);
+        };
+        if branch.is_deleted() {
+            return Ok(None);
+        }
+        // the type of a root type is not known until it is accessed in the document
+        let type_ref = [shared.type_ref(), branch.type_ref()]
+            .into_iter()
+            .find(|type_ref| !matches!(type_ref, TypeRef::Undefined))
+            .cloned()
+            .or_else(|| root_type_ref(t, &Out::UndefinedRef(branch)));
+        let Some(value) = type_ref.and_then(|type_ref| typed_ref(branch, &type_ref)) else {
+            return Ok(None);
+        };
+        Ok(Some(value.into_py(py).unbind()))
+    }
+
+    /// Observe the transactions that changed the document. The origin of their events is looked
+    /// up in `origins` (mapping encoded origins to Python objects), falling back to its bytes.
+    /// If `track_changes` is set, the events also report the shared types that were changed.
+    #[pyo3(signature = (f, origins=None, track_changes=false))]
+    pub fn observe(&mut self, py: Python<'_>, f: Py<PyAny>, origins: Option<Py<PyDict>>, track_changes: bool) -> PyResult<Py<Subscription>> {
+        let changed_types = track_changes.then(|| ChangedTypes::new(&self.doc)).transpose()?;
         let sub = self.doc
             .observe_transaction_cleanup(move |txn, event| {
+                let mut changed = changed_types.as_ref().map(|changed_types| changed_types.take());
                 if !event.delete_set.is_empty() || event.before_state != event.after_state {
+                    if is_aborting(txn) {
+                        // the content of the document is the same as before the transaction
+                        changed = changed.map(|_| Changes::new());
+                    }
                     Python::attach(|py| {
-                        let event = TransactionEvent::new(py, event, txn);
+                        let mut event = TransactionEvent::new(py, event, txn);
+                        event.changed = changed;
+                        event.origin = origin_into_py(py, txn, origins.as_ref());
                         if let Err(err) = f.call1(py, (event,)) {
                             err.restore(py)
                         }
@@ -219,9 +497,71 @@ impl Doc {
         Ok(s)
     }
 
+    /// Observe the updates of the document, called with the v1-encoded update and the origin
+    /// of the transaction, looked up in `origins` like in `observe`.
+    #[pyo3(signature = (f, origins=None))]
+    pub fn observe_update(&mut self, py: Python<'_>, f: Py<PyAny>, origins: Option<Py<PyDict>>) -> PyResult<Py<Subscription>> {
+        let sub = self.doc
+            .observe_update_v1(move |txn, event| {
+                Python::attach(|py| {
+                    let update = PyBytes::new(py, &event.update);
+                    let origin = origin_into_py(py, txn, origins.as_ref());
+                    if let Err(err) = f.call1(py, (update, origin)) {
+                        err.restore(py)
+                    }
+                })
+            })
+            .map_err(|_| TransactionError::new_err("Cannot observe a document in a transaction"))?;
+        let s: Py<Subscription> = Py::new(py, Subscription::from(sub))?;
+        Ok(s)
+    }
+
+    /// Observe the updates of the document, called with the v2-encoded update and the origin
+    /// of the transaction, looked up in `origins` like in `observe`.
+    #[pyo3(signature = (f, origins=None))]
+    pub fn observe_update_v2(&mut self, py: Python<'_>, f: Py<PyAny>, origins: Option<Py<PyDict>>) -> PyResult<Py<Subscription>> {
+        let sub = self.doc
+            .observe_update_v2(move |txn, event| {
+                Python::attach(|py| {
+                    let update = PyBytes::new(py, &event.update);
+                    let origin = origin_into_py(py, txn, origins.as_ref());
+                    if let Err(err) = f.call1(py, (update, origin)) {
+                        err.restore(py)
+                    }
+                })
+            })
+            .map_err(|_| TransactionError::new_err("Cannot observe a document in a transaction"))?;
+        let s: Py<Subscription> = Py::new(py, Subscription::from(sub))?;
+        Ok(s)
+    }
+
+    /// Observe the end of every transaction, even those that did not change the document,
+    /// called with the origin of the transaction, looked up in `origins` like in `observe`.
+    #[pyo3(signature = (f, origins=None))]
+    pub fn observe_after_transaction(&mut self, py: Python<'_>, f: Py<PyAny>, origins: Option<Py<PyDict>>) -> PyResult<Py<Subscription>> {
+        let sub = self.doc
+            .observe_after_transaction(move |txn| {
+                if is_aborting(txn) {
+                    return;
+                }
+                Python::attach(|py| {
+                    let origin = origin_into_py(py, txn, origins.as_ref());
+                    if let Err(err) = f.call1(py, (origin,)) {
+                        err.restore(py)
+                    }
+                })
+            })
+            .map_err(|_| TransactionError::new_err("Cannot observe a document in a transaction"))?;
+        let s: Py<Subscription> = Py::new(py, Subscription::from(sub))?;
+        Ok(s)
+    }
+
     pub fn observe_subdocs(&mut self, py: Python<'_>, f: Py<PyAny>) -> PyResult<Py<Subscription>> {
         let sub = self.doc
-            .observe_subdocs(move |_, event| {
+            .observe_subdocs(move |txn, event| {
+                if is_aborting(txn) {
+                    return;
+                }
                 Python::attach(|py| {
                     let event = SubdocsEvent::new(py, event);
                     if let Err(err) = f.call1(py, (event,)) {
@@ -235,6 +575,133 @@ impl Doc {
     }
 }
 
+/// The documents in which an aborted transaction is being committed, by the address of their
+/// store, which is shared by all the `Doc` wrappers of a document.
+fn aborting() -> &'static Mutex<HashSet<usize>> {
+    static ABORTING: OnceLock<Mutex<HashSet<usize>>> = OnceLock::new();
+    ABORTING.get_or_init(Mutex::default)
+}
+
+pub(crate) fn store_key<T: ReadTxn>(txn: &T) -> usize {
+    txn.store() as *const Store as usize
+}
+
+/// Whether the transaction is an aborted transaction being committed, in which case only the
+/// observers of the document are called.
+pub(crate) fn is_aborting<T: ReadTxn>(txn: &T) -> bool {
+    aborting().lock().unwrap().contains(&store_key(txn))
+}
+
+/// The events that the deep observers of the root types of a document restored at a snapshot get
+/// when applying an update to it, by root type name, with the restored document.
+pub(crate) fn changes_into_py<'py, T: ReadTxn>(
+    py: Python<'py>,
+    txn: &T,
+    snapshot: &yrs::Snapshot,
+    update: Update,
+) -> PyResult<(Doc, Bound<'py, PyDict>)> {
+    let (doc, root_events) = replay_events(py, txn, snapshot, update, |py, _, txn, event| {
+        let event = event_into_py(py, txn, event);
+        // the events are read after their transaction, so their content is computed now
+        for attr in ["target", "path", "delta", "keys"] {
+            if event.hasattr(attr)? {
+                event.getattr(attr)?;
+            }
+        }
+        Ok(Some(event))
+    })?;
+    let events = PyDict::new(py);
+    for (name, root_events) in root_events {
+        if !root_events.bind(py).is_empty() {
+            events.set_item(name, root_events)?;
+        }
+    }
+    Ok((Doc::from(doc), events))
+}
+
+/// The origin of a transaction: the Python object it was registered with in `origins`, or its
+/// bytes.
+fn origin_into_py(py: Python<'_>, txn: &TransactionMut, origins: Option<&Py<PyDict>>) -> Option<Py<PyAny>> {
+    txn.origin().map(|origin| {
+        let origin = PyBytes::new(py, origin.as_ref());
+        origins
+            .and_then(|origins| origins.bind(py).get_item(&origin).ok().flatten())
+            .unwrap_or(origin.into_any())
+            .unbind()
+    })
+}
+
+/// The branch of a shared type.
+pub(crate) fn shared_branch(shared: &Bound<'_, PyAny>) -> PyResult<BranchPtr> {
+    if let Ok(text) = shared.cast::<Text>() {
+        return Ok(BranchPtr::from(AsRef::<Branch>::as_ref(&text.borrow().text)));
+    }
+    if let Ok(array) = shared.cast::<Array>() {
+        return Ok(BranchPtr::from(AsRef::<Branch>::as_ref(&array.borrow().array)));
+    }
+    if let Ok(map) = shared.cast::<Map>() {
+        return Ok(BranchPtr::from(AsRef::<Branch>::as_ref(&map.borrow().map)));
+    }
+    if let Ok(fragment) = shared.cast::<XmlFragment>() {
+        return Ok(BranchPtr::from(AsRef::<Branch>::as_ref(&fragment.get().fragment)));
+    }
+    if let Ok(element) = shared.cast::<XmlElement>() {
+        return Ok(BranchPtr::from(AsRef::<Branch>::as_ref(&element.get().element)));
+    }
+    if let Ok(text) = shared.cast::<XmlText>() {
+        return Ok(BranchPtr::from(AsRef::<Branch>::as_ref(&text.get().text)));
+    }
+    Err(PyValueError::new_err("Not a shared type"))
+}
+
+/// The type of a root type. It is not known until the root type is accessed in the document
+/// (e.g. when it was only received from a remote document), so it is inferred from its content
+/// then, if it has any.
+pub(crate) fn root_type_ref<T: ReadTxn>(txn: &T, root: &Out) -> Option<TypeRef> {
+    let branch = match root {
+        Out::YText(_) => return Some(TypeRef::Text),
+        Out::YArray(_) => return Some(TypeRef::Array),
+        Out::YMap(_) => return Some(TypeRef::Map),
+        Out::YXmlFragment(_) => return Some(TypeRef::XmlFragment),
+        Out::UndefinedRef(branch) => *branch,
+        _ => return None,
+    };
+    if !TextRef::from(branch).get_string(txn).is_empty() {
+        return Some(TypeRef::Text);
+    }
+    match ArrayRef::from(branch).get(txn, 0) {
+        Some(Out::YXmlElement(_) | Out::YXmlText(_)) => return Some(TypeRef::XmlFragment),
+        Some(_) => return Some(TypeRef::Array),
+        None => {}
+    }
+    (MapRef::from(branch).len(txn) > 0).then_some(TypeRef::Map)
+}
+
+/// The shared type of a given type on a branch.
+fn typed_ref(branch: BranchPtr, type_ref: &TypeRef) -> Option<Out> {
+    Some(match type_ref {
+        TypeRef::Text => Out::YText(TextRef::from(branch)),
+        TypeRef::Array => Out::YArray(ArrayRef::from(branch)),
+        TypeRef::Map => Out::YMap(MapRef::from(branch)),
+        TypeRef::XmlElement(_) => Out::YXmlElement(XmlElementRef::from(branch)),
+        TypeRef::XmlFragment => Out::YXmlFragment(XmlFragmentRef::from(branch)),
+        TypeRef::XmlText => Out::YXmlText(XmlTextRef::from(branch)),
+        _ => return None,
+    })
+}
+
+/// The IDs between two states of a document.
+fn inserted_ids(before_state: &StateVector, after_state: &StateVector) -> _IdSet {
+    let mut ids = _IdSet::new();
+    for (client, &clock) in after_state.iter() {
+        let before = before_state.get(client);
+        if clock > before {
+            ids.insert(ID::new(*client, before), clock - before);
+        }
+    }
+    ids
+}
+
 #[pyclass(unsendable)]
 pub struct TransactionEvent {
     event: *const TransactionCleanupEvent,
@@ -243,7 +710,11 @@ pub struct TransactionEvent {
     after_state: Option<Py<PyBytes>>,
     delete_set: Option<Py<PyBytes>>,
     update: Option<Py<PyBytes>>,
+    update_v2: Option<Py<PyBytes>>,
     transaction: Option<Py<PyAny>>,
+    origin: Option<Py<PyAny>>,
+    changed: Option<Changes>,
+    id_sets: Option<(_IdSet, _IdSet)>,
 }
 
 impl TransactionEvent {
@@ -257,12 +728,27 @@ impl TransactionEvent {
             after_state: None,
             delete_set: None,
             update: None,
+            update_v2: None,
             transaction: None,
+            origin: None,
+            changed: None,
+            id_sets: None,
         };
         transaction_event.update(py);
         transaction_event
     }
 
+    /// The IDs of the content inserted and deleted by the transaction.
+    pub(crate) fn id_sets(&mut self) -> (&_IdSet, &_IdSet) {
+        if self.id_sets.is_none() {
+            let event = self.event();
+            let insertions = inserted_ids(&event.before_state, &event.after_state);
+            self.id_sets = Some((insertions, event.delete_set.clone()));
+        }
+        let (insertions, deletions) = self.id_sets.as_ref().unwrap();
+        (insertions, deletions)
+    }
+
     fn event(&self) -> &TransactionCleanupEvent {
         unsafe { self.event.as_ref().unwrap() }
     }
@@ -284,6 +770,26 @@ impl TransactionEvent {
         }
     }
 
+    /// The origin of the transaction, if any.
+    #[getter]
+    pub fn origin(&self, py: Python<'_>) -> Option<Py<PyAny>> {
+        self.origin.as_ref().map(|origin| origin.clone_ref(py))
+    }
+
+    /// The shared types changed in the transaction, as a dict mapping their paths (starting
+    /// with the name of their root type) to their changed keys and index ranges, if tracked.
+    #[getter]
+    pub fn changed_types<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        self.changed.as_ref().map(|changed| changed_types_into_py(py, changed)).transpose()
+    }
+
+    /// The paths of the shared types changed in the transaction and of their parent types,
+    /// if tracked.
+    #[getter]
+    pub fn changed_parent_types<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PySet>>> {
+        self.changed.as_ref().map(|changed| changed_parent_types_into_py(py, changed)).transpose()
+    }
+
     #[getter]
     pub fn before_state<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyBytes> {
         if let Some(before_state) = &self.before_state {
@@ -331,6 +837,18 @@ impl TransactionEvent {
             update
         }
     }
+
+    #[getter]
+    pub fn update_v2<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        if let Some(update_v2) = &self.update_v2 {
+            update_v2.clone_ref(py).into_bound(py)
+        } else {
+            let update_v2 = self.txn().encode_update_v2();
+            let update_v2 = PyBytes::new(py, &update_v2);
+            self.update_v2 = Some(update_v2.clone().unbind());
+            update_v2
+        }
+    }
 }
 
 #[pyclass(unsendable)]
@@ -338,6 +856,9 @@ pub struct SubdocsEvent {
     added: Py<PyAny>,
     removed: Py<PyAny>,
     loaded: Py<PyAny>,
+    added_docs: Py<PyAny>,
+    removed_docs: Py<PyAny>,
+    loaded_docs: Py<PyAny>,
 }
 
 impl SubdocsEvent {
@@ -348,10 +869,19 @@ impl SubdocsEvent {
         let removed = PyList::new(py, removed).unwrap().into_py_any(py).unwrap();
         let loaded: Vec<String> = event.loaded().map(|d| d.guid().clone().to_string()).collect();
         let loaded = PyList::new(py, loaded).unwrap().into_py_any(py).unwrap();
+        let added_docs: Vec<Doc> = event.added().map(|d| Doc::from(d.clone())).collect();
+        let added_docs = added_docs.into_py_any(py).unwrap();
+        let removed_docs: Vec<Doc> = event.removed().map(|d| Doc::from(d.clone())).collect();
+        let removed_docs = removed_docs.into_py_any(py).unwrap();
+        let loaded_docs: Vec<Doc> = event.loaded().map(|d| Doc::from(d.clone())).collect();
+        let loaded_docs = loaded_docs.into_py_any(py).unwrap();
         SubdocsEvent {
             added,
             removed,
             loaded,
+            added_docs,
+            removed_docs,
+            loaded_docs,
         }
     }
 }
@@ -372,4 +902,19 @@ impl SubdocsEvent {
     pub fn loaded(&mut self, py: Python<'_>) -> Py<PyAny> {
         self.loaded.clone_ref(py)
     }
+
+    #[getter]
+    pub fn added_docs(&mut self, py: Python<'_>) -> Py<PyAny> {
+        self.added_docs.clone_ref(py)
+    }
+
+    #[getter]
+    pub fn removed_docs(&mut self, py: Python<'_>) -> Py<PyAny> {
+        self.removed_docs.clone_ref(py)
+    }
+
+    #[getter]
+    pub fn loaded_docs(&mut self, py: Python<'_>) -> Py<PyAny> {
+        self.loaded_docs.clone_ref(py)
+    }
 }
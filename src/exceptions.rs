@@ -0,0 +1,7 @@
+// The exceptions are defined in `pycrdt._exceptions`, so that they also derive from the built-in
+// exceptions that were raised before (`ValueError`, `RuntimeError`, `TypeError`).
+pyo3::import_exception!(pycrdt._exceptions, DecodeError);
+pyo3::import_exception!(pycrdt._exceptions, IntegrationError);
+pyo3::import_exception!(pycrdt._exceptions, OffsetError);
+pyo3::import_exception!(pycrdt._exceptions, TransactionError);
+pyo3::import_exception!(pycrdt._exceptions, ConversionError);
@@ -1,8 +1,8 @@
 use std::cell::RefCell;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
 
-use pyo3::exceptions::{PyTypeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::{PyBytes, PyIterator, PyList};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -13,11 +13,14 @@ use yrs::updates::decoder::Decode;
 use yrs::updates::encoder::Encode;
 use yrs::{
     Any, AttrRange as _AttrRange, ClientID, ContentAttribute as _ContentAttribute, Diff,
-    IdMap as _IdMap, ID,
+    IdMap as _IdMap, IdSet as _IdSet, Update, ID,
 };
 
+use crate::doc::TransactionEvent;
+use crate::exceptions::{ConversionError, DecodeError};
 use crate::type_conversions::{any_to_value, py_to_json_any, value_to_any, ToPython};
 use crate::undo::IdSet;
+use crate::update::decode_v2;
 
 /// A JSON-compatible attribute value, used as the value type of [`yrs::IdMap`].
 ///
@@ -60,7 +63,7 @@ impl<'de> Deserialize<'de> for AttrValue {
 
 /// A named attribute attached to a range of block IDs.
 #[pyclass(from_py_object)]
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct ContentAttribute {
     pub(crate) inner: _ContentAttribute<AttrValue>,
 }
@@ -167,6 +170,31 @@ impl IdMap {
     fn extract_attrs(attributes: Vec<ContentAttribute>) -> Vec<_ContentAttribute<AttrValue>> {
         attributes.into_iter().map(|a| a.inner).collect()
     }
+
+    fn from_id_sets(insertions: &_IdSet, deletions: &_IdSet, attributes: Vec<ContentAttribute>) -> (IdMap, IdMap) {
+        let attributes = IdMap::extract_attrs(attributes);
+        (
+            IdMap { inner: _IdMap::from_set(insertions.clone(), attributes.clone()) },
+            IdMap { inner: _IdMap::from_set(deletions.clone(), attributes) },
+        )
+    }
+
+    /// The ranges covering the `length` clocks starting at `id`, with their attributes, or `None`
+    /// for the gaps that are not in the map.
+    pub(crate) fn attributed_ranges(&self, id: ID, length: u32) -> Vec<(Range<u32>, Option<Vec<ContentAttribute>>)> {
+        self.inner
+            .attributions(&BlockRange::new(id, length))
+            .into_iter()
+            .map(|range| {
+                let start = ID::new(id.client, range.range.start);
+                let attributes = self
+                    .inner
+                    .contains(&start)
+                    .then(|| AttrRange::from_inner(range.clone()).attributes);
+                (range.range, attributes)
+            })
+            .collect()
+    }
 }
 
 #[pymethods]
@@ -253,7 +281,7 @@ impl IdMap {
             self.inner.diff_with(set.borrow().inner());
             Ok(())
         } else {
-            Err(PyTypeError::new_err("diff_with() expects an IdMap or IdSet"))
+            Err(ConversionError::new_err("diff_with() expects an IdMap or IdSet"))
         }
     }
 
@@ -340,6 +368,28 @@ impl IdMap {
         }
     }
 
+    /// Build the maps of the IDs inserted and deleted by an update, attaching `attributes` to
+    /// every range. The update is v1-encoded, unless `v2` is set.
+    #[staticmethod]
+    #[pyo3(signature = (update, attributes, *, v2=false))]
+    pub fn from_update(update: &Bound<'_, PyBytes>, attributes: Vec<ContentAttribute>, v2: bool) -> PyResult<(IdMap, IdMap)> {
+        let update = if v2 {
+            decode_v2(update.as_bytes())
+        } else {
+            Update::decode_v1(update.as_bytes()).map_err(|e| e.to_string())
+        };
+        let update = update.map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))?;
+        Ok(IdMap::from_id_sets(&update.insertions(true), update.delete_set(), attributes))
+    }
+
+    /// Build the maps of the IDs inserted and deleted by the transaction of an event, attaching
+    /// `attributes` to every range.
+    #[staticmethod]
+    pub fn from_event(mut event: PyRefMut<'_, TransactionEvent>, attributes: Vec<ContentAttribute>) -> (IdMap, IdMap) {
+        let (insertions, deletions) = event.id_sets();
+        IdMap::from_id_sets(insertions, deletions, attributes)
+    }
+
     /// Encode the map to bytes.
     pub fn encode(&self) -> Py<PyAny> {
         let encoded = self.inner.encode_v1();
@@ -352,7 +402,7 @@ impl IdMap {
         let bytes: &[u8] = data.as_bytes();
         match _IdMap::<AttrValue>::decode_v1(bytes) {
             Ok(inner) => Ok(IdMap { inner }),
-            Err(e) => Err(PyValueError::new_err(format!("Failed to decode IdMap: {}", e))),
+            Err(e) => Err(DecodeError::new_err(format!("Failed to decode IdMap: {}", e))),
         }
     }
 
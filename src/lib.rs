@@ -16,19 +16,28 @@ mod undo;
 mod update;
 mod xml;
 mod snapshot;
+mod snapshot_reader;
+mod sync;
+mod awareness;
+mod exceptions;
+mod changed_types;
+use crate::awareness::Awareness;
 use crate::doc::Doc;
 use crate::doc::TransactionEvent;
 use crate::doc::SubdocsEvent;
 use crate::text::{Text, TextEvent};
 use crate::array::{Array, ArrayEvent};
 use crate::map::{Map, MapEvent};
-use crate::transaction::Transaction;
+use crate::transaction::{Transaction, TransactionAcquisition};
 use crate::sticky_index::{StickyIndex, decode_sticky_index, get_sticky_index_from_json_string};
 use crate::subscription::Subscription;
 use crate::id_map::{AttrRange, ContentAttribute, IdMap};
 use crate::undo::{StackItem, UndoManager, IdSet};
-use crate::update::{get_state, get_update, merge_updates};
+use crate::update::{
+    convert_update_v1_to_v2, convert_update_v2_to_v1, decode_update, decode_update_v2, get_state, get_state_v2, get_update, get_update_v2, merge_updates, merge_updates_v2,
+};
 use crate::snapshot::Snapshot;
+use crate::sync::{Message, decode_messages, decode_sync_message, encode_messages};
 
 #[pymodule]
 fn _pycrdt(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
@@ -42,6 +51,7 @@ fn _pycrdt(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<Map>()?;
     m.add_class::<MapEvent>()?;
     m.add_class::<Transaction>()?;
+    m.add_class::<TransactionAcquisition>()?;
     m.add_class::<StackItem>()?;
     m.add_class::<IdSet>()?;
     m.add_class::<IdMap>()?;
@@ -55,10 +65,22 @@ fn _pycrdt(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<XmlText>()?;
     m.add_class::<XmlEvent>()?;
     m.add_class::<Snapshot>()?;
+    m.add_class::<Message>()?;
+    m.add_class::<Awareness>()?;
     m.add_function(wrap_pyfunction!(get_state, m)?)?;
     m.add_function(wrap_pyfunction!(get_update, m)?)?;
     m.add_function(wrap_pyfunction!(merge_updates, m)?)?;
+    m.add_function(wrap_pyfunction!(get_state_v2, m)?)?;
+    m.add_function(wrap_pyfunction!(get_update_v2, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_update, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_update_v2, m)?)?;
+    m.add_function(wrap_pyfunction!(merge_updates_v2, m)?)?;
+    m.add_function(wrap_pyfunction!(convert_update_v1_to_v2, m)?)?;
+    m.add_function(wrap_pyfunction!(convert_update_v2_to_v1, m)?)?;
     m.add_function(wrap_pyfunction!(decode_sticky_index, m)?)?;
     m.add_function(wrap_pyfunction!(get_sticky_index_from_json_string, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_messages, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_sync_message, m)?)?;
+    m.add_function(wrap_pyfunction!(encode_messages, m)?)?;
     Ok(())
 }
@@ -1,6 +1,6 @@
 use pyo3::prelude::*;
 use pyo3::IntoPyObjectExt;
-use pyo3::exceptions::{PyValueError, PyTypeError};
+use pyo3::exceptions::PyValueError;
 use pyo3::types::{PyString, PyDict, PyList};
 use yrs::{
     Any, DeepObservable, Doc as _Doc, Map as _Map, MapRef, Observable, TransactionMut, XmlFragmentPrelim
@@ -9,6 +9,8 @@ use yrs::types::ToJson;
 use yrs::types::text::TextPrelim;
 use yrs::types::array::ArrayPrelim;
 use yrs::types::map::{MapPrelim, MapEvent as _MapEvent};
+use crate::exceptions::ConversionError;
+use crate::transaction::TransactionAccess;
 use crate::transaction::Transaction;
 use crate::subscription::Subscription;
 use crate::type_conversions::{EntryChangeWrapper, events_into_py, py_to_any, ToPython};
@@ -32,18 +34,17 @@ impl Map {
 #[pymethods]
 impl Map {
     fn len(&self, txn: &mut Transaction)  -> PyResult<u32> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let len = self.map.len(t);
         Ok(len)
     }
 
     fn insert(&self, txn: &mut Transaction, key: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
-        match py_to_any(value) {
-            Any::Undefined => Err(PyTypeError::new_err("Type not supported")),
+        let mut t = _t.txn_mut()?;
+        match py_to_any(value)? {
+            Any::Undefined => Err(ConversionError::new_err("Type not supported")),
             v => {
                 self.map.insert(&mut t, key, v);
                 Ok(())
@@ -53,7 +54,7 @@ impl Map {
 
     fn insert_text_prelim(&self, txn: &mut Transaction, key: &str) -> PyResult<Text> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated = self.map.insert(&mut t, key, TextPrelim::new(""));
         let shared = Text::from(integrated);
         Ok(shared)
@@ -61,7 +62,7 @@ impl Map {
 
     fn insert_array_prelim(&self, txn: &mut Transaction, key: &str) -> PyResult<Array> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated = self.map.insert(&mut t, key, ArrayPrelim::default());
         let shared = Array::from(integrated);
         Ok(shared)
@@ -69,7 +70,7 @@ impl Map {
 
     fn insert_map_prelim(&self, txn: &mut Transaction, key: &str) -> PyResult<Map> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated = self.map.insert(&mut t, key, MapPrelim::default());
         let shared = Map::from(integrated);
         Ok(shared)
@@ -77,25 +78,25 @@ impl Map {
 
     fn insert_xmlfragment_prelim(&self, txn: &mut Transaction, key: &str) -> PyResult<XmlFragment> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated = self.map.insert(&mut t, key, XmlFragmentPrelim::default());
         let shared = XmlFragment::from(integrated);
         Ok(shared)
     }
 
     fn insert_xmlelement_prelim(&self, _txn: &mut Transaction, _key: &str) -> PyResult<Py<PyAny>> {
-        Err(PyTypeError::new_err("Cannot insert an XmlElement into a map - insert it into an XmlFragment and insert that into the map"))
+        Err(ConversionError::new_err("Cannot insert an XmlElement into a map - insert it into an XmlFragment and insert that into the map"))
     }
 
     fn insert_xmltext_prelim(&self, _txn: &mut Transaction, _key: &str) -> PyResult<Py<PyAny>> {
-        Err(PyTypeError::new_err("Cannot insert an XmlText into a map - insert it into an XmlFragment and insert that into the map"))
+        Err(ConversionError::new_err("Cannot insert an XmlText into a map - insert it into an XmlFragment and insert that into the map"))
     }
 
     fn insert_doc(&self, txn: &mut Transaction, key: &str, doc: &Bound<'_, PyAny>) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let d1: Doc = doc.extract()
-            .map_err(|_| PyTypeError::new_err("Expected Doc object"))?;
+            .map_err(|_| ConversionError::new_err("Expected Doc object"))?;
         let d2: _Doc = d1.doc;
         let doc_ref = self.map.insert(&mut t, key, d2);
         doc_ref.load(t);
@@ -104,15 +105,14 @@ impl Map {
 
     fn remove(&self, txn: &mut Transaction, key: &str) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         self.map.remove(&mut t, key);
         Ok(())
     }
 
     fn get<'py>(&self, py: Python<'py>, txn: &mut Transaction, key: &str) -> PyResult<Bound<'py, PyAny>> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let v = self.map.get(t, key);
         if v == None {
             Err(PyValueError::new_err("Key error"))
@@ -121,32 +121,29 @@ impl Map {
         }
     }
 
-    fn keys<'py>(&self, py: Python<'py>, txn: &mut Transaction) -> Bound<'py, PyList> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+    fn keys<'py>(&self, py: Python<'py>, txn: &mut Transaction) -> PyResult<Bound<'py, PyList>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let it = self.map.keys(t);
         let mut v: Vec<String> = Vec::new();
         for k in it {
             v.push(k.into());
         }
-        PyList::new(py, v).unwrap()
+        PyList::new(py, v)
     }
 
-    fn to_json(&mut self, txn: &mut Transaction) -> Py<PyAny> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+    fn to_json(&mut self, txn: &mut Transaction) -> PyResult<Py<PyAny>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let mut s = String::new();
         self.map.to_json(t).to_json(&mut s);
-        Python::attach(|py| PyString::new(py, s.as_str()).into())
+        Ok(Python::attach(|py| PyString::new(py, s.as_str()).into()))
     }
 
     /// Returns true if the given key exists in the map.
     fn has(&self, txn: &mut Transaction, key: &str) -> PyResult<bool> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         Ok(self.map.get(t, key).is_some())
     }
 
@@ -1,10 +1,26 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
 use pyo3::prelude::*;
-use pyo3::types::PyBytes;
-use yrs::{Snapshot as _Snapshot, ReadTxn};
-use crate::doc::Doc;
+use pyo3::IntoPyObjectExt;
+use pyo3::types::{PyBytes, PyList};
+use yrs::{ClientID, DeepObservable, Out, Snapshot as _Snapshot, ReadTxn, StateVector, Text as _Text, TextRef, TransactionMut, ID};
+use pyo3::exceptions::PyValueError;
+use yrs::{Doc as _Doc, Options, Update};
+use crate::array::ArrayEvent;
+use crate::doc::{root_type_ref, shared_branch, Doc};
+use crate::map::MapEvent;
+use crate::exceptions::{DecodeError, IntegrationError};
+use crate::transaction::{Transaction, TransactionAccess};
+use crate::type_conversions::ToPython;
+use crate::undo::IdSet;
+use crate::snapshot_reader::SnapshotReader;
+use crate::update::catch_decode_v2;
 use pyo3::types::PyType;
 use yrs::Transact;
-use yrs::updates::encoder::Encode;
+use yrs::branch::BranchPtr;
+use yrs::types::{Event, Events, TypeRef};
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
 use yrs::updates::decoder::Decode;
 
 #[pyclass(unsendable)]
@@ -22,9 +38,8 @@ impl Snapshot {
 impl Snapshot {
     /// Construct a snapshot from a Doc
     #[classmethod]
-    pub fn from_doc(_cls: &Bound<'_, PyType>, doc: &mut Doc) -> Self {
-        let txn = doc.doc.transact();
-        let snapshot = txn.snapshot();
+    pub fn from_doc(_cls: &Bound<'_, PyType>, py: Python<'_>, doc: &Doc) -> Self {
+        let snapshot = py.detach(|| doc.doc.transact().snapshot());
         Snapshot { snapshot }
     }
 
@@ -40,7 +55,259 @@ impl Snapshot {
         let bytes: &[u8] = data.as_bytes();
         match _Snapshot::decode_v1(bytes) {
             Ok(snapshot) => Ok(Snapshot { snapshot }),
-            Err(e) => Err(pyo3::exceptions::PyValueError::new_err(format!("Failed to decode snapshot: {}", e)))
+            Err(e) => Err(DecodeError::new_err(format!("Failed to decode snapshot: {}", e)))
         }
     }
+
+    /// Construct a snapshot from an encoded state vector and a delete set
+    #[classmethod]
+    pub fn from_state(_cls: &Bound<'_, PyType>, state: &Bound<'_, PyBytes>, delete_set: &IdSet) -> PyResult<Self> {
+        let state_map = StateVector::decode_v1(state.as_bytes())
+            .map_err(|e| DecodeError::new_err(format!("Failed to decode state vector: {}", e)))?;
+        Ok(Snapshot { snapshot: _Snapshot::new(state_map, delete_set.inner().clone()) })
+    }
+
+    /// Restore the state of the document at the snapshot in a new document
+    pub fn restore(&self, txn: &mut Transaction) -> PyResult<Doc> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        Ok(Doc::from(restore_doc(t, &self.snapshot)?))
+    }
+
+    /// The content of an array or a map at the snapshot, or the string representation of an XML
+    /// node, read from the document in place
+    pub fn read<'py>(&self, py: Python<'py>, txn: &mut Transaction, shared: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+        let branch = shared_branch(shared)?;
+        let mut t0 = txn.transaction();
+        // the texts are read with their blocks split at the snapshot, which requires a read-write transaction
+        let t = t0.txn_mut()?;
+        require_skip_gc(t)?;
+        SnapshotReader::new(t, &self.snapshot)?.read(py, t, branch)
+    }
+
+    /// The changes of the arrays and maps of the document from a previous snapshot to this
+    /// snapshot, as a document restored at this snapshot and a list of
+    /// (path, changes) tuples. The changes are an array delta or the map key changes, like in
+    /// array and map events. If a shared type is given, only its changes are returned.
+    #[pyo3(signature = (txn, prev, shared=None))]
+    pub fn diff<'py>(
+        &self,
+        py: Python<'py>,
+        txn: &mut Transaction,
+        prev: &Snapshot,
+        shared: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<(Doc, Bound<'py, PyList>)> {
+        let precedes = prev.snapshot.state_map.iter()
+            .all(|(client, clock)| self.snapshot.state_map.get(client) >= *clock)
+            && prev.snapshot.delete_set.diff(&self.snapshot.delete_set).is_empty();
+        if !precedes {
+            return Err(PyValueError::new_err("The previous snapshot must precede the snapshot"));
+        }
+        let target = shared.map(shared_branch).transpose()?.map(|branch| branch.id());
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        let update = encode_state_from_snapshot(t, &self.snapshot)?;
+        let (doc, root_changes) = replay_events(py, t, &prev.snapshot, update, move |py, name, txn, event| {
+            let (branch, path, delta) = match event {
+                Event::Array(e) => {
+                    let delta = ArrayEvent::new(e, txn).delta(py);
+                    (BranchPtr::from(e.target().as_ref()), e.path(), delta)
+                }
+                Event::Map(e) => {
+                    let keys = MapEvent::new(e, txn).keys(py)?;
+                    (BranchPtr::from(e.target().as_ref()), e.path(), keys)
+                }
+                _ => return Ok(None),
+            };
+            if target.as_ref().is_some_and(|id| *id != branch.id()) {
+                return Ok(None);
+            }
+            let path = path.into_py(py);
+            path.cast::<PyList>()?.insert(0, name)?;
+            Ok(Some((path, delta).into_bound_py_any(py)?))
+        })?;
+        let changes = PyList::empty(py);
+        for (_, root_change) in root_changes {
+            for change in root_change.bind(py).iter() {
+                changes.append(change)?;
+            }
+        }
+        Ok((Doc::from(doc), changes))
+    }
+
+    /// Encode the snapshot to bytes, using the v2 encoding
+    pub fn encode_v2<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.snapshot.encode_v2())
+    }
+
+    /// Decode a snapshot from v2-encoded bytes
+    #[classmethod]
+    pub fn decode_v2(_cls: &Bound<'_, PyType>, data: &Bound<'_, PyBytes>) -> PyResult<Self> {
+        match catch_decode_v2(|| _Snapshot::decode_v2(data.as_bytes())) {
+            Ok(snapshot) => Ok(Snapshot { snapshot }),
+            Err(e) => Err(DecodeError::new_err(format!("Failed to decode snapshot: {}", e)))
+        }
+    }
+
+    /// The encoded state vector of the snapshot
+    pub fn state_vector<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.snapshot.state_map.encode_v1())
+    }
+
+    /// The content deleted at the time of the snapshot
+    pub fn delete_set(&self) -> IdSet {
+        IdSet::from(self.snapshot.delete_set.clone())
+    }
+
+    /// Whether the item with the given ID existed and was not deleted at the time of the snapshot
+    pub fn is_visible(&self, client: u64, clock: u32) -> bool {
+        let id = ID::new(ClientID::new(client), clock);
+        self.snapshot.state_map.get(&id.client) > clock && !self.snapshot.delete_set.contains(&id)
+    }
+
+    fn __eq__(&self, other: &Snapshot) -> bool {
+        self.snapshot == other.snapshot
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        // the state vector is not ordered
+        let mut state: Vec<(ClientID, u32)> = self.snapshot.state_map.iter().map(|(client, clock)| (*client, *clock)).collect();
+        state.sort();
+        state.hash(&mut hasher);
+        self.snapshot.delete_set.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Content that was deleted can only be read at a snapshot if it was not garbage collected.
+pub(crate) fn require_skip_gc(txn: &TransactionMut) -> PyResult<()> {
+    if !txn.doc().skip_gc() {
+        return Err(PyValueError::new_err("Document must be created with skip_gc=True to be read at a snapshot"));
+    }
+    Ok(())
+}
+
+/// Encodes the state of a document at a snapshot as an update.
+pub(crate) fn encode_state_from_snapshot<T: ReadTxn>(txn: &T, snapshot: &_Snapshot) -> PyResult<Update> {
+    // a client with a zero clock has no content at the snapshot, but yrs expects it to have some
+    let state_map: StateVector = snapshot.state_map.iter()
+        .filter(|(_, clock)| **clock > 0)
+        .map(|(&client, &clock)| (client, clock))
+        .collect();
+    let snapshot = _Snapshot::new(state_map, snapshot.delete_set.clone());
+    if let Some(update) = encode_blocks_at(txn, &snapshot)?
+        && update.state_vector().iter().all(|(client, &clock)| clock <= snapshot.state_map.get(client)) {
+        return Ok(update);
+    }
+    // yrs encodes the last block of a client wrongly when the snapshot only includes a part of it
+    // (the whole block, or a block that cannot be decoded), so the blocks of the document are
+    // split at the snapshot first, in a copy of the document (like when diffing a text at a snapshot)
+    let copy = _Doc::with_options(Options { skip_gc: true, ..Options::default() });
+    let mut copy_txn = copy.transact_mut();
+    let state = Update::decode_v1(&txn.encode_state_as_update_v1(&StateVector::default()))
+        .map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))?;
+    copy_txn.apply_update(state)
+        .map_err(|e| IntegrationError::new_err(format!("Cannot apply update: {}", e)))?;
+    let root = copy_txn.root_refs().find_map(|(_, root)| match root {
+        Out::UndefinedRef(branch) => Some(branch),
+        _ => None,
+    });
+    if let Some(branch) = root {
+        TextRef::from(branch).diff_range(&mut copy_txn, Some(&snapshot), None, |_| ());
+    }
+    encode_blocks_at(&copy_txn, &snapshot)?
+        .ok_or_else(|| DecodeError::new_err("Cannot encode the state of the document at the snapshot"))
+}
+
+/// The blocks of a document at a snapshot, or None if yrs encoded them wrongly.
+fn encode_blocks_at<T: ReadTxn>(txn: &T, snapshot: &_Snapshot) -> PyResult<Option<Update>> {
+    let mut encoder = EncoderV1::new();
+    // the only error is that the content was garbage collected
+    txn.encode_state_from_snapshot(snapshot, &mut encoder)
+        .map_err(|_| PyValueError::new_err("Document must be created with skip_gc=True to be read at a snapshot"))?;
+    Ok(Update::decode_v1(&encoder.to_vec()).ok())
+}
+
+/// Restores the state of a document at a snapshot in a new document.
+pub(crate) fn restore_doc<T: ReadTxn>(txn: &T, snapshot: &_Snapshot) -> PyResult<_Doc> {
+    let update = encode_state_from_snapshot(txn, snapshot)?;
+    let options = Options { skip_gc: true, ..Options::default() };
+    let doc = _Doc::with_options(options);
+    doc.transact_mut().apply_update(update)
+        .map_err(|e| IntegrationError::new_err(format!("Cannot apply update: {}", e)))?;
+    Ok(doc)
+}
+
+/// The converted events of root types, by root type name.
+pub(crate) type RootEvents = Vec<(String, Py<PyList>)>;
+
+/// Restores the state of a document at a snapshot in a new document, and applies an update to it.
+/// The events of its root types are converted with the given function (which is passed the name
+/// of the root type), and returned per root type, sorted by name.
+pub(crate) fn replay_events<T, F>(
+    py: Python<'_>,
+    txn: &T,
+    snapshot: &_Snapshot,
+    update: Update,
+    convert: F,
+) -> PyResult<(_Doc, RootEvents)>
+where
+    T: ReadTxn,
+    F: for<'py> Fn(Python<'py>, &str, &TransactionMut, &Event) -> PyResult<Option<Bound<'py, PyAny>>> + Send + Sync + 'static,
+{
+    let doc = restore_doc(txn, snapshot)?;
+    let convert = Arc::new(convert);
+    // the first error raised while converting the events
+    let error: Arc<Mutex<Option<PyErr>>> = Arc::default();
+    let mut subscriptions = Vec::new();
+    // the events of different root types are not triggered in a predictable order,
+    // so they are collected per root type
+    let mut root_events = Vec::new();
+    let mut roots: Vec<(&str, Out)> = txn.root_refs().collect();
+    roots.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in roots {
+        let converted = PyList::empty(py).unbind();
+        root_events.push((name.to_string(), converted.clone_ref(py)));
+        let observer = {
+            let name = name.to_string();
+            let convert = convert.clone();
+            let error = error.clone();
+            move |txn: &TransactionMut, events: &Events| {
+                Python::attach(|py| {
+                    for event in events.iter() {
+                        let converted = match convert(py, &name, txn, event) {
+                            Ok(Some(event)) => converted.bind(py).append(event),
+                            Ok(None) => Ok(()),
+                            Err(err) => Err(err),
+                        };
+                        if let Err(err) = converted {
+                            error.lock().unwrap().get_or_insert(err);
+                        }
+                    }
+                })
+            }
+        };
+        // the root types that are empty after the update may have had content at the snapshot
+        let type_ref = root_type_ref(txn, &value).or_else(|| {
+            let restored = doc.transact();
+            let root = restored.root_refs().find(|(root_name, _)| *root_name == name)?.1;
+            root_type_ref(&restored, &root)
+        });
+        let subscription = match type_ref {
+            Some(TypeRef::Text) => doc.get_or_insert_text(name).observe_deep(observer),
+            Some(TypeRef::Array) => doc.get_or_insert_array(name).observe_deep(observer),
+            Some(TypeRef::Map) => doc.get_or_insert_map(name).observe_deep(observer),
+            Some(TypeRef::XmlFragment) => doc.get_or_insert_xml_fragment(name).observe_deep(observer),
+            _ => continue,
+        };
+        subscriptions.push(subscription);
+    }
+    doc.transact_mut().apply_update(update)
+        .map_err(|e| IntegrationError::new_err(format!("Cannot apply update: {}", e)))?;
+    drop(subscriptions);
+    if let Some(err) = error.lock().unwrap().take() {
+        return Err(err);
+    }
+    Ok((doc, root_events))
 }
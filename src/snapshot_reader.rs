@@ -0,0 +1,524 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::Arc;
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyDict, PyList, PyString};
+use yrs::block::{ItemContent, BLOCK_GC_REF_NUMBER, BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN};
+use yrs::branch::{BranchID, BranchPtr};
+use yrs::encoding::read::{Error, Read};
+use yrs::types::TypeRef;
+use yrs::types::text::YChange;
+use yrs::updates::decoder::{Decoder, DecoderV1};
+use yrs::{Any, ClientID, OffsetKind, Out, ReadTxn, Snapshot, StateVector, Text, TextRef, TransactionMut, ID};
+use crate::exceptions::DecodeError;
+use crate::type_conversions::ToPython;
+
+/// The parent of an item, as encoded in an update.
+enum EncodedParent {
+    Root(Arc<str>),
+    Nested(ID),
+    /// The parent is the one of the item's origin (or right origin).
+    Inherited,
+}
+
+/// A shared type, by the name of a root type or the ID of the item of a nested type.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Parent {
+    Root(Arc<str>),
+    Nested(ID),
+}
+
+/// An item of the document, as encoded in its state.
+struct Block {
+    id: ID,
+    len: u32,
+    origin: Option<ID>,
+    right_origin: Option<ID>,
+    parent: EncodedParent,
+    parent_sub: Option<Arc<str>>,
+    content: ItemContent,
+}
+
+/// A single element of an item: items are split into elements, so that the origins of other items
+/// always point to a whole element.
+struct Element {
+    id: ID,
+    block: usize,
+    offset: usize,
+    origin: Option<ID>,
+    right_origin: Option<ID>,
+    key: Option<Arc<str>>,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+/// The elements of a shared type, linked in the order they had at the snapshot: the sequence of
+/// an array or an XML node, and the entries of a map (or XML attributes) by key.
+#[derive(Default)]
+struct Elements {
+    elements: Vec<Element>,
+    index: HashMap<ID, usize>,
+    /// The first element of the sequence (with no key) and of the entries of each key.
+    starts: HashMap<Option<Arc<str>>, usize>,
+}
+
+/// Reads the shared types of a document as they were at a snapshot, from the document itself,
+/// like Yjs does with `typeListToArraySnapshot` and `typeMapGetAllSnapshot`. The items of the
+/// document are not exposed by yrs, so they are decoded from its state, and the items of a shared
+/// type are put back in order by integrating the ones that existed at the snapshot, as yrs does.
+pub(crate) struct SnapshotReader<'a> {
+    snapshot: &'a Snapshot,
+    blocks: Vec<Block>,
+    /// The blocks of each client, in clock order.
+    clients: HashMap<ClientID, Vec<usize>>,
+    /// The blocks of each shared type.
+    children: HashMap<Parent, Vec<usize>>,
+    /// The parent and key of each block.
+    parents: Vec<Option<(Parent, Option<Arc<str>>)>>,
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub(crate) fn new<T: ReadTxn>(txn: &T, snapshot: &'a Snapshot) -> PyResult<Self> {
+        let update = txn.encode_state_as_update_v1(&StateVector::default());
+        let blocks = read_blocks(&update, &snapshot.state_map)
+            .map_err(|e| DecodeError::new_err(format!("Cannot read document state: {}", e)))?;
+        let mut clients: HashMap<ClientID, Vec<usize>> = HashMap::new();
+        for (i, block) in blocks.iter().enumerate() {
+            clients.entry(block.id.client).or_default().push(i);
+        }
+        let mut reader = SnapshotReader {
+            snapshot,
+            blocks,
+            clients,
+            children: HashMap::new(),
+            parents: Vec::new(),
+        };
+        reader.resolve_parents();
+        Ok(reader)
+    }
+
+    /// The content of a shared type at the snapshot: a list for an array, a dict for a map, and
+    /// the string representation of an XML node.
+    pub(crate) fn read<'py>(&self, py: Python<'py>, txn: &mut TransactionMut, branch: BranchPtr) -> PyResult<Bound<'py, PyAny>> {
+        let parent = match branch.id() {
+            BranchID::Root(name) => Parent::Root(name),
+            BranchID::Nested(id) => Parent::Nested(id),
+        };
+        match branch.type_ref() {
+            TypeRef::Array => self.array(py, txn, &parent),
+            TypeRef::Map => self.map(py, txn, &parent),
+            TypeRef::XmlElement(_) | TypeRef::XmlFragment | TypeRef::XmlText => {
+                Ok(PyString::new(py, &self.xml_string(txn, &parent, branch.type_ref())).into_any())
+            }
+            _ => Err(PyValueError::new_err("Cannot read this shared type at a snapshot")),
+        }
+    }
+
+    fn is_visible(&self, id: &ID) -> bool {
+        self.snapshot.state_map.get(&id.client) > id.clock && !self.snapshot.delete_set.contains(id)
+    }
+
+    /// Whether a shared type existed and was not deleted at the snapshot.
+    fn exists(&self, parent: &Parent) -> bool {
+        match parent {
+            Parent::Root(_) => true,
+            Parent::Nested(id) => self.is_visible(id),
+        }
+    }
+
+    /// The block containing an ID.
+    fn find(&self, id: &ID) -> Option<usize> {
+        let blocks = self.clients.get(&id.client)?;
+        let i = blocks.partition_point(|&i| self.blocks[i].id.clock + self.blocks[i].len <= id.clock);
+        blocks.get(i).copied().filter(|&i| self.blocks[i].id.clock <= id.clock)
+    }
+
+    /// Find the parent of every block: a block whose parent was not encoded has the parent (and
+    /// key) of its origin, or of its right origin.
+    fn resolve_parents(&mut self) {
+        let mut parents: Vec<Option<(Parent, Option<Arc<str>>)>> = vec![None; self.blocks.len()];
+        for i in 0..self.blocks.len() {
+            let mut inheriting = Vec::new();
+            let mut j = i;
+            let resolved = loop {
+                if let Some(parent) = &parents[j] {
+                    break Some(parent.clone());
+                }
+                let block = &self.blocks[j];
+                match &block.parent {
+                    EncodedParent::Root(name) => break Some((Parent::Root(name.clone()), block.parent_sub.clone())),
+                    EncodedParent::Nested(id) => break Some((Parent::Nested(*id), block.parent_sub.clone())),
+                    EncodedParent::Inherited => {
+                        inheriting.push(j);
+                        match block.origin.or(block.right_origin).and_then(|id| self.find(&id)) {
+                            Some(k) if !inheriting.contains(&k) => j = k,
+                            _ => break None,
+                        }
+                    }
+                }
+            };
+            if let Some(resolved) = resolved {
+                for k in inheriting.into_iter().chain([j]) {
+                    parents[k] = Some(resolved.clone());
+                }
+            }
+        }
+        for (i, parent) in parents.iter().enumerate() {
+            if let Some((parent, _)) = parent {
+                self.children.entry(parent.clone()).or_default().push(i);
+            }
+        }
+        self.parents = parents;
+    }
+
+    /// The elements of a shared type that existed at the snapshot, in order.
+    fn elements(&self, parent: &Parent) -> Elements {
+        let mut elements = Elements::default();
+        if !self.exists(parent) {
+            return elements;
+        }
+        let Some(children) = self.children.get(parent) else {
+            return elements;
+        };
+        for &i in children {
+            let block = &self.blocks[i];
+            let key = self.parents[i].as_ref().and_then(|(_, key)| key.clone());
+            let end = self.snapshot.state_map.get(&block.id.client).min(block.id.clock + block.len);
+            for clock in block.id.clock..end {
+                let id = ID::new(block.id.client, clock);
+                let offset = (clock - block.id.clock) as usize;
+                let origin = if offset == 0 { block.origin } else { Some(ID::new(id.client, clock - 1)) };
+                elements.index.insert(id, elements.elements.len());
+                elements.elements.push(Element {
+                    id,
+                    block: i,
+                    offset,
+                    origin,
+                    right_origin: block.right_origin,
+                    key: key.clone(),
+                    left: None,
+                    right: None,
+                });
+            }
+        }
+        // the elements are integrated after their origins
+        let mut integrated = vec![false; elements.elements.len()];
+        for i in 0..elements.elements.len() {
+            let mut stack = vec![i];
+            while let Some(&j) = stack.last() {
+                if integrated[j] {
+                    stack.pop();
+                    continue;
+                }
+                let element = &elements.elements[j];
+                let dependency = [element.origin, element.right_origin]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|id| elements.index.get(&id).copied())
+                    .find(|&k| !integrated[k] && !stack.contains(&k));
+                match dependency {
+                    Some(k) => stack.push(k),
+                    None => {
+                        elements.integrate(j);
+                        integrated[j] = true;
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        elements
+    }
+
+    /// The visible elements of the sequence of a shared type, in order.
+    fn sequence<'e>(&self, elements: &'e Elements) -> Vec<&'e Element> {
+        let mut sequence = Vec::new();
+        let mut next = elements.starts.get(&None).copied();
+        while let Some(i) = next {
+            let element = &elements.elements[i];
+            if self.is_visible(&element.id) {
+                sequence.push(element);
+            }
+            next = element.right;
+        }
+        sequence
+    }
+
+    /// The visible entries of a map (or the attributes of an XML element), sorted by key: the value
+    /// of a key is the last one set before the snapshot, if it was not deleted then.
+    fn entries<'e>(&self, elements: &'e Elements) -> Vec<(&'e Arc<str>, &'e Element)> {
+        let mut entries = Vec::new();
+        for (key, &start) in elements.starts.iter() {
+            let Some(key) = key else {
+                continue;
+            };
+            let mut last = start;
+            while let Some(right) = elements.elements[last].right {
+                last = right;
+            }
+            let element = &elements.elements[last];
+            if self.is_visible(&element.id) {
+                entries.push((key, element));
+            }
+        }
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+
+    fn array<'py>(&self, py: Python<'py>, txn: &mut TransactionMut, parent: &Parent) -> PyResult<Bound<'py, PyAny>> {
+        let elements = self.elements(parent);
+        let list = PyList::empty(py);
+        for element in self.sequence(&elements) {
+            if let Some(value) = self.value(py, txn, element)? {
+                list.append(value)?;
+            }
+        }
+        Ok(list.into_any())
+    }
+
+    fn map<'py>(&self, py: Python<'py>, txn: &mut TransactionMut, parent: &Parent) -> PyResult<Bound<'py, PyAny>> {
+        let elements = self.elements(parent);
+        let dict = PyDict::new(py);
+        for (key, element) in self.entries(&elements) {
+            if let Some(value) = self.value(py, txn, element)? {
+                dict.set_item(key.as_ref(), value)?;
+            }
+        }
+        Ok(dict.into_any())
+    }
+
+    /// An element converted to a Python object, the shared types being converted like with their
+    /// `to_py` method, at the snapshot.
+    fn value<'py>(&self, py: Python<'py>, txn: &mut TransactionMut, element: &Element) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let value = match &self.blocks[element.block].content {
+            ItemContent::Any(values) => values[element.offset].clone().into_py(py),
+            ItemContent::JSON(values) => Any::from(values[element.offset].as_str()).into_py(py),
+            ItemContent::Binary(buf) => Any::from(buf.clone()).into_py(py),
+            ItemContent::Embed(value) => value.clone().into_py(py),
+            ItemContent::Doc(_, doc) => {
+                // the subdocuments are the ones of the document
+                let guid = doc.guid();
+                match txn.subdocs().find(|subdoc| subdoc.guid() == guid) {
+                    Some(subdoc) => Out::YDoc(subdoc.clone()).into_py(py),
+                    None => return Ok(None),
+                }
+            }
+            ItemContent::Type(branch) => {
+                let parent = Parent::Nested(element.id);
+                match branch.type_ref() {
+                    TypeRef::Array => self.array(py, txn, &parent)?,
+                    TypeRef::Map => self.map(py, txn, &parent)?,
+                    TypeRef::Text => PyString::new(py, &text_string(txn, &element.id, self.snapshot)).into_any(),
+                    TypeRef::XmlText => PyString::new(py, &xml_text_string(txn, &element.id, self.snapshot)).into_any(),
+                    TypeRef::XmlElement(_) => return Err(PyValueError::new_err("XmlElement has no Python equivalent")),
+                    TypeRef::XmlFragment => return Err(PyValueError::new_err("XmlFragment has no Python equivalent")),
+                    _ => return Ok(None),
+                }
+            }
+            ItemContent::String(_) | ItemContent::Format(_, _) | ItemContent::Deleted(_) => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+
+    /// The string representation of an XML node at the snapshot, like yrs' `get_string`, with the
+    /// attributes sorted by name.
+    fn xml_string(&self, txn: &mut TransactionMut, parent: &Parent, type_ref: &TypeRef) -> String {
+        let mut s = String::new();
+        if !self.exists(parent) {
+            return s;
+        }
+        if let TypeRef::XmlText = type_ref {
+            return match parent {
+                Parent::Nested(id) => xml_text_string(txn, id, self.snapshot),
+                Parent::Root(_) => s,
+            };
+        }
+        let elements = self.elements(parent);
+        if let TypeRef::XmlElement(tag) = type_ref {
+            write!(s, "<{}", tag).unwrap();
+            for (key, element) in self.entries(&elements) {
+                if let Some(value) = any_value(&self.blocks[element.block].content, element.offset) {
+                    write!(s, " {}=\"{}\"", key, value).unwrap();
+                }
+            }
+            s.push('>');
+        }
+        for element in self.sequence(&elements) {
+            match &self.blocks[element.block].content {
+                ItemContent::Type(branch) => {
+                    let child = Parent::Nested(element.id);
+                    s.push_str(&self.xml_string(txn, &child, branch.type_ref()));
+                }
+                content => {
+                    if let Some(value) = any_value(content, element.offset) {
+                        write!(s, "{}", value).unwrap();
+                    }
+                }
+            }
+        }
+        if let TypeRef::XmlElement(tag) = type_ref {
+            write!(s, "</{}>", tag).unwrap();
+        }
+        s
+    }
+}
+
+impl Elements {
+    /// Insert an element after its origin, resolving the conflicts with the elements inserted
+    /// concurrently at the same position like yrs' `Item::resolve_conflict`.
+    fn integrate(&mut self, i: usize) {
+        let element = &self.elements[i];
+        let key = element.key.clone();
+        let mut left = element.origin.and_then(|id| self.index.get(&id).copied());
+        let right = element.right_origin.and_then(|id| self.index.get(&id).copied());
+        let conflict = match (left, right) {
+            (None, None) => true,
+            (None, Some(right)) => self.elements[right].left.is_some(),
+            (Some(left), _) => self.elements[left].right != right,
+        };
+        if conflict {
+            let mut o = match left {
+                Some(left) => self.elements[left].right,
+                None => self.starts.get(&key).copied(),
+            };
+            let mut conflicting = HashSet::new();
+            let mut before_origin = HashSet::new();
+            while let Some(j) = o {
+                if Some(j) == right {
+                    break;
+                }
+                before_origin.insert(j);
+                conflicting.insert(j);
+                let (element, other) = (&self.elements[i], &self.elements[j]);
+                if element.origin == other.origin {
+                    if other.id.client < element.id.client {
+                        left = Some(j);
+                        conflicting.clear();
+                    } else if element.right_origin == other.right_origin {
+                        break;
+                    }
+                } else if let Some(other_origin) = other.origin.and_then(|id| self.index.get(&id).copied()) {
+                    if !before_origin.contains(&other_origin) {
+                        break;
+                    }
+                    if !conflicting.contains(&other_origin) {
+                        left = Some(j);
+                        conflicting.clear();
+                    }
+                } else {
+                    break;
+                }
+                o = self.elements[j].right;
+            }
+        }
+        let right = match left {
+            Some(left) => self.elements[left].right.replace(i),
+            None => self.starts.insert(key, i),
+        };
+        if let Some(right) = right {
+            self.elements[right].left = Some(i);
+        }
+        self.elements[i].left = left;
+        self.elements[i].right = right;
+    }
+}
+
+/// Read the items of an update, leaving out the ones that were not integrated at a state.
+fn read_blocks(update: &[u8], state: &StateVector) -> Result<Vec<Block>, Error> {
+    let mut decoder = DecoderV1::from(update);
+    let mut blocks = Vec::new();
+    let clients_len: u32 = decoder.read_var()?;
+    for _ in 0..clients_len {
+        let blocks_len: u32 = decoder.read_var()?;
+        let client = decoder.read_client()?;
+        let mut clock: u32 = decoder.read_var()?;
+        for _ in 0..blocks_len {
+            let len = match decoder.read_info()? {
+                BLOCK_SKIP_REF_NUMBER => decoder.read_var()?,
+                BLOCK_GC_REF_NUMBER => decoder.read_len()?,
+                info => {
+                    let block = read_block(&mut decoder, info, ID::new(client, clock))?;
+                    let len = block.len;
+                    if clock < state.get(&client) {
+                        blocks.push(block);
+                    }
+                    len
+                }
+            };
+            clock += len;
+        }
+    }
+    Ok(blocks)
+}
+
+fn read_block<D: Decoder>(decoder: &mut D, info: u8, id: ID) -> Result<Block, Error> {
+    let cant_copy_parent_info = info & (HAS_ORIGIN | HAS_RIGHT_ORIGIN) == 0;
+    let origin = if info & HAS_ORIGIN != 0 { Some(decoder.read_left_id()?) } else { None };
+    let right_origin = if info & HAS_RIGHT_ORIGIN != 0 { Some(decoder.read_right_id()?) } else { None };
+    let parent = if !cant_copy_parent_info {
+        EncodedParent::Inherited
+    } else if decoder.read_parent_info()? {
+        EncodedParent::Root(decoder.read_string()?.into())
+    } else {
+        EncodedParent::Nested(decoder.read_left_id()?)
+    };
+    let parent_sub = if cant_copy_parent_info && info & HAS_PARENT_SUB != 0 {
+        Some(decoder.read_string()?.into())
+    } else {
+        None
+    };
+    let content = ItemContent::decode(decoder, info)?;
+    let len = content.len(OffsetKind::Utf16);
+    Ok(Block { id, len, origin, right_origin, parent, parent_sub, content })
+}
+
+/// The value of an element that is not a shared type.
+fn any_value(content: &ItemContent, offset: usize) -> Option<Any> {
+    match content {
+        ItemContent::Any(values) => values.get(offset).cloned(),
+        ItemContent::Embed(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// The string of a text at a snapshot, read from the text itself, which yrs supports.
+fn text_string(txn: &mut TransactionMut, id: &ID, snapshot: &Snapshot) -> String {
+    let Some(branch) = BranchID::Nested(*id).get_branch(txn) else {
+        return String::new();
+    };
+    let mut s = String::new();
+    for diff in TextRef::from(branch).diff_range(txn, Some(snapshot), None, YChange::identity) {
+        if let Out::Any(Any::String(chunk)) = diff.insert {
+            s.push_str(&chunk);
+        }
+    }
+    s
+}
+
+/// The string representation of an XML text at a snapshot, like yrs' `get_string`.
+fn xml_text_string(txn: &mut TransactionMut, id: &ID, snapshot: &Snapshot) -> String {
+    let Some(branch) = BranchID::Nested(*id).get_branch(txn) else {
+        return String::new();
+    };
+    let mut s = String::new();
+    for diff in TextRef::from(branch).diff_range(txn, Some(snapshot), None, YChange::identity) {
+        let mut attrs: Vec<_> = diff.attributes.iter().flat_map(|attrs| attrs.iter()).collect();
+        attrs.sort_by(|a, b| a.0.cmp(b.0));
+        for (node, value) in attrs.iter() {
+            write!(s, "<{}", node).unwrap();
+            if let Any::Map(value) = value {
+                for (k, v) in value.iter() {
+                    write!(s, " {}=\"{}\"", k, v).unwrap();
+                }
+            }
+            s.push('>');
+        }
+        if let Out::Any(value) = diff.insert {
+            write!(s, "{}", value).unwrap();
+        }
+        for (node, _) in attrs.iter().rev() {
+            write!(s, "</{}>", node).unwrap();
+        }
+    }
+    s
+}
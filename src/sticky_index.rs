@@ -4,6 +4,8 @@ use std::cell::RefCell;
 use yrs::{StickyIndex as _StickyIndex, Assoc};
 use yrs::updates::decoder::Decode;
 use yrs::updates::encoder::Encode;
+use crate::exceptions::{ConversionError, DecodeError, IntegrationError};
+use crate::transaction::TransactionAccess;
 use crate::Transaction;
 
 #[pyclass(unsendable)]
@@ -19,29 +21,36 @@ impl From<Option<_StickyIndex>> for StickyIndex {
     }
 }
 
-impl From<&[u8]> for StickyIndex {
-    fn from(data: &[u8]) -> Self {
-        let sticky_index = _StickyIndex::decode_v1(data).unwrap();
-        let s: _StickyIndex = unsafe {std::mem::transmute(sticky_index.clone())};
-        StickyIndex { sticky_index: RefCell::from(Some(s)), assoc: sticky_index.assoc }
+impl TryFrom<&[u8]> for StickyIndex {
+    type Error = PyErr;
+
+    fn try_from(data: &[u8]) -> PyResult<Self> {
+        let sticky_index = _StickyIndex::decode_v1(data)
+            .map_err(|e| DecodeError::new_err(format!("Cannot decode sticky index: {}", e)))?;
+        Ok(StickyIndex::from(Some(sticky_index)))
     }
 }
 
-impl From<&str> for StickyIndex {
-    fn from(data: &str) -> Self {
-        let sticky_index = serde_json::from_str::<_StickyIndex>(data).unwrap();
-        let s: _StickyIndex = unsafe {std::mem::transmute(sticky_index.clone())};
-        StickyIndex { sticky_index: RefCell::from(Some(s)), assoc: sticky_index.assoc }
+impl TryFrom<&str> for StickyIndex {
+    type Error = PyErr;
+
+    fn try_from(data: &str) -> PyResult<Self> {
+        let sticky_index = serde_json::from_str::<_StickyIndex>(data)
+            .map_err(|e| DecodeError::new_err(format!("Cannot decode sticky index: {}", e)))?;
+        Ok(StickyIndex::from(Some(sticky_index)))
     }
 }
 
 #[pymethods]
 impl StickyIndex {
-    pub fn get_offset(&self, txn: &mut Transaction) -> u32 {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
-        self.sticky_index.borrow_mut().as_mut().unwrap().get_offset(t).unwrap().index
+    pub fn get_offset(&self, txn: &mut Transaction) -> PyResult<u32> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
+        let offset = self.sticky_index.borrow_mut().as_mut().unwrap().get_offset(t);
+        match offset {
+            Some(offset) => Ok(offset.index),
+            None => Err(IntegrationError::new_err("Sticky index does not point to an existing position")),
+        }
     }
 
     pub fn encode(&self) -> Py<PyAny> {
@@ -49,9 +58,10 @@ impl StickyIndex {
         Python::attach(|py| PyBytes::new(py, &encoded).into())
     }
 
-    pub fn to_json_string(&self) -> Py<PyAny> {
-        let encoded = serde_json::to_string(self.sticky_index.borrow_mut().as_mut().unwrap()).unwrap();
-        Python::attach(|py| PyString::new(py, &encoded).into())
+    pub fn to_json_string(&self) -> PyResult<Py<PyAny>> {
+        let encoded = serde_json::to_string(self.sticky_index.borrow_mut().as_mut().unwrap())
+            .map_err(|e| ConversionError::new_err(format!("Cannot encode sticky index: {}", e)))?;
+        Ok(Python::attach(|py| PyString::new(py, &encoded).into()))
     }
 
     pub fn get_assoc(&self) -> i8 {
@@ -65,13 +75,13 @@ impl StickyIndex {
 }
 
 #[pyfunction]
-pub fn decode_sticky_index<'py>(data: &Bound<'_, PyBytes>) -> StickyIndex {
+pub fn decode_sticky_index<'py>(data: &Bound<'_, PyBytes>) -> PyResult<StickyIndex> {
     let data: &[u8] = data.as_bytes();
-    StickyIndex::from(data)
+    StickyIndex::try_from(data)
 }
 
 #[pyfunction]
-pub fn get_sticky_index_from_json_string<'py>(data: &Bound<'_, PyString>) -> StickyIndex {
-    let data: &str = data.to_str().unwrap();
-    StickyIndex::from(data)
+pub fn get_sticky_index_from_json_string<'py>(data: &Bound<'_, PyString>) -> PyResult<StickyIndex> {
+    let data: &str = data.to_str()?;
+    StickyIndex::try_from(data)
 }
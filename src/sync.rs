@@ -0,0 +1,169 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyBytes;
+use yrs::StateVector;
+use yrs::encoding::read::Cursor;
+use yrs::sync::{AwarenessUpdate, Message as _Message, SyncMessage};
+use yrs::sync::protocol::{MSG_AUTH, MSG_AWARENESS, MSG_QUERY_AWARENESS, MSG_SYNC};
+use yrs::updates::decoder::{Decode, Decoder, DecoderV1};
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
+use crate::exceptions::DecodeError;
+
+/// A y-sync protocol message, as exchanged between Yjs peers.
+#[pyclass(frozen, eq)]
+#[derive(PartialEq)]
+pub struct Message {
+    message: _Message,
+}
+
+impl Message {
+    pub fn from(message: _Message) -> Self {
+        Message { message }
+    }
+}
+
+#[pymethods]
+impl Message {
+    /// Create a SYNC_STEP1 message carrying the given encoded state vector.
+    #[staticmethod]
+    pub fn sync_step1(state: &Bound<'_, PyBytes>) -> PyResult<Self> {
+        let state_vector = StateVector::decode_v1(state.as_bytes())
+            .map_err(|e| DecodeError::new_err(format!("Cannot decode state: {}", e)))?;
+        Ok(Message::from(_Message::Sync(SyncMessage::SyncStep1(state_vector))))
+    }
+
+    /// Create a SYNC_STEP2 message carrying the given update.
+    #[staticmethod]
+    pub fn sync_step2(update: &Bound<'_, PyBytes>) -> Self {
+        Message::from(_Message::Sync(SyncMessage::SyncStep2(update.as_bytes().to_vec())))
+    }
+
+    /// Create a SYNC_UPDATE message carrying the given update.
+    #[staticmethod]
+    pub fn sync_update(update: &Bound<'_, PyBytes>) -> Self {
+        Message::from(_Message::Sync(SyncMessage::Update(update.as_bytes().to_vec())))
+    }
+
+    /// Create an AWARENESS message carrying the given encoded awareness update.
+    #[staticmethod]
+    pub fn awareness(update: &Bound<'_, PyBytes>) -> PyResult<Self> {
+        let update = AwarenessUpdate::decode_v1(update.as_bytes())
+            .map_err(|e| DecodeError::new_err(format!("Cannot decode awareness update: {}", e)))?;
+        Ok(Message::from(_Message::Awareness(update)))
+    }
+
+    /// Create an AUTH message, denying permission if a `reason` is given.
+    #[staticmethod]
+    #[pyo3(signature = (reason=None))]
+    pub fn auth(reason: Option<String>) -> Self {
+        Message::from(_Message::Auth(reason))
+    }
+
+    /// Create a QUERY_AWARENESS message.
+    #[staticmethod]
+    pub fn query_awareness() -> Self {
+        Message::from(_Message::AwarenessQuery)
+    }
+
+    /// Create a custom message with the given `tag` and payload.
+    #[staticmethod]
+    pub fn custom(tag: u8, data: &Bound<'_, PyBytes>) -> PyResult<Self> {
+        if tag <= MSG_QUERY_AWARENESS {
+            return Err(PyValueError::new_err(format!("Message tag {} is reserved", tag)));
+        }
+        Ok(Message::from(_Message::Custom(tag, data.as_bytes().to_vec())))
+    }
+
+    /// The message type tag (see `YMessageType`).
+    #[getter]
+    pub fn message_type(&self) -> u8 {
+        match &self.message {
+            _Message::Sync(_) => MSG_SYNC,
+            _Message::Awareness(_) => MSG_AWARENESS,
+            _Message::Auth(_) => MSG_AUTH,
+            _Message::AwarenessQuery => MSG_QUERY_AWARENESS,
+            _Message::Custom(tag, _) => *tag,
+        }
+    }
+
+    /// The sync message type tag (see `YSyncMessageType`), for SYNC messages only.
+    #[getter]
+    pub fn sync_message_type(&self) -> Option<u8> {
+        match &self.message {
+            _Message::Sync(SyncMessage::SyncStep1(_)) => Some(0),
+            _Message::Sync(SyncMessage::SyncStep2(_)) => Some(1),
+            _Message::Sync(SyncMessage::Update(_)) => Some(2),
+            _ => None,
+        }
+    }
+
+    /// The message payload: the state for SYNC_STEP1, the update for SYNC_STEP2 and
+    /// SYNC_UPDATE, the awareness update for AWARENESS, and the data for custom messages.
+    #[getter]
+    pub fn payload<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        match &self.message {
+            _Message::Sync(SyncMessage::SyncStep1(state_vector)) => Some(PyBytes::new(py, &state_vector.encode_v1())),
+            _Message::Sync(SyncMessage::SyncStep2(update)) => Some(PyBytes::new(py, update)),
+            _Message::Sync(SyncMessage::Update(update)) => Some(PyBytes::new(py, update)),
+            _Message::Awareness(update) => Some(PyBytes::new(py, &update.encode_v1())),
+            _Message::Custom(_, data) => Some(PyBytes::new(py, data)),
+            _Message::Auth(_) | _Message::AwarenessQuery => None,
+        }
+    }
+
+    /// The reason why permission was denied, for AUTH messages only.
+    #[getter]
+    pub fn reason(&self) -> Option<String> {
+        match &self.message {
+            _Message::Auth(reason) => reason.clone(),
+            _ => None,
+        }
+    }
+
+    /// Encode the message to bytes.
+    pub fn encode<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.message.encode_v1())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Message({:?})", self.message)
+    }
+}
+
+/// Decode all the messages contained in `data`, which must be consumed entirely.
+#[pyfunction]
+pub fn decode_messages(data: &Bound<'_, PyBytes>) -> PyResult<Vec<Message>> {
+    let data = data.as_bytes();
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let mut decoder = DecoderV1::new(Cursor::new(&data[offset..]));
+        let message = _Message::decode(&mut decoder)
+            .map_err(|e| DecodeError::new_err(format!("Cannot decode message: {}", e)))?;
+        // the decoder does not expose its cursor, the rest of the buffer tells how much was read
+        let rest = decoder.read_to_end()
+            .map_err(|e| DecodeError::new_err(format!("Cannot decode message: {}", e)))?
+            .len();
+        offset = data.len() - rest;
+        messages.push(Message::from(message));
+    }
+    Ok(messages)
+}
+
+/// Decode a SYNC message whose leading message type has already been read.
+#[pyfunction]
+pub fn decode_sync_message(data: &Bound<'_, PyBytes>) -> PyResult<Message> {
+    SyncMessage::decode_v1(data.as_bytes())
+        .map(|message| Message::from(_Message::Sync(message)))
+        .map_err(|e| DecodeError::new_err(format!("Cannot decode message: {}", e)))
+}
+
+/// Encode `messages` one after another into a single buffer.
+#[pyfunction]
+pub fn encode_messages<'py>(py: Python<'py>, messages: Vec<PyRef<'py, Message>>) -> Bound<'py, PyBytes> {
+    let mut encoder = EncoderV1::new();
+    for message in messages.iter() {
+        message.message.encode(&mut encoder);
+    }
+    PyBytes::new(py, &encoder.to_vec())
+}
@@ -1,24 +1,39 @@
+use std::ops::Range;
+
 use pyo3::prelude::*;
 use pyo3::IntoPyObjectExt;
 use pyo3::types::{PyDict, PyIterator, PyList, PyString, PyTuple};
 use yrs::{
+    Any,
     Assoc,
     GetString,
+    ID,
+    IdSet as _IdSet,
     IndexedSequence,
     Observable,
+    OffsetKind,
+    Out,
+    ReadTxn,
+    Snapshot as _Snapshot,
     TextRef,
     Text as _Text,
     TransactionMut,
 };
 use yrs::types::array::ArrayPrelim;
 use yrs::types::map::MapPrelim;
-use yrs::types::text::{TextEvent as _TextEvent, TextPrelim, YChange};
+use yrs::branch::BranchID;
+use yrs::types::Attrs;
+use yrs::types::text::{ChangeKind, Diff, TextEvent as _TextEvent, TextPrelim, YChange};
+use crate::transaction::TransactionAccess;
 use crate::transaction::Transaction;
+use crate::exceptions::OffsetError;
+use crate::snapshot::{require_skip_gc, Snapshot};
 use crate::subscription::Subscription;
 use crate::type_conversions::{py_to_any, py_to_attrs, ToPython};
 use crate::array::Array;
 use crate::map::Map;
 use crate::sticky_index::StickyIndex;
+use crate::id_map::{ContentAttribute, IdMap};
 
 
 #[pyclass]
@@ -37,9 +52,8 @@ impl Text {
 #[pymethods]
 impl Text {
     fn len(&self, txn: &mut Transaction)  -> PyResult<u32> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let len = self.text.len(t);
         Ok(len)
     }
@@ -47,7 +61,8 @@ impl Text {
     #[pyo3(signature = (txn, index, chunk, attrs=None))]
     fn insert(&self, txn: &mut Transaction, index: u32, chunk: &str, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index])?;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
             self.text.insert_with_attributes(&mut t, index, chunk, attrs);
@@ -59,9 +74,10 @@ impl Text {
 
     #[pyo3(signature = (txn, index, embed, attrs=None))]
     fn insert_embed(&self, txn: &mut Transaction, index: u32, embed: Bound<'_, PyAny>, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<()> {
-        let embed = py_to_any(&embed);
+        let embed = py_to_any(&embed)?;
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index])?;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
             self.text.insert_embed_with_attributes(&mut t, index, embed, attrs);
@@ -74,7 +90,8 @@ impl Text {
     #[pyo3(signature = (txn, index, attrs=None))]
     fn insert_array_prelim<'py>(&self, txn: &mut Transaction, index: u32, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<Array> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index])?;
         let integrated;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
@@ -89,7 +106,8 @@ impl Text {
     #[pyo3(signature = (txn, index, attrs=None))]
     fn insert_map_prelim<'py>(&self, txn: &mut Transaction, index: u32, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<Map> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index])?;
         let integrated;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
@@ -104,7 +122,8 @@ impl Text {
     #[pyo3(signature = (txn, index, attrs=None))]
     fn insert_text_prelim<'py>(&self, txn: &mut Transaction, index: u32, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<Text> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index])?;
         let integrated;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
@@ -118,7 +137,8 @@ impl Text {
 
     fn format(&self, txn: &mut Transaction, index: u32, len: u32, attrs: Bound<'_, PyIterator>) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index, index + len])?;
         let attrs = py_to_attrs(attrs)?;
         self.text.format(&mut t, index, len, attrs);
         Ok(())
@@ -126,50 +146,37 @@ impl Text {
 
     fn remove_range(&self, txn: &mut Transaction, index: u32, len: u32) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index, index + len])?;
         self.text.remove_range(&mut t, index, len);
         Ok(())
     }
 
-    fn get_string<'py>(&mut self, py: Python<'py>, txn: &mut Transaction) -> Bound<'py, PyString> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
+    fn get_string<'py>(&mut self, py: Python<'py>, txn: &mut Transaction) -> PyResult<Bound<'py, PyString>> {
+        let t0 = txn.transaction();
+        let t = &t0.txn_ref()?;
         let s = self.text.get_string(t);
-        PyString::new(py, &s)
+        Ok(PyString::new(py, &s))
     }
 
-    fn diff<'py>(&self, py: Python<'py>, txn: &mut Transaction) -> Bound<'py, PyList> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
-
-        let iter = self.text.diff(t, YChange::identity)
-            .into_iter()
-            .map(|diff| {
-                let attrs = diff.attributes.map(|attrs| {
-                    let pyattrs = PyDict::new(py);
-                    for (name, value) in attrs.into_iter() {
-                        pyattrs.set_item(
-                            PyString::intern(py, &*name),
-                            value.into_py(py),
-                        ).unwrap();
-                    }
-                    pyattrs.into_any()
-                }).unwrap_or_else(|| py.None().into_bound(py));
+    #[pyo3(signature = (txn, snapshot=None))]
+    fn diff<'py>(&self, py: Python<'py>, txn: &mut Transaction, snapshot: Option<PyRef<'_, Snapshot>>) -> PyResult<Bound<'py, PyList>> {
+        text_diff(py, &self.text, txn, snapshot)
+    }
 
-                PyTuple::new(py, [
-                    diff.insert.into_py(py),
-                    attrs,
-                ]).unwrap()
-            });
+    #[pyo3(signature = (txn, prev_snapshot, snapshot=None))]
+    fn diff_snapshots<'py>(&self, py: Python<'py>, txn: &mut Transaction, prev_snapshot: PyRef<'_, Snapshot>, snapshot: Option<PyRef<'_, Snapshot>>) -> PyResult<Bound<'py, PyList>> {
+        text_diff_snapshots(py, &self.text, txn, prev_snapshot, snapshot)
+    }
 
-        PyList::new(py, iter).unwrap()
+    #[pyo3(signature = (txn, insertions, deletions=None, snapshot=None))]
+    fn diff_attributed<'py>(&self, py: Python<'py>, txn: &mut Transaction, insertions: PyRef<'_, IdMap>, deletions: Option<PyRef<'_, IdMap>>, snapshot: Option<PyRef<'_, Snapshot>>) -> PyResult<Bound<'py, PyList>> {
+        text_attributed_diff(py, &self.text, txn, &insertions, deletions.as_deref(), snapshot)
     }
 
     fn sticky_index<'py>(&self, py: Python<'py>, txn: &mut Transaction, index: u32, assoc: i8) -> PyResult<Py<StickyIndex>> {
         let mut _t = txn.transaction();
-        let t = _t.as_mut().unwrap().as_mut();
+        let t = _t.txn_mut()?;
         let _assoc: Assoc;
         match assoc {
             0 => _assoc = Assoc::After,
@@ -198,6 +205,290 @@ impl Text {
     }
 }
 
+/// Check that indices of a text are on character boundaries: with the bytes offset kind, yrs
+/// panics when an index is inside a multi-byte character.
+pub(crate) fn check_char_boundaries<T: _Text>(text: &T, txn: &TransactionMut, indices: &[u32]) -> PyResult<()> {
+    if !matches!(txn.doc().offset_kind(), OffsetKind::Bytes) {
+        return Ok(());
+    }
+    // the start and the end of the text are always boundaries
+    let len = text.len(txn);
+    let indices: Vec<u32> = indices.iter().copied().filter(|&index| index > 0 && index < len).collect();
+    let Some(&last) = indices.iter().max() else {
+        return Ok(());
+    };
+    let mut start = 0;
+    for chunk in text.diff(txn, YChange::identity) {
+        if start >= last {
+            break;
+        }
+        let Out::Any(Any::String(chunk)) = chunk.insert else {
+            // an embed has a length of 1
+            start += 1;
+            continue;
+        };
+        let end = start + chunk.len() as u32;
+        for &index in &indices {
+            if index > start && index < end && !chunk.is_char_boundary((index - start) as usize) {
+                return Err(OffsetError::new_err(format!("Index {} is not on a character boundary", index)));
+            }
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+/// The formatted chunks of a text as `(insert, attributes)` tuples, optionally as they were at
+/// a snapshot.
+pub fn text_diff<'py, T: _Text>(
+    py: Python<'py>,
+    text: &T,
+    txn: &mut Transaction,
+    snapshot: Option<PyRef<'_, Snapshot>>,
+) -> PyResult<Bound<'py, PyList>> {
+    let mut t0 = txn.transaction();
+    let diff = match snapshot {
+        None => text.diff(&t0.txn_ref()?, YChange::identity),
+        Some(snapshot) => {
+            // the blocks are split at the snapshot, which requires a read-write transaction
+            let t = t0.txn_mut()?;
+            require_skip_gc(t)?;
+            text.diff_range(t, Some(&snapshot.snapshot), None, YChange::identity)
+        }
+    };
+    let iter = diff
+        .into_iter()
+        .map(|diff| PyTuple::new(py, [diff.insert.into_py(py), attrs_into_py(py, diff.attributes)]).unwrap());
+    PyList::new(py, iter)
+}
+
+/// The formatted chunks of a text as `(insert, attributes, change)` tuples, as they were at a
+/// snapshot (the current one by default), including the content removed since a previous
+/// snapshot. The change is `None` for content that was not added or removed since then.
+pub fn text_diff_snapshots<'py, T: _Text>(
+    py: Python<'py>,
+    text: &T,
+    txn: &mut Transaction,
+    prev_snapshot: PyRef<'_, Snapshot>,
+    snapshot: Option<PyRef<'_, Snapshot>>,
+) -> PyResult<Bound<'py, PyList>> {
+    let mut t0 = txn.transaction();
+    let t = t0.txn_mut()?;
+    require_skip_gc(t)?;
+    let snapshot = match snapshot {
+        Some(snapshot) => snapshot.snapshot.clone(),
+        None => t.snapshot(),
+    };
+    let diff = text.diff_range(t, Some(&snapshot), Some(&prev_snapshot.snapshot), YChange::identity);
+
+    let iter = diff
+        .into_iter()
+        .map(|diff| {
+            let attrs = attrs_into_py(py, diff.attributes);
+            let change = diff.ychange.map(|change| {
+                let pychange = PyDict::new(py);
+                let kind = match change.kind {
+                    ChangeKind::Added => "added",
+                    ChangeKind::Removed => "removed",
+                };
+                pychange.set_item("type", kind).unwrap();
+                pychange.set_item("client", change.id.client.get()).unwrap();
+                pychange.set_item("clock", change.id.clock).unwrap();
+                pychange.into_any()
+            }).unwrap_or_else(|| py.None().into_bound(py));
+            PyTuple::new(py, [diff.insert.into_py(py), attrs, change]).unwrap()
+        });
+
+    PyList::new(py, iter)
+}
+
+fn attrs_into_py(py: Python<'_>, attrs: Option<Box<Attrs>>) -> Bound<'_, PyAny> {
+    attrs.map(|attrs| {
+        let pyattrs = PyDict::new(py);
+        for (name, value) in attrs.into_iter() {
+            pyattrs.set_item(
+                PyString::intern(py, &name),
+                value.into_py(py),
+            ).unwrap();
+        }
+        pyattrs.into_any()
+    }).unwrap_or_else(|| py.None().into_bound(py))
+}
+
+/// A chunk of an attributed text diff. The attribution holds the attributes of the insertion of
+/// the chunk, and of its deletion if it is a tombstone. It is `None` for embedded values, whose ID
+/// is not known.
+struct AttributedChunk {
+    insert: Out,
+    attrs: Option<Box<Attrs>>,
+    attribution: Option<(Vec<ContentAttribute>, Option<Vec<ContentAttribute>>)>,
+}
+
+/// The formatted chunks of a text as `(insert, attributes, attribution)` tuples, optionally as
+/// they were at a snapshot, split where the attributes that `insertions` (and `deletions` for
+/// tombstones) attach to their content change. If `deletions` is given, the deleted content that
+/// it covers is rendered as tombstones.
+pub fn text_attributed_diff<'py, T: _Text>(
+    py: Python<'py>,
+    text: &T,
+    txn: &mut Transaction,
+    insertions: &IdMap,
+    deletions: Option<&IdMap>,
+    snapshot: Option<PyRef<'_, Snapshot>>,
+) -> PyResult<Bound<'py, PyList>> {
+    let mut t0 = txn.transaction();
+    let t = t0.txn_mut()?;
+    let snapshot = match snapshot {
+        Some(snapshot) => {
+            require_skip_gc(t)?;
+            snapshot.snapshot.clone()
+        }
+        None => t.snapshot(),
+    };
+    // all the content is added since an empty snapshot, so that each item is its own chunk
+    // along with its ID
+    let empty = _Snapshot::default();
+    let visible = text.diff_range(t, Some(&snapshot), Some(&empty), |change| change.id);
+    let mut chunks = Vec::new();
+    match deletions {
+        None => {
+            for diff in visible {
+                push_attributed(&mut chunks, diff, insertions, None);
+            }
+        }
+        Some(deletions) => {
+            // the blocks are already split at the deletions, so each item of this diff is
+            // either deleted or visible as a whole at the snapshot
+            let all = _Snapshot::new(snapshot.state_map.clone(), _IdSet::new());
+            let every = text.diff_range(t, Some(&all), Some(&empty), |change| change.id);
+            let mut visible = visible.into_iter().peekable();
+            for diff in every {
+                match chunk_id(&diff) {
+                    Some(id) if snapshot.delete_set.contains(&id) => {
+                        push_attributed(&mut chunks, diff, insertions, Some(deletions));
+                    }
+                    Some(id) => {
+                        for diff in visible.by_ref() {
+                            let found = chunk_id(&diff) == Some(id);
+                            push_attributed(&mut chunks, diff, insertions, None);
+                            if found {
+                                break;
+                            }
+                        }
+                    }
+                    // an embedded value is only known to be visible if it is the next one
+                    None => {
+                        if let Some(next) = visible.next_if(|next| chunk_id(next).is_none() && next.insert == diff.insert) {
+                            push_attributed(&mut chunks, next, insertions, None);
+                        }
+                    }
+                }
+            }
+            for diff in visible {
+                push_attributed(&mut chunks, diff, insertions, None);
+            }
+        }
+    }
+
+    let iter = chunks.into_iter().map(|chunk| {
+        let attrs = attrs_into_py(py, chunk.attrs);
+        let attribution = chunk.attribution.map(|(inserted, deleted)| {
+            let pyattribution = PyDict::new(py);
+            pyattribution.set_item("insert", inserted).unwrap();
+            if let Some(deleted) = deleted {
+                pyattribution.set_item("delete", deleted).unwrap();
+            }
+            pyattribution.into_any()
+        }).unwrap_or_else(|| py.None().into_bound(py));
+        PyTuple::new(py, [chunk.insert.into_py(py), attrs, attribution]).unwrap()
+    });
+    PyList::new(py, iter)
+}
+
+/// The ID of the item of a chunk: the ID of the text or of the shared type, `None` for other
+/// embedded values.
+fn chunk_id(diff: &Diff<ID>) -> Option<ID> {
+    match diff.insert.try_branch().map(|branch| branch.id()) {
+        Some(BranchID::Nested(id)) => Some(id),
+        _ => diff.ychange,
+    }
+}
+
+/// Appends the chunks of a diff item, split at the attribution boundaries of its content. If
+/// `deletions` is given the item is a tombstone, and only its content covered by `deletions` is
+/// appended.
+fn push_attributed(chunks: &mut Vec<AttributedChunk>, diff: Diff<ID>, insertions: &IdMap, deletions: Option<&IdMap>) {
+    let Some(id) = chunk_id(&diff) else {
+        push_chunk(chunks, AttributedChunk { insert: diff.insert, attrs: diff.attributes, attribution: None });
+        return;
+    };
+    let content = match &diff.insert {
+        Out::Any(Any::String(s)) => Some(s.clone()),
+        _ => None,
+    };
+    // item clocks count UTF-16 code units
+    let len = content.as_ref().map_or(1, |s| s.encode_utf16().count() as u32);
+    let inserted = insertions.attributed_ranges(id, len);
+    let deleted = deletions.map(|deletions| deletions.attributed_ranges(id, len));
+    let mut bounds: Vec<u32> = inserted
+        .iter()
+        .chain(deleted.iter().flatten())
+        .map(|(range, _)| range.end - id.clock)
+        .collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let attributes_at = |ranges: &[(Range<u32>, Option<Vec<ContentAttribute>>)], offset: u32| {
+        ranges
+            .iter()
+            .find(|(range, _)| range.contains(&(id.clock + offset)))
+            .and_then(|(_, attributes)| attributes.clone())
+    };
+    let mut units = content.as_deref().map(|s| s.chars());
+    let mut start = 0;
+    for end in bounds {
+        // a bound within a surrogate pair is moved after it
+        let part = units.as_mut().map(|units| {
+            let mut part = String::new();
+            let mut offset = start;
+            while offset < end {
+                let Some(c) = units.next() else { break };
+                offset += c.len_utf16() as u32;
+                part.push(c);
+            }
+            part
+        });
+        let attribution = match &deleted {
+            None => Some((attributes_at(&inserted, start).unwrap_or_default(), None)),
+            Some(deleted) => attributes_at(deleted, start)
+                .map(|deleted| (attributes_at(&inserted, start).unwrap_or_default(), Some(deleted))),
+        };
+        start = end;
+        if let Some(attribution) = attribution {
+            let insert = match part {
+                Some(part) if part.is_empty() => continue,
+                Some(part) => Out::Any(Any::from(part)),
+                None => diff.insert.clone(),
+            };
+            push_chunk(chunks, AttributedChunk { insert, attrs: diff.attributes.clone(), attribution: Some(attribution) });
+        }
+    }
+}
+
+/// Appends a chunk, merging its text into the previous chunk if they have the same attributes
+/// and attribution.
+fn push_chunk(chunks: &mut Vec<AttributedChunk>, chunk: AttributedChunk) {
+    if let Some(last) = chunks.last_mut()
+        && let (Out::Any(Any::String(prev)), Out::Any(Any::String(next))) = (&last.insert, &chunk.insert)
+        && last.attrs == chunk.attrs
+        && last.attribution == chunk.attribution
+    {
+        last.insert = Out::Any(Any::from(format!("{}{}", prev, next)));
+        return;
+    }
+    chunks.push(chunk);
+}
+
 #[pyclass(unsendable)]
 pub struct TextEvent {
     event: *const _TextEvent,
@@ -1,6 +1,46 @@
+use futures_task::{waker, ArcWake};
 use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PySet};
 use std::cell::{RefCell, RefMut};
-use yrs::{Origin, TransactionMut};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+use yrs::encoding::write::Write;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
+use yrs::{
+    AcquireTransaction, AcquireTransactionMut, Doc as _Doc, Options, Origin, ReadTxn,
+    Snapshot, StateVector, Store, Transact, Transaction as ReadOnlyTransaction, TransactionMut,
+    Update,
+};
+use crate::changed_types::{paths_into_py, pending_changed_types};
+use crate::doc::{changes_into_py, is_aborting, Doc};
+use crate::exceptions::TransactionError;
+use crate::snapshot::restore_doc;
+use crate::undo::IdSet as PyIdSet;
+
+/// Restore the document of a transaction at a snapshot in a new document.
+fn restore_at(t: &TransactionMut, snapshot: Snapshot) -> PyResult<Doc> {
+    let copy = skip_gc_copy(t)?;
+    let copy_txn = copy.transact();
+    Ok(Doc::from(restore_doc(&copy_txn, &snapshot)?))
+}
+
+/// Copy the document of a transaction to a document that keeps its deleted content, so that it can
+/// be read at any state. The deleted content of the transaction is only garbage collected when it
+/// is committed, so it is copied too.
+fn skip_gc_copy(t: &TransactionMut) -> PyResult<_Doc> {
+    let update = Update::decode_v1(&t.encode_state_as_update_v1(&StateVector::default()))
+        .map_err(|e| TransactionError::new_err(format!("Cannot copy document: {}", e)))?;
+    let copy = _Doc::with_options(Options { skip_gc: true, ..Options::default() });
+    copy.transact_mut().apply_update(update)
+        .map_err(|e| TransactionError::new_err(format!("Cannot copy document: {}", e)))?;
+    Ok(copy)
+}
 
 pub enum Cell<'a, T> {
     Owned(T),
@@ -16,36 +56,122 @@ impl<'a, T> AsRef<T> for Cell<'a, T> {
     }
 }
 
-impl<'a, T> AsMut<T> for Cell<'a, T> {
-    fn as_mut(&mut self) -> &mut T {
+/// A read-write transaction, or a read-only one that other read-only transactions can be held
+/// along with.
+// read-write transactions are the most common, and are not boxed to avoid allocating them
+#[allow(clippy::large_enum_variant)]
+pub enum Txn {
+    ReadWrite(Cell<'static, TransactionMut<'static>>),
+    ReadOnly(ReadOnlyTransaction<'static>),
+}
+
+/// A transaction that can be read from, whether it is read-write or read-only.
+pub enum ReadTxnRef<'a> {
+    ReadWrite(&'a TransactionMut<'static>),
+    ReadOnly(&'a ReadOnlyTransaction<'static>),
+}
+
+impl ReadTxn for ReadTxnRef<'_> {
+    fn store(&self) -> &Store {
         match self {
-            Cell::Owned(v) => v,
-            Cell::Borrowed(_) => {
-                panic!("Transactions executed in context of observer callbacks cannot be used to modify document structure")
-            }
+            ReadTxnRef::ReadWrite(txn) => txn.store(),
+            ReadTxnRef::ReadOnly(txn) => txn.store(),
+        }
+    }
+}
+
+/// Access to the underlying transaction, failing if it was dropped or, for modifications, if it
+/// is read-only or belongs to an observer callback.
+pub trait TransactionAccess {
+    fn txn_ref(&self) -> PyResult<ReadTxnRef<'_>>;
+    fn txn_mut(&mut self) -> PyResult<&mut TransactionMut<'static>>;
+}
+
+impl TransactionAccess for Option<Txn> {
+    fn txn_ref(&self) -> PyResult<ReadTxnRef<'_>> {
+        match self {
+            Some(Txn::ReadWrite(cell)) => Ok(ReadTxnRef::ReadWrite(cell.as_ref())),
+            Some(Txn::ReadOnly(txn)) => Ok(ReadTxnRef::ReadOnly(txn)),
+            None => Err(TransactionError::new_err("Transaction was dropped")),
         }
     }
+
+    fn txn_mut(&mut self) -> PyResult<&mut TransactionMut<'static>> {
+        match self {
+            Some(Txn::ReadWrite(Cell::Owned(v))) => Ok(v),
+            Some(Txn::ReadWrite(Cell::Borrowed(_))) => Err(TransactionError::new_err(
+                "Transactions executed in context of observer callbacks cannot be used to modify document structure",
+            )),
+            Some(Txn::ReadOnly(_)) => Err(TransactionError::new_err(
+                "Read-only transaction cannot be used to modify document structure",
+            )),
+            None => Err(TransactionError::new_err("Transaction was dropped")),
+        }
+    }
+}
+
+/// A transaction that can be used with the GIL released, in `Python::detach`.
+///
+/// Releasing the GIL does not move the work to another thread, so `Detached` is only `Send` for
+/// the transaction types that are used this way, which are not shared with other threads.
+pub struct Detached<T>(T);
+
+// SAFETY: the reference is taken from a borrowed `Transaction`, which is `unsendable`, so no
+// other thread can reach the transaction while the GIL is released.
+unsafe impl Send for Detached<ReadTxnRef<'_>> {}
+
+// SAFETY: the reference is taken from a mutably borrowed `Transaction`, which is `unsendable`,
+// so no other thread can reach the transaction while the GIL is released.
+unsafe impl Send for Detached<&mut TransactionMut<'static>> {}
+
+// SAFETY: the acquisition is mutably borrowed from a `TransactionAcquisition`, which is
+// `unsendable`, and it is only polled by the thread that released the GIL.
+unsafe impl Send for Detached<&mut Acquire> {}
+
+// SAFETY: the acquired transaction is only returned to the thread that released the GIL, where
+// it is moved into an `unsendable` `Transaction`.
+unsafe impl Send for Detached<Txn> {}
+
+impl<T> Detached<T> {
+    pub fn new(txn: T) -> Self {
+        Detached(txn)
+    }
+
+    pub fn txn(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
 }
 
 #[pyclass(unsendable)]
-pub struct Transaction(RefCell<Option<Cell<'static, TransactionMut<'static>>>>);
+pub struct Transaction(RefCell<Option<Txn>>);
 
 impl<'doc> From<TransactionMut<'doc>> for Transaction {
     fn from(txn: TransactionMut<'doc>) -> Self {
         let t: TransactionMut<'static> = unsafe { std::mem::transmute(txn) };
-        Transaction(RefCell::from(Some(Cell::Owned(t))))
+        Transaction(RefCell::from(Some(Txn::ReadWrite(Cell::Owned(t)))))
     }
 }
 
 impl<'doc> From<&TransactionMut<'doc>> for Transaction {
     fn from(txn: &TransactionMut<'doc>) -> Self {
         let t: &TransactionMut<'static> = unsafe { std::mem::transmute(txn) };
-        Transaction(RefCell::from(Some(Cell::Borrowed(t))))
+        Transaction(RefCell::from(Some(Txn::ReadWrite(Cell::Borrowed(t)))))
+    }
+}
+
+impl<'doc> From<ReadOnlyTransaction<'doc>> for Transaction {
+    fn from(txn: ReadOnlyTransaction<'doc>) -> Self {
+        let t: ReadOnlyTransaction<'static> = unsafe { std::mem::transmute(txn) };
+        Transaction(RefCell::from(Some(Txn::ReadOnly(t))))
     }
 }
 
 impl Transaction {
-    pub fn transaction(&self) -> RefMut<'_, Option<Cell<'static, TransactionMut<'static>>>> {
+    pub fn transaction(&self) -> RefMut<'_, Option<Txn>> {
         self.0.borrow_mut()
     }
 }
@@ -53,7 +179,7 @@ impl Transaction {
 #[pymethods]
 impl Transaction {
     pub fn commit(&mut self, py: Python<'_>) -> PyResult<()> {
-        self.transaction().as_mut().unwrap().as_mut().commit();
+        self.transaction().txn_mut()?.commit();
         // Check if any Python exception was raised during commit (e.g., in callbacks)
         if let Some(err) = pyo3::PyErr::take(py) {
             return Err(err);
@@ -65,14 +191,259 @@ impl Transaction {
         self.0.replace(None);
     }
 
-    pub fn origin(&self) -> Option<i128> {
+    /// Delete the content inserted so far in the transaction, locally or by the updates applied
+    /// in it. The content it deleted that existed before it is not restored (see `restore_before`).
+    pub fn abort(&mut self) -> PyResult<()> {
+        let mut _t = self.transaction();
+        let t = _t.txn_mut()?;
+        let inserted = t.insert_set().clone();
+        // the inserted content is deleted by an update made of a delete set only
+        let mut encoder = EncoderV1::new();
+        encoder.write_var(0u32);
+        inserted.encode(&mut encoder);
+        let update = Update::decode_v1(&encoder.to_vec())
+            .map_err(|e| TransactionError::new_err(format!("Cannot roll back transaction: {}", e)))?;
+        t.apply_update(update)
+            .map_err(|e| TransactionError::new_err(format!("Cannot roll back transaction: {}", e)))
+    }
+
+    /// The document as it was before the transaction, restored in a new document, if the
+    /// transaction deleted content that existed before it, for that content to be inserted back.
+    pub fn restore_before(&mut self) -> PyResult<Option<Doc>> {
+        let mut _t = self.transaction();
+        let t = _t.txn_mut()?;
+        let deleted = t.delete_set().diff(t.insert_set());
+        if deleted.is_empty() {
+            return Ok(None);
+        }
+        // the content inserted in the transaction is left out, and the content it deleted
+        // is visible
+        let state: StateVector = t.before_state().iter()
+            .filter(|(_, clock)| **clock > 0)
+            .map(|(&client, &clock)| (client, clock))
+            .collect();
+        let delete_set = t.snapshot().delete_set.diff(&deleted);
+        Ok(Some(restore_at(t, Snapshot::new(state, delete_set))?))
+    }
+
+    /// The events that the deep observers of the root types will get for the changes made so far
+    /// in the transaction, by root type name, with the document they are replayed on.
+    pub fn pending_events<'py>(&mut self, py: Python<'py>) -> PyResult<(Doc, Bound<'py, PyDict>)> {
+        let mut _t = self.transaction();
+        let t = _t.txn_mut()?;
+        let update = Update::decode_v1(&t.encode_update_v1())
+            .map_err(|e| TransactionError::new_err(format!("Cannot replay transaction: {}", e)))?;
+        let delete_set = t.snapshot().delete_set.diff(t.delete_set());
+        let snapshot = Snapshot::new(t.before_state().clone(), delete_set);
+        let copy = skip_gc_copy(t)?;
+        let copy_txn = copy.transact();
+        changes_into_py(py, &copy_txn, &snapshot, update)
+    }
+
+    /// Whether content was inserted or deleted in the transaction.
+    pub fn has_changes(&self) -> PyResult<bool> {
         let transaction = self.0.borrow();
-        let origin: Option<&Origin> = transaction.as_ref().unwrap().as_ref().origin();
-        if origin.is_some() {
-            let data: [u8; 16] = origin.unwrap().as_ref().try_into().expect("Slice with incorrect length");
-            Some(i128::from_be_bytes(data))
+        match transaction.txn_ref()? {
+            ReadTxnRef::ReadWrite(txn) => Ok(!txn.insert_set().is_empty() || !txn.delete_set().is_empty()),
+            ReadTxnRef::ReadOnly(_) => Ok(false),
+        }
+    }
+
+    /// Whether the transaction is an aborted transaction being committed.
+    pub fn aborting(&self) -> PyResult<bool> {
+        let transaction = self.0.borrow();
+        match transaction.txn_ref()? {
+            ReadTxnRef::ReadWrite(txn) => Ok(is_aborting(txn)),
+            ReadTxnRef::ReadOnly(_) => Ok(false),
+        }
+    }
+
+    /// The update of the changes made so far in the transaction.
+    pub fn pending_update<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let transaction = self.0.borrow();
+        match transaction.txn_ref()? {
+            ReadTxnRef::ReadWrite(txn) => Ok(PyBytes::new(py, &txn.encode_update_v1())),
+            ReadTxnRef::ReadOnly(_) => Err(TransactionError::new_err("Read-only transaction has no changes")),
+        }
+    }
+
+    /// The content deleted so far in the transaction.
+    pub fn pending_delete_set(&self) -> PyResult<PyIdSet> {
+        let transaction = self.0.borrow();
+        match transaction.txn_ref()? {
+            ReadTxnRef::ReadWrite(txn) => Ok(PyIdSet::from(txn.delete_set().clone())),
+            ReadTxnRef::ReadOnly(_) => Err(TransactionError::new_err("Read-only transaction has no changes")),
+        }
+    }
+
+    /// The paths of the shared types changed so far in the transaction.
+    pub fn pending_changed_types<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PySet>> {
+        let transaction = self.0.borrow();
+        match transaction.txn_ref()? {
+            ReadTxnRef::ReadWrite(txn) => {
+                let paths = pending_changed_types(txn)
+                    .map_err(|e| TransactionError::new_err(format!("Cannot get changed types: {}", e)))?;
+                paths_into_py(py, &paths)
+            }
+            ReadTxnRef::ReadOnly(_) => Err(TransactionError::new_err("Read-only transaction has no changes")),
+        }
+    }
+
+    pub fn origin<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        let transaction = self.0.borrow();
+        let origin: Option<&Origin> = match transaction.txn_ref()? {
+            ReadTxnRef::ReadWrite(txn) => txn.origin(),
+            ReadTxnRef::ReadOnly(_) => None,
+        };
+        Ok(origin.map(|origin| PyBytes::new(py, origin.as_ref())))
+    }
+}
+
+/// A pending acquisition of the transaction of a document, in the queue of the document lock.
+pub enum Acquire {
+    ReadWrite(AcquireTransactionMut<'static>),
+    ReadOnly(AcquireTransaction<'static>),
+}
+
+impl Acquire {
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Txn> {
+        match self {
+            Acquire::ReadWrite(fut) => Pin::new(fut).poll(cx).map(|txn| Txn::ReadWrite(Cell::Owned(txn))),
+            Acquire::ReadOnly(fut) => Pin::new(fut).poll(cx).map(Txn::ReadOnly),
+        }
+    }
+}
+
+/// Wakes up a thread blocked in `TransactionAcquisition::wait`.
+struct ThreadWaker(Thread);
+
+impl ArcWake for ThreadWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.unpark();
+    }
+}
+
+/// Wakes up a task waiting for `TransactionAcquisition::poll`, by calling a Python callback that
+/// must be safe to call from any thread.
+struct PyWaker(Py<PyAny>);
+
+impl ArcWake for PyWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        // the document lock is woken up with its internal state locked, so the callback is called
+        // in the notifier thread, as taking the GIL here could deadlock with a thread holding it
+        let _ = notifier().send(arc_self.clone());
+    }
+}
+
+/// The channel to the thread that calls the callbacks of the woken up `PyWaker`s, which is
+/// started on the first wakeup and runs for the lifetime of the process.
+fn notifier() -> &'static Sender<Arc<PyWaker>> {
+    static NOTIFIER: OnceLock<Sender<Arc<PyWaker>>> = OnceLock::new();
+    NOTIFIER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Arc<PyWaker>>();
+        thread::spawn(move || {
+            for waker in receiver {
+                Python::attach(|py| {
+                    if let Err(err) = waker.0.call0(py) {
+                        err.write_unraisable(py, None);
+                    }
+                })
+            }
+        });
+        sender
+    })
+}
+
+/// The acquisition of a transaction, which waits for the ongoing transactions to be released.
+/// Acquisitions are served in order, and dropping a pending acquisition leaves the queue.
+#[pyclass(unsendable)]
+pub struct TransactionAcquisition {
+    acquire: Option<Acquire>,
+    // the pending acquisition borrows the store of the document
+    _doc: _Doc,
+}
+
+impl TransactionAcquisition {
+    pub fn new(doc: &_Doc, origin: Option<&[u8]>, read_only: bool) -> Self {
+        let acquire = if read_only {
+            let fut: AcquireTransaction<'_> = yrs::AsyncTransact::transact(doc);
+            Acquire::ReadOnly(unsafe { std::mem::transmute::<AcquireTransaction<'_>, AcquireTransaction<'static>>(fut) })
         } else {
-            None
+            let fut: AcquireTransactionMut<'_> = match origin {
+                Some(origin) => yrs::AsyncTransact::transact_mut_with(doc, origin),
+                None => yrs::AsyncTransact::transact_mut(doc),
+            };
+            Acquire::ReadWrite(unsafe { std::mem::transmute::<AcquireTransactionMut<'_>, AcquireTransactionMut<'static>>(fut) })
+        };
+        TransactionAcquisition { acquire: Some(acquire), _doc: doc.clone() }
+    }
+
+    fn acquire(&mut self) -> PyResult<&mut Acquire> {
+        self.acquire
+            .as_mut()
+            .ok_or_else(|| TransactionError::new_err("Transaction was already acquired"))
+    }
+
+    fn acquired(&mut self, py: Python<'_>, txn: Txn) -> PyResult<Py<Transaction>> {
+        self.acquire = None;
+        Py::new(py, Transaction(RefCell::from(Some(txn))))
+    }
+}
+
+#[pymethods]
+impl TransactionAcquisition {
+    /// Block until the transaction is acquired, with the GIL released, or until the timeout (in
+    /// seconds) expires, in which case the acquisition is cancelled. A negative timeout waits
+    /// forever.
+    #[pyo3(signature = (timeout=None))]
+    pub fn wait(&mut self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Option<Py<Transaction>>> {
+        // a timeout too large to be represented waits forever
+        let deadline = timeout
+            .filter(|timeout| *timeout >= 0.0)
+            .and_then(|timeout| Duration::try_from_secs_f64(timeout).ok())
+            .and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut acquire = Detached::new(self.acquire()?);
+        let txn = py.detach(|| {
+            let waker = waker(Arc::new(ThreadWaker(thread::current())));
+            let mut cx = Context::from_waker(&waker);
+            loop {
+                if let Poll::Ready(txn) = acquire.txn().poll(&mut cx) {
+                    return Some(Detached::new(txn));
+                }
+                match deadline {
+                    None => thread::park(),
+                    Some(deadline) => {
+                        let now = Instant::now();
+                        if now >= deadline {
+                            return None;
+                        }
+                        thread::park_timeout(deadline - now);
+                    }
+                }
+            }
+        });
+        match txn {
+            Some(txn) => Ok(Some(self.acquired(py, txn.into_inner())?)),
+            None => {
+                self.cancel();
+                Ok(None)
+            }
+        }
+    }
+
+    /// Try to acquire the transaction without blocking. If it cannot be acquired yet, `wake` is
+    /// called (possibly from another thread) when it should be polled again.
+    pub fn poll(&mut self, py: Python<'_>, wake: Py<PyAny>) -> PyResult<Option<Py<Transaction>>> {
+        let waker = waker(Arc::new(PyWaker(wake)));
+        let mut cx = Context::from_waker(&waker);
+        match self.acquire()?.poll(&mut cx) {
+            Poll::Ready(txn) => Ok(Some(self.acquired(py, txn)?)),
+            Poll::Pending => Ok(None),
         }
     }
+
+    /// Leave the queue, if the transaction was not acquired yet.
+    pub fn cancel(&mut self) {
+        self.acquire = None;
+    }
 }
@@ -1,9 +1,10 @@
 use pyo3::prelude::*;
 use pyo3::IntoPyObjectExt;
-use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::exceptions::PyValueError;
 use pyo3::types::{PyAny, PyBool, PyByteArray, PyBytes, PyDict, PyFloat, PyIterator, PyList, PyInt, PyString, PyTuple};
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde_json::Value;
-use yrs::types::{Attrs, Change, EntryChange, Delta, Events, Path, PathSegment};
+use yrs::types::{Attrs, Change, EntryChange, Delta, Event, Events, Path, PathSegment};
 use yrs::{Any, Out, TransactionMut, XmlOut};
 use std::collections::{VecDeque, HashMap};
 use std::sync::Arc;
@@ -11,6 +12,7 @@ use crate::text::{Text, TextEvent};
 use crate::array::{Array, ArrayEvent};
 use crate::map::{Map, MapEvent};
 use crate::doc::Doc;
+use crate::exceptions::{ConversionError, DecodeError};
 use crate::xml::{XmlElement, XmlEvent, XmlFragment, XmlText};
 
 pub trait ToPython {
@@ -215,54 +217,62 @@ impl ToPython for Any {
     }
 }
 
-pub fn py_to_any<'py>(value: &Bound<'py, PyAny>) -> Any {
-    if value.is_none() {
+pub fn py_to_any<'py>(value: &Bound<'py, PyAny>) -> PyResult<Any> {
+    let any = if value.is_none() {
         Any::Null
     } else if value.is_instance_of::<PyBytes>() {
-        let v: &[u8] = value.extract().unwrap();
+        let v: &[u8] = value.extract()?;
         Any::Buffer(v.into())
     } else if value.is_instance_of::<PyString>() {
-        let v: &str = value.extract().unwrap();
+        let v: &str = value.extract()?;
         Any::String(v.into())
     } else if value.is_instance_of::<PyBool>() {
-        let v: bool = value.extract().unwrap();
+        let v: bool = value.extract()?;
         Any::Bool(v)
     } else if value.is_instance_of::<PyInt>() {
         const MAX_JS_NUMBER: i64 = 2_i64.pow(53) - 1;
-        let v: i64 = value.extract().unwrap();
+        let v: i64 = value
+            .extract()
+            .map_err(|_| ConversionError::new_err("Integer does not fit in 64 bits"))?;
         if v.abs() > MAX_JS_NUMBER {
             Any::BigInt(v)
         } else {
             Any::Number(v as f64)
         }
     } else if value.is_instance_of::<PyFloat>() {
-        let v: f64 = value.extract().unwrap();
+        let v: f64 = value.extract()?;
         Any::Number(v)
     } else if let Ok(v) = value.cast::<PyList>() {
         let mut items = Vec::new();
         for i in v.iter() {
-            let a = py_to_any(&i);
+            let a = py_to_any(&i)?;
             items.push(a);
         }
         Any::Array(items.into())
     } else if let Ok(v) = value.cast::<PyTuple>() {
         let mut items = Vec::new();
         for i in v.iter() {
-            let a = py_to_any(&i);
+            let a = py_to_any(&i)?;
             items.push(a);
         }
         Any::Array(items.into())
     } else if let Ok(val) = value.cast::<PyDict>() {
         let mut items: HashMap<String, Any> = HashMap::new();
         for (k, v) in val.iter() {
-            let k = k.cast::<PyString>().unwrap().to_str().unwrap().to_string();
-            let v = py_to_any(&v);
-            items.insert(k, v);
+            let Ok(k) = k.cast::<PyString>() else {
+                return Err(ConversionError::new_err(format!(
+                    "Keys must be str, not {}",
+                    k.get_type().name()?
+                )));
+            };
+            let v = py_to_any(&v)?;
+            items.insert(k.to_str()?.to_string(), v);
         }
         Any::Map(items.into())
     } else {
         Any::Undefined
-    }
+    };
+    Ok(any)
 }
 
 /// Convert a Python object into a JSON-compatible [`Any`], used for `IdMap` attribute values
@@ -272,11 +282,8 @@ pub fn py_to_any<'py>(value: &Bound<'py, PyAny>) -> Any {
 /// JS-number semantics as every other pycrdt value (small ints become `Any::Number`), and then
 /// rejects anything with no JSON representation: `bytes` (-> `Any::Buffer`), non-finite floats
 /// (`NaN`/`inf`), and unsupported types (-> `Any::Undefined`).
-///
-/// Note: a `dict` with non-string keys panics inside `py_to_any` before this validation runs (the
-/// same as for any other pycrdt value), surfacing as a `PanicException` rather than a `TypeError`.
 pub(crate) fn py_to_json_any(value: &Bound<'_, PyAny>) -> PyResult<Any> {
-    let any = py_to_any(value);
+    let any = py_to_any(value)?;
     ensure_json_compatible(&any)?;
     Ok(any)
 }
@@ -289,10 +296,10 @@ fn ensure_json_compatible(any: &Any) -> PyResult<()> {
         Any::Number(_) => Err(PyValueError::new_err(
             "NaN and infinity cannot be used as attribute values",
         )),
-        Any::Buffer(_) => Err(PyTypeError::new_err(
+        Any::Buffer(_) => Err(ConversionError::new_err(
             "bytes cannot be used as a JSON attribute value",
         )),
-        Any::Undefined => Err(PyTypeError::new_err(
+        Any::Undefined => Err(ConversionError::new_err(
             "attribute value must be JSON-serializable (None, bool, int, float, str, list, tuple, or dict)",
         )),
         Any::Array(items) => {
@@ -334,19 +341,152 @@ pub(crate) fn value_to_any(value: &Value) -> Any {
     Any::from_json(&value.to_string()).expect("serde_json::Value renders to valid JSON for Any")
 }
 
+/// Serialize a Python object into a JSON string, keeping `dict` insertion order and integer values
+/// as `json.dumps(value, separators=(",", ":"))` does. Used for awareness states, which are
+/// replicated to other peers as JSON strings.
+pub(crate) fn py_to_json_string(value: &Bound<'_, PyAny>) -> PyResult<String> {
+    let mut json = String::new();
+    write_json(value, &mut json)?;
+    Ok(json)
+}
+
+fn write_json(value: &Bound<'_, PyAny>, json: &mut String) -> PyResult<()> {
+    if value.is_none() {
+        json.push_str("null");
+    } else if let Ok(v) = value.cast::<PyBool>() {
+        json.push_str(if v.is_true() { "true" } else { "false" });
+    } else if value.is_instance_of::<PyInt>() {
+        json.push_str(value.str()?.to_str()?);
+    } else if let Ok(v) = value.cast::<PyFloat>() {
+        if !v.value().is_finite() {
+            return Err(PyValueError::new_err("Out of range float values are not JSON compliant"));
+        }
+        json.push_str(value.repr()?.to_str()?);
+    } else if let Ok(v) = value.cast::<PyString>() {
+        json.push_str(&Value::String(v.to_str()?.to_string()).to_string());
+    } else if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+        json.push('[');
+        for (i, item) in value.try_iter()?.enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write_json(&item?, json)?;
+        }
+        json.push(']');
+    } else if let Ok(v) = value.cast::<PyDict>() {
+        json.push('{');
+        for (i, (k, v)) in v.iter().enumerate() {
+            let Ok(k) = k.cast::<PyString>() else {
+                return Err(ConversionError::new_err("Keys must be str"));
+            };
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str(&Value::String(k.to_str()?.to_string()).to_string());
+            json.push(':');
+            write_json(&v, json)?;
+        }
+        json.push('}');
+    } else {
+        return Err(ConversionError::new_err(format!(
+            "Object of type {} is not JSON serializable",
+            value.get_type().name()?,
+        )));
+    }
+    Ok(())
+}
+
+/// Parse a JSON string into a Python object, keeping integers as `int` and object keys in their
+/// original order, as `json.loads(json)` does.
+pub(crate) fn json_to_py<'py>(py: Python<'py>, json: &str) -> PyResult<Bound<'py, PyAny>> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    JsonToPy(py)
+        .deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|_| value))
+        .map_err(|e| DecodeError::new_err(format!("Cannot parse JSON: {}", e)))?
+}
+
+#[derive(Clone, Copy)]
+struct JsonToPy<'py>(Python<'py>);
+
+impl<'de, 'py> DeserializeSeed<'de> for JsonToPy<'py> {
+    type Value = PyResult<Bound<'py, PyAny>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'py> Visitor<'de> for JsonToPy<'py> {
+    type Value = PyResult<Bound<'py, PyAny>>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Ok(self.0.None().into_bound(self.0)))
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(v.into_bound_py_any(self.0))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(v.into_bound_py_any(self.0))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(v.into_bound_py_any(self.0))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(v.into_bound_py_any(self.0))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(v.into_bound_py_any(self.0))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let list = PyList::empty(self.0);
+        while let Some(item) = seq.next_element_seed(self)? {
+            if let Err(err) = item.and_then(|item| list.append(item)) {
+                return Ok(Err(err));
+            }
+        }
+        Ok(Ok(list.into_any()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let dict = PyDict::new(self.0);
+        while let Some(key) = map.next_key::<String>()? {
+            let value = map.next_value_seed(self)?;
+            if let Err(err) = value.and_then(|value| dict.set_item(key, value)) {
+                return Ok(Err(err));
+            }
+        }
+        Ok(Ok(dict.into_any()))
+    }
+}
+
 pub(crate) fn events_into_py<'py>(py: Python<'py>, txn: &TransactionMut, events: &Events) -> Bound<'py, PyList> {
-    let py_events = events.iter().map(|event| match event {
-        yrs::types::Event::Text(e_txt) => Py::new(py, TextEvent::new(e_txt, txn)).unwrap().into_bound_py_any(py).unwrap(),
-        yrs::types::Event::Array(e_arr) => Py::new(py, ArrayEvent::new(e_arr, txn)).unwrap().into_bound_py_any(py).unwrap(),
-        yrs::types::Event::Map(e_map) => Py::new(py, MapEvent::new(e_map, txn)).unwrap().into_bound_py_any(py).unwrap(),
-        yrs::types::Event::XmlFragment(e_xml) => unsafe {
+    let py_events = events.iter().map(|event| event_into_py(py, txn, event));
+    PyList::new(py, py_events).unwrap()
+}
+
+pub(crate) fn event_into_py<'py>(py: Python<'py>, txn: &TransactionMut, event: &Event) -> Bound<'py, PyAny> {
+    match event {
+        Event::Text(e_txt) => Py::new(py, TextEvent::new(e_txt, txn)).unwrap().into_bound_py_any(py).unwrap(),
+        Event::Array(e_arr) => Py::new(py, ArrayEvent::new(e_arr, txn)).unwrap().into_bound_py_any(py).unwrap(),
+        Event::Map(e_map) => Py::new(py, MapEvent::new(e_map, txn)).unwrap().into_bound_py_any(py).unwrap(),
+        Event::XmlFragment(e_xml) => unsafe {
             Py::new(py, XmlEvent::from_xml_event(e_xml, txn, py)).unwrap().into_bound_py_any(py).unwrap()
         },
-        yrs::types::Event::XmlText(e_xml) => unsafe {
+        Event::XmlText(e_xml) => unsafe {
             Py::new(py, XmlEvent::from_xml_text_event(e_xml, txn, py)).unwrap().into_bound_py_any(py).unwrap()
         },
-    });
-    PyList::new(py, py_events).unwrap()
+    }
 }
 
 /// Converts an iterator of k,v tuples to an [`Attrs`] map
@@ -355,7 +495,7 @@ pub(crate) fn py_to_attrs<'py>(
 ) -> PyResult<Attrs> {
     pyobj.map(|res| res.and_then(|item| {
         let key = item.get_item(0)?.extract::<Bound<PyString>>()?;
-        let value = item.get_item(1).map(|v| py_to_any(&v))?;
+        let value = py_to_any(&item.get_item(1)?)?;
         Ok((Arc::from(key.to_str()?), value))
     })).collect::<PyResult<Attrs>>()
 }
@@ -4,7 +4,6 @@ use std::task::{Context, Poll};
 use std::collections::HashSet;
 use std::sync::Arc;
 use pyo3::prelude::*;
-use pyo3::exceptions::{PyRuntimeError, PyTypeError};
 use pyo3::types::{PyList, PyBytes};
 use yrs::IdSet as _IdSet;
 use yrs::undo::{
@@ -16,6 +15,7 @@ use yrs::sync::{Clock, Timestamp};
 use yrs::updates::encoder::Encode;
 use yrs::updates::decoder::Decode;
 use crate::doc::Doc;
+use crate::exceptions::{ConversionError, DecodeError, TransactionError};
 use crate::text::Text;
 use crate::array::Array;
 use crate::map::Map;
@@ -49,7 +49,7 @@ impl IdSet {
         let bytes: &[u8] = data.as_bytes();
         match _IdSet::decode_v1(bytes) {
             Ok(id_set) => Ok(IdSet { id_set }),
-            Err(e) => Err(pyo3::exceptions::PyValueError::new_err(format!(
+            Err(e) => Err(DecodeError::new_err(format!(
                 "Failed to decode IdSet: {}",
                 e
             ))),
@@ -71,8 +71,8 @@ impl IdSet {
     }
 }
 
-struct PythonClock {
-    timestamp: Py<PyAny>,
+pub(crate) struct PythonClock {
+    pub(crate) timestamp: Py<PyAny>,
 }
 
 impl Clock for PythonClock {
@@ -131,11 +131,11 @@ impl UndoManager {
         self.undo_manager.expand_scope(&doc.doc, &scope.fragment);
     }
 
-    pub fn include_origin(&mut self, origin: i128) {
+    pub fn include_origin(&mut self, origin: &[u8]) {
         self.undo_manager.include_origin(origin);
     }
 
-    pub fn exclude_origin(&mut self, origin: i128) {
+    pub fn exclude_origin(&mut self, origin: &[u8]) {
         self.undo_manager.exclude_origin(origin);
     }
 
@@ -149,7 +149,7 @@ impl UndoManager {
         let mut cx = Context::from_waker(&waker);
         match future.poll(&mut cx) {
             Poll::Ready(value) => { Ok(value) }
-            Poll::Pending => { Err(PyRuntimeError::new_err("Cannot acquire transaction")) }
+            Poll::Pending => { Err(TransactionError::new_err("Cannot acquire transaction")) }
         }
     }
 
@@ -163,7 +163,7 @@ impl UndoManager {
         let mut cx = Context::from_waker(&waker);
         match future.poll(&mut cx) {
             Poll::Ready(value) => { Ok(value) }
-            Poll::Pending => { Err(PyRuntimeError::new_err("Cannot acquire transaction")) }
+            Poll::Pending => { Err(TransactionError::new_err("Cannot acquire transaction")) }
         }
     }
 
@@ -213,7 +213,7 @@ impl StackItem {
         } else if let Ok(attr) = doc.getattr("_doc") {
             attr.extract::<Doc>()?
         } else {
-            return Err(PyTypeError::new_err("'doc' must be a Doc or pycrdt.Doc"));
+            return Err(ConversionError::new_err("'doc' must be a Doc or pycrdt.Doc"));
         };
         let stack_item = _StackItem::with_meta(
             _doc.doc.guid(),
@@ -1,13 +1,22 @@
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
 use pyo3::prelude::*;
-use pyo3::exceptions::PyValueError;
-use pyo3::types::{PyBytes, PyTuple};
-use yrs::{diff_updates_v1, encode_state_vector_from_update_v1, merge_updates_v1};
+use pyo3::types::{PyBytes, PyDict, PyList, PyString, PyTuple};
+use yrs::{Any, diff_updates_v1, diff_updates_v2, encode_state_vector_from_update_v1, merge_updates_v1, IdSet, StateVector, Update, ID};
+use yrs::block::{ItemContent, BLOCK_GC_REF_NUMBER, BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN};
+use yrs::encoding::read::{Cursor, Error, Read};
+use yrs::types::TypeRef;
+use yrs::updates::decoder::{Decode, Decoder, DecoderV1, DecoderV2};
+use yrs::updates::encoder::Encode;
+use yrs::OffsetKind;
+use crate::exceptions::DecodeError;
+use crate::type_conversions::ToPython;
 
 #[pyfunction]
 pub fn merge_updates<'py>(py: Python<'py>, updates: &Bound<'_, PyTuple>) -> PyResult<Bound<'py, PyBytes>> {
-    let updates: Vec<Vec<u8>> = updates.extract().unwrap();
-    let Ok(update) = merge_updates_v1(&updates) else {
-        return Err(PyValueError::new_err("Cannot merge updates"));
+    let updates: Vec<Vec<u8>> = updates.extract()?;
+    let Ok(update) = py.detach(|| merge_updates_v1(&updates)) else {
+        return Err(DecodeError::new_err("Cannot merge updates"));
     };
     Ok(PyBytes::new(py, &update))
 }
@@ -15,8 +24,8 @@ pub fn merge_updates<'py>(py: Python<'py>, updates: &Bound<'_, PyTuple>) -> PyRe
 #[pyfunction]
 pub fn get_state<'py>(py: Python<'py>, update: &Bound<'_, PyBytes>) -> PyResult<Bound<'py, PyBytes>> {
     let update: &[u8] = update.extract()?;
-    let Ok(u) = encode_state_vector_from_update_v1(&update) else {
-        return Err(PyValueError::new_err(
+    let Ok(u) = py.detach(|| encode_state_vector_from_update_v1(update)) else {
+        return Err(DecodeError::new_err(
             "Cannot encode state vector from update",
         ));
     };
@@ -27,8 +36,279 @@ pub fn get_state<'py>(py: Python<'py>, update: &Bound<'_, PyBytes>) -> PyResult<
 pub fn get_update<'py>(py: Python<'py>, update: &Bound<'_, PyBytes>, state: &Bound<'_, PyBytes>) -> PyResult<Bound<'py, PyBytes>> {
     let update: &[u8] = update.extract()?;
     let state: &[u8] = state.extract()?;
-    let Ok(u) = diff_updates_v1(&update, &state) else {
-        return Err(PyValueError::new_err("Cannot diff updates"));
+    let Ok(u) = py.detach(|| diff_updates_v1(update, state)) else {
+        return Err(DecodeError::new_err("Cannot diff updates"));
     };
     Ok(PyBytes::new(py, &u))
 }
+
+#[pyfunction]
+pub fn merge_updates_v2<'py>(py: Python<'py>, updates: &Bound<'_, PyTuple>) -> PyResult<Bound<'py, PyBytes>> {
+    let updates: Vec<Vec<u8>> = updates.extract()?;
+    let Ok(update) = py.detach(|| catch_decode_v2(|| yrs::merge_updates_v2(&updates))) else {
+        return Err(DecodeError::new_err("Cannot merge updates"));
+    };
+    Ok(PyBytes::new(py, &update))
+}
+
+/// State vectors are always returned in the v1 format, as Yjs does, so that they can be
+/// exchanged with any peer regardless of the update encoding.
+#[pyfunction]
+pub fn get_state_v2<'py>(py: Python<'py>, update: &Bound<'_, PyBytes>) -> PyResult<Bound<'py, PyBytes>> {
+    let update = update.as_bytes();
+    let Ok(u) = py.detach(|| decode_v2(update).map(|u| u.state_vector().encode_v1())) else {
+        return Err(DecodeError::new_err(
+            "Cannot encode state vector from update",
+        ));
+    };
+    Ok(PyBytes::new(py, &u))
+}
+
+#[pyfunction]
+pub fn get_update_v2<'py>(py: Python<'py>, update: &Bound<'_, PyBytes>, state: &Bound<'_, PyBytes>) -> PyResult<Bound<'py, PyBytes>> {
+    let Ok(state_vector) = StateVector::decode_v1(state.as_bytes()) else {
+        return Err(DecodeError::new_err("Cannot decode state"));
+    };
+    let update = update.as_bytes();
+    let Ok(u) = py.detach(|| catch_decode_v2(|| diff_updates_v2(update, &state_vector.encode_v2()))) else {
+        return Err(DecodeError::new_err("Cannot diff updates"));
+    };
+    Ok(PyBytes::new(py, &u))
+}
+
+#[pyfunction]
+pub fn convert_update_v1_to_v2<'py>(py: Python<'py>, update: &Bound<'_, PyBytes>) -> PyResult<Bound<'py, PyBytes>> {
+    let u = Update::decode_v1(update.as_bytes())
+        .map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))?;
+    Ok(PyBytes::new(py, &u.encode_v2()))
+}
+
+#[pyfunction]
+pub fn convert_update_v2_to_v1<'py>(py: Python<'py>, update: &Bound<'_, PyBytes>) -> PyResult<Bound<'py, PyBytes>> {
+    let u = decode_v2(update.as_bytes())
+        .map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))?;
+    Ok(PyBytes::new(py, &u.encode_v1()))
+}
+
+/// Decode an update into its blocks and delete set, like Yjs' `decodeUpdate`.
+#[pyfunction]
+pub fn decode_update<'py>(py: Python<'py>, update: &Bound<'_, PyBytes>) -> PyResult<Bound<'py, PyDict>> {
+    let mut decoder = DecoderV1::from(update.as_bytes());
+    decode_blocks(py, &mut decoder)
+        .map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))
+}
+
+#[pyfunction]
+pub fn decode_update_v2<'py>(py: Python<'py>, update: &Bound<'_, PyBytes>) -> PyResult<Bound<'py, PyDict>> {
+    catch_decode_v2(|| {
+        DecoderV2::new(Cursor::new(update.as_bytes()))
+            .and_then(|mut decoder| decode_blocks(py, &mut decoder))
+    })
+    .map_err(|e| DecodeError::new_err(format!("Cannot decode update: {}", e)))
+}
+
+/// Run a v2 decoding. Some malformed v2 input makes yrs panic (e.g. when reading past the end
+/// of a column) instead of returning an error, so the panic is turned into an error.
+pub(crate) fn catch_decode_v2<T, E: Display>(decode: impl FnOnce() -> Result<T, E>) -> Result<T, String> {
+    match panic::catch_unwind(AssertUnwindSafe(decode)) {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err("malformed v2 encoding".to_string()),
+    }
+}
+
+/// Decode a v2-encoded update.
+pub(crate) fn decode_v2(update: &[u8]) -> Result<Update, String> {
+    catch_decode_v2(|| Update::decode_v2(update))
+}
+
+/// Read the blocks and the delete set of an update, following `Update::decode`, but keeping
+/// every block as it was encoded instead of integrating it.
+fn decode_blocks<'py, D: Decoder>(py: Python<'py>, decoder: &mut D) -> Result<Bound<'py, PyDict>, Error> {
+    let structs = PyList::empty(py);
+    let clients_len: u32 = decoder.read_var()?;
+    for _ in 0..clients_len {
+        let blocks_len: u32 = decoder.read_var()?;
+        let client = decoder.read_client()?.get();
+        let mut clock: u32 = decoder.read_var()?;
+        for _ in 0..blocks_len {
+            let block = PyDict::new(py);
+            let length = match decoder.read_info()? {
+                BLOCK_SKIP_REF_NUMBER => {
+                    block_set(&block, "type", "skip")?;
+                    decoder.read_var()?
+                }
+                BLOCK_GC_REF_NUMBER => {
+                    block_set(&block, "type", "gc")?;
+                    decoder.read_len()?
+                }
+                info => {
+                    block_set(&block, "type", "item")?;
+                    decode_item(py, decoder, info, &block)?
+                }
+            };
+            block_set(&block, "client", client)?;
+            block_set(&block, "clock", clock)?;
+            block_set(&block, "length", length)?;
+            clock = next_clock(clock, length)?;
+            structs.append(block).map_err(py_error)?;
+        }
+    }
+    let delete_set = PyDict::new(py);
+    for (client, ranges) in IdSet::decode(decoder)?.iter() {
+        let ranges: Vec<(u32, u32)> = ranges.iter().map(|range| (range.start, range.end - range.start)).collect();
+        block_set(&delete_set, client.get(), ranges)?;
+    }
+    let result = PyDict::new(py);
+    block_set(&result, "structs", structs)?;
+    block_set(&result, "delete_set", delete_set)?;
+    Ok(result)
+}
+
+/// The IDs and lengths of the items of a v1 update.
+pub fn update_items(update: &[u8]) -> Result<Vec<(ID, u32)>, Error> {
+    let mut decoder = DecoderV1::from(update);
+    let mut items = Vec::new();
+    let clients_len: u32 = decoder.read_var()?;
+    for _ in 0..clients_len {
+        let blocks_len: u32 = decoder.read_var()?;
+        let client = decoder.read_client()?;
+        let mut clock: u32 = decoder.read_var()?;
+        for _ in 0..blocks_len {
+            let length = match decoder.read_info()? {
+                BLOCK_SKIP_REF_NUMBER => decoder.read_var()?,
+                BLOCK_GC_REF_NUMBER => decoder.read_len()?,
+                info => {
+                    let length = skip_item(&mut decoder, info)?;
+                    items.push((ID::new(client, clock), length));
+                    length
+                }
+            };
+            clock = next_clock(clock, length)?;
+        }
+    }
+    Ok(items)
+}
+
+fn skip_item<D: Decoder>(decoder: &mut D, info: u8) -> Result<u32, Error> {
+    let cant_copy_parent_info = info & (HAS_ORIGIN | HAS_RIGHT_ORIGIN) == 0;
+    if info & HAS_ORIGIN != 0 {
+        decoder.read_left_id()?;
+    }
+    if info & HAS_RIGHT_ORIGIN != 0 {
+        decoder.read_right_id()?;
+    }
+    if cant_copy_parent_info {
+        if decoder.read_parent_info()? {
+            decoder.read_string()?;
+        } else {
+            decoder.read_left_id()?;
+        }
+        if info & HAS_PARENT_SUB != 0 {
+            decoder.read_string()?;
+        }
+    }
+    Ok(ItemContent::decode(decoder, info)?.len(OffsetKind::Utf16))
+}
+
+fn decode_item<D: Decoder>(py: Python<'_>, decoder: &mut D, info: u8, item: &Bound<'_, PyDict>) -> Result<u32, Error> {
+    let id = |id: ID| (id.client.get(), id.clock);
+    let cant_copy_parent_info = info & (HAS_ORIGIN | HAS_RIGHT_ORIGIN) == 0;
+    let origin = if info & HAS_ORIGIN != 0 { Some(id(decoder.read_left_id()?)) } else { None };
+    let right_origin = if info & HAS_RIGHT_ORIGIN != 0 { Some(id(decoder.read_right_id()?)) } else { None };
+    block_set(item, "origin", origin)?;
+    block_set(item, "right_origin", right_origin)?;
+    // the parent is only encoded when it cannot be deduced from the origins
+    let parent = if !cant_copy_parent_info {
+        None
+    } else if decoder.read_parent_info()? {
+        Some(PyString::new(py, checked_str(decoder.read_string()?)?).into_any())
+    } else {
+        Some(id(decoder.read_left_id()?).into_pyobject(py).map_err(py_error)?.into_any())
+    };
+    block_set(item, "parent", parent)?;
+    let parent_sub = if cant_copy_parent_info && info & HAS_PARENT_SUB != 0 {
+        Some(checked_str(decoder.read_string()?)?.to_string())
+    } else {
+        None
+    };
+    block_set(item, "parent_sub", parent_sub)?;
+    let content = ItemContent::decode(decoder, info)?;
+    let length = content.len(OffsetKind::Utf16);
+    let (kind, value) = match content {
+        ItemContent::Any(values) => {
+            values.iter().try_for_each(check_any)?;
+            ("any", values.into_py(py))
+        }
+        ItemContent::Binary(buf) => ("binary", PyBytes::new(py, &buf).into_any()),
+        ItemContent::Deleted(_) => ("deleted", py.None().into_bound(py)),
+        ItemContent::Doc(_, doc) => ("doc", PyString::new(py, checked_str(&doc.guid())?).into_any()),
+        ItemContent::JSON(values) => {
+            values.iter().try_for_each(|value| checked_str(value).map(|_| ()))?;
+            ("json", PyList::new(py, values).map_err(py_error)?.into_any())
+        }
+        ItemContent::Embed(value) => {
+            check_any(&value)?;
+            ("embed", value.into_py(py))
+        }
+        ItemContent::Format(key, value) => {
+            check_any(&value)?;
+            let format = (checked_str(&key)?, (*value).into_py(py));
+            ("format", format.into_pyobject(py).map_err(py_error)?.into_any())
+        }
+        ItemContent::String(s) => ("string", PyString::new(py, checked_str(s.as_str())?).into_any()),
+        ItemContent::Type(branch) => ("type", PyString::new(py, checked_str(&type_name(branch.type_ref()))?).into_any()),
+    };
+    block_set(item, "content", kind)?;
+    block_set(item, "value", value)?;
+    Ok(length)
+}
+
+/// A string read from an update: the decoders don't check that it is valid UTF-8, which a Python
+/// string requires.
+fn checked_str(s: &str) -> Result<&str, Error> {
+    std::str::from_utf8(s.as_bytes()).map_err(|_| Error::Custom("Invalid UTF-8 string".to_string()))
+}
+
+/// Check the strings of a value read from an update (see `checked_str`).
+fn check_any(value: &Any) -> Result<(), Error> {
+    match value {
+        Any::String(s) => checked_str(s).map(|_| ()),
+        Any::Array(values) => values.iter().try_for_each(check_any),
+        Any::Map(entries) => entries.iter().try_for_each(|(key, value)| {
+            checked_str(key)?;
+            check_any(value)
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn type_name(type_ref: &TypeRef) -> String {
+    match type_ref {
+        TypeRef::Array => "Array".to_string(),
+        TypeRef::Map => "Map".to_string(),
+        TypeRef::Text => "Text".to_string(),
+        TypeRef::XmlElement(name) => format!("XmlElement({})", name),
+        TypeRef::XmlFragment => "XmlFragment".to_string(),
+        TypeRef::XmlHook => "XmlHook".to_string(),
+        TypeRef::XmlText => "XmlText".to_string(),
+        TypeRef::SubDoc => "Doc".to_string(),
+        TypeRef::Undefined => "Undefined".to_string(),
+    }
+}
+
+fn block_set<'py, K, V>(dict: &Bound<'py, PyDict>, key: K, value: V) -> Result<(), Error>
+where
+    K: IntoPyObject<'py>,
+    V: IntoPyObject<'py>,
+{
+    dict.set_item(key, value).map_err(py_error)
+}
+
+fn py_error(err: PyErr) -> Error {
+    Error::Custom(err.to_string())
+}
+
+/// The clock following a block, which crafted updates could make overflow.
+fn next_clock(clock: u32, length: u32) -> Result<u32, Error> {
+    clock.checked_add(length).ok_or(Error::UnexpectedValue)
+}
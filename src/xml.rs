@@ -1,8 +1,8 @@
 use pyo3::prelude::*;
 use pyo3::IntoPyObjectExt;
-use pyo3::types::{PyBool, PyDict, PyIterator, PyList, PyString, PyTuple};
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBool, PyDict, PyIterator, PyList};
 use pyo3::{pyclass, pymethods, Bound, PyAny, PyResult, Python};
-use yrs::types::text::YChange;
 use yrs::types::xml::{XmlEvent as _XmlEvent, XmlTextEvent as _XmlTextEvent};
 use yrs::{
     ArrayPrelim,
@@ -23,12 +23,15 @@ use yrs::{
     XmlTextRef
 };
 
+use crate::transaction::TransactionAccess;
 use crate::subscription::Subscription;
 use crate::type_conversions::{events_into_py, py_to_any, py_to_attrs, EntryChangeWrapper, ToPython};
 use crate::transaction::Transaction;
+use crate::snapshot::Snapshot;
 use crate::array::Array;
 use crate::map::Map;
-use crate::text::Text;
+use crate::text::{check_char_boundaries, text_attributed_diff, text_diff, text_diff_snapshots, Text};
+use crate::id_map::IdMap;
 
 /// Implements methods common to `XmlFragment`, `XmlElement`, and `XmlText`.
 macro_rules! impl_xml_methods {
@@ -55,82 +58,83 @@ macro_rules! impl_xml_methods {
                 }
             }
 
-            fn get_string(&self, txn: &mut Transaction) -> String {
-                let mut t0 = txn.transaction();
-                let t1 = t0.as_mut().unwrap();
-                let t = t1.as_ref();
-                self.$inner.get_string(t)
+            fn get_string(&self, txn: &mut Transaction) -> PyResult<String> {
+                let t0 = txn.transaction();
+                let t = &t0.txn_ref()?;
+                Ok(self.$inner.get_string(t))
             }
 
-            fn len(&self, txn: &mut Transaction)  -> u32 {
-                let mut t0 = txn.transaction();
-                let t1 = t0.as_mut().unwrap();
-                let t = t1.as_ref();
-                self.$inner.len(t)
+            fn len(&self, txn: &mut Transaction)  -> PyResult<u32> {
+                let t0 = txn.transaction();
+                let t = &t0.txn_ref()?;
+                Ok(self.$inner.len(t))
             }
 
             $(
-                fn get<'py>(&self, py: Python<'py>, txn: &mut Transaction, index: u32) -> Bound<'py, PyAny> {
-                    let mut t0 = txn.transaction();
-                    let t1 = t0.as_mut().unwrap();
-                    let t = t1.as_ref();
-                    self.$finner.get(t, index).unwrap().into_py(py)
+                fn get<'py>(&self, py: Python<'py>, txn: &mut Transaction, index: u32) -> PyResult<Bound<'py, PyAny>> {
+                    let t0 = txn.transaction();
+                    let t = &t0.txn_ref()?;
+                    match self.$finner.get(t, index) {
+                        Some(node) => Ok(node.into_py(py)),
+                        None => Err(PyValueError::new_err("Index error")),
+                    }
                 }
 
-                fn remove_range(&self, txn: &mut Transaction, index: u32, len: u32) {
+                fn remove_range(&self, txn: &mut Transaction, index: u32, len: u32) -> PyResult<()> {
                     let mut _t = txn.transaction();
-                    let mut t = _t.as_mut().unwrap().as_mut();
+                    let mut t = _t.txn_mut()?;
                     self.$finner.remove_range(&mut t, index, len);
+                    Ok(())
                 }
 
-                fn insert_str(&self, txn: &mut Transaction, index: u32, text: &str) -> XmlText {
+                fn insert_str(&self, txn: &mut Transaction, index: u32, text: &str) -> PyResult<XmlText> {
                     let mut _t = txn.transaction();
-                    let mut t = _t.as_mut().unwrap().as_mut();
-                    self.$finner.insert(&mut t, index, XmlTextPrelim::new(text)).into()
+                    let mut t = _t.txn_mut()?;
+                    Ok(self.$finner.insert(&mut t, index, XmlTextPrelim::new(text)).into())
                 }
 
-                fn insert_element_prelim(&self, txn: &mut Transaction, index: u32, tag: &str) -> XmlElement {
+                fn insert_element_prelim(&self, txn: &mut Transaction, index: u32, tag: &str) -> PyResult<XmlElement> {
                     let mut _t = txn.transaction();
-                    let mut t = _t.as_mut().unwrap().as_mut();
-                    self.$finner.insert(&mut t, index, XmlElementPrelim::empty(tag)).into()
+                    let mut t = _t.txn_mut()?;
+                    Ok(self.$finner.insert(&mut t, index, XmlElementPrelim::empty(tag)).into())
                 }
             )?
 
             $(
-                fn attributes<'py>(&self, py: Python<'py>, txn: &mut Transaction) -> Vec<(String, Bound<'py, PyAny>)> {
-                    let mut t0 = txn.transaction();
-                    let t1 = t0.as_mut().unwrap();
-                    let t = t1.as_ref();
-                    self.$xinner
+                fn attributes<'py>(&self, py: Python<'py>, txn: &mut Transaction) -> PyResult<Vec<(String, Bound<'py, PyAny>)>> {
+                    let t0 = txn.transaction();
+                    let t = &t0.txn_ref()?;
+                    Ok(self.$xinner
                         .attributes(t)
                         .map(|(k, v)| (String::from(k), v.into_py(py)))
-                        .collect()
+                        .collect())
                 }
 
-                fn attribute<'py>(&self, py: Python<'py>, txn: &mut Transaction, name: &str) -> Option<Bound<'py, PyAny>> {
-                    let mut t0 = txn.transaction();
-                    let t1 = t0.as_mut().unwrap();
-                    let t = t1.as_ref();
-                    Some(self.$xinner.get_attribute(t, name)?.into_py(py))
+                fn attribute<'py>(&self, py: Python<'py>, txn: &mut Transaction, name: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+                    let t0 = txn.transaction();
+                    let t = &t0.txn_ref()?;
+                    Ok(self.$xinner.get_attribute(t, name).map(|value| value.into_py(py)))
                 }
 
-                fn insert_attribute(&self, txn: &mut Transaction, name: &str, value: Bound<'_, PyAny>) {
+                fn insert_attribute(&self, txn: &mut Transaction, name: &str, value: Bound<'_, PyAny>) -> PyResult<()> {
+                    let value = py_to_any(&value)?;
                     let mut _t = txn.transaction();
-                    let mut t = _t.as_mut().unwrap().as_mut();
-                    self.$xinner.insert_attribute(&mut t, name, py_to_any(&value));
+                    let mut t = _t.txn_mut()?;
+                    self.$xinner.insert_attribute(&mut t, name, value);
+                    Ok(())
                 }
 
-                fn remove_attribute(&self, txn: &mut Transaction, name: &str) {
+                fn remove_attribute(&self, txn: &mut Transaction, name: &str) -> PyResult<()> {
                     let mut _t = txn.transaction();
-                    let mut t = _t.as_mut().unwrap().as_mut();
+                    let mut t = _t.txn_mut()?;
                     self.$xinner.remove_attribute(&mut t, &name);
+                    Ok(())
                 }
 
-                fn siblings<'py>(&self, py: Python<'py>, txn: &mut Transaction) -> Vec<Bound<'py, PyAny>> {
-                    let mut t0 = txn.transaction();
-                    let t1 = t0.as_mut().unwrap();
-                    let t = t1.as_ref();
-                    self.$xinner.siblings(t).map(|node| node.into_py(py)).collect()
+                fn siblings<'py>(&self, py: Python<'py>, txn: &mut Transaction) -> PyResult<Vec<Bound<'py, PyAny>>> {
+                    let t0 = txn.transaction();
+                    let t = &t0.txn_ref()?;
+                    Ok(self.$xinner.siblings(t).map(|node| node.into_py(py)).collect())
                 }
             )?
 
@@ -238,7 +242,8 @@ impl_xml_methods!(XmlText[text, xml: text] {
     #[pyo3(signature = (txn, index, text, attrs=None))]
     fn insert(&self, txn: &mut Transaction, index: u32, text: &str, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index])?;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
             self.text.insert_with_attributes(&mut t, index, text, attrs);
@@ -250,9 +255,10 @@ impl_xml_methods!(XmlText[text, xml: text] {
 
     #[pyo3(signature = (txn, index, embed, attrs=None))]
     fn insert_embed<'py>(&self, txn: &mut Transaction, index: u32, embed: Bound<'py, PyAny>, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<()> {
-        let embed = py_to_any(&embed);
+        let embed = py_to_any(&embed)?;
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index])?;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
             self.text.insert_embed_with_attributes(&mut t, index, embed, attrs);
@@ -265,7 +271,7 @@ impl_xml_methods!(XmlText[text, xml: text] {
     #[pyo3(signature = (txn, index, attrs=None))]
     fn insert_array_prelim<'py>(&self, txn: &mut Transaction, index: u32, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<Array> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
@@ -280,7 +286,7 @@ impl_xml_methods!(XmlText[text, xml: text] {
     #[pyo3(signature = (txn, index, attrs=None))]
     fn insert_map_prelim<'py>(&self, txn: &mut Transaction, index: u32, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<Map> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
@@ -295,7 +301,7 @@ impl_xml_methods!(XmlText[text, xml: text] {
     #[pyo3(signature = (txn, index, attrs=None))]
     fn insert_text_prelim<'py>(&self, txn: &mut Transaction, index: u32, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<Text> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
@@ -310,7 +316,7 @@ impl_xml_methods!(XmlText[text, xml: text] {
     #[pyo3(signature = (txn, index, attrs=None))]
     fn insert_xmltext_prelim<'py>(&self, txn: &mut Transaction, index: u32, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<XmlText> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
@@ -325,7 +331,7 @@ impl_xml_methods!(XmlText[text, xml: text] {
     #[pyo3(signature = (txn, index, tag, attrs=None))]
     fn insert_xmlelement_prelim<'py>(&self, txn: &mut Transaction, index: u32, tag: &str, attrs: Option<Bound<'_, PyIterator>>) -> PyResult<XmlElement> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
         let integrated;
         if let Some(attrs) = attrs {
             let attrs = py_to_attrs(attrs)?;
@@ -337,49 +343,36 @@ impl_xml_methods!(XmlText[text, xml: text] {
         Ok(shared)
     }
 
-    fn remove_range(&self, txn: &mut Transaction, index: u32, len: u32) {
+    fn remove_range(&self, txn: &mut Transaction, index: u32, len: u32) -> PyResult<()> {
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index, index + len])?;
         self.text.remove_range(&mut t, index, len);
+        Ok(())
     }
 
     fn format(&self, txn: &mut Transaction, index: u32, len: u32, attrs: Bound<'_, PyIterator>) -> PyResult<()> {
         let attrs = py_to_attrs(attrs)?;
         let mut _t = txn.transaction();
-        let mut t = _t.as_mut().unwrap().as_mut();
+        let mut t = _t.txn_mut()?;
+        check_char_boundaries(&self.text, t, &[index, index + len])?;
         self.text.format(&mut t, index, len, attrs);
         Ok(())
     }
 
-    fn diff<'py>(&self, py: Python<'py>, txn: &mut Transaction) -> Bound<'py, PyList> {
-        let mut t0 = txn.transaction();
-        let t1 = t0.as_mut().unwrap();
-        let t = t1.as_ref();
-
-        let iter = self.text.diff(t, YChange::identity)
-            .into_iter()
-            .map(|diff| {
-                let attrs = diff.attributes.map(|attrs| {
-                    let pyattrs = PyDict::new(py);
-                    for (name, value) in attrs.into_iter() {
-                        pyattrs.set_item(
-                            PyString::intern(py, &*name),
-                            value.into_py(py),
-                        ).unwrap();
-                    }
-                    pyattrs.into_any()
-                }).unwrap_or_else(|| py.None().into_bound(py));
+    #[pyo3(signature = (txn, snapshot=None))]
+    fn diff<'py>(&self, py: Python<'py>, txn: &mut Transaction, snapshot: Option<PyRef<'_, Snapshot>>) -> PyResult<Bound<'py, PyList>> {
+        text_diff(py, &self.text, txn, snapshot)
+    }
 
-                PyTuple::new(py, [
-                    diff.insert.into_py(py),
-                    attrs,
-                ]).unwrap()
-            });
+    #[pyo3(signature = (txn, prev_snapshot, snapshot=None))]
+    fn diff_snapshots<'py>(&self, py: Python<'py>, txn: &mut Transaction, prev_snapshot: PyRef<'_, Snapshot>, snapshot: Option<PyRef<'_, Snapshot>>) -> PyResult<Bound<'py, PyList>> {
+        text_diff_snapshots(py, &self.text, txn, prev_snapshot, snapshot)
+    }
 
-        PyList::new(
-            py,
-            iter
-        ).unwrap()
+    #[pyo3(signature = (txn, insertions, deletions=None, snapshot=None))]
+    fn diff_attributed<'py>(&self, py: Python<'py>, txn: &mut Transaction, insertions: PyRef<'_, IdMap>, deletions: Option<PyRef<'_, IdMap>>, snapshot: Option<PyRef<'_, Snapshot>>) -> PyResult<Bound<'py, PyList>> {
+        text_attributed_diff(py, &self.text, txn, &insertions, deletions.as_deref(), snapshot)
     }
 
     fn observe(&self, f: Py<PyAny>) -> Subscription {
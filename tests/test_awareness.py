@@ -239,29 +239,21 @@ def test_awareness_increment_clock():
         create_awareness_update(awareness.client_id, "null"),
         "custom_origin",
     )
-    assert len(changes) == 2
-    assert changes[0] == (
-        "change",
-        (
-            {
-                "added": [],
-                "updated": [],
-                "removed": [awareness.client_id],
-            },
-            "custom_origin",
-        ),
-    )
-    assert changes[1] == (
-        "update",
+    # a remote client cannot remove the local state, its clock is increased instead
+    assert awareness.get_local_state() == {}
+    assert changes == [
         (
-            {
-                "added": [],
-                "updated": [],
-                "removed": [awareness.client_id],
-            },
-            "custom_origin",
+            "update",
+            (
+                {
+                    "added": [],
+                    "updated": [awareness.client_id],
+                    "removed": [],
+                },
+                "custom_origin",
+            ),
         ),
-    )
+    ]
     assert awareness.meta.get(awareness.client_id, {}).get("clock") == 2
 
 
@@ -356,17 +348,17 @@ def test_awareness_encode():
 def test_awareness_encode_wrong_id():
     ydoc = Doc()
     awareness = Awareness(ydoc)
-    with pytest.raises(TypeError):
+    with pytest.raises(ValueError):
         awareness.encode_awareness_update([10])
 
 
 async def test_awareness_periodic_updates():
     ydoc = Doc()
     outdated_timeout = 200
-    awareness = Awareness(ydoc, outdated_timeout=outdated_timeout)
+    now = 0
+    awareness = Awareness(ydoc, outdated_timeout=outdated_timeout, timestamp=lambda: now)
     remote_client_id = 0
-    awareness._meta[remote_client_id] = {"clock": 0, "lastUpdated": 0}
-    awareness._states[remote_client_id] = {}
+    awareness.apply_awareness_update(create_awareness_update(remote_client_id, {}), "remote")
     changes = []
 
     def callback(topic, value):
@@ -378,7 +370,8 @@ async def test_awareness_periodic_updates():
         with pytest.raises(RuntimeError) as excinfo:
             await tg.start(awareness.start)
         assert str(excinfo.value) == "Awareness already started"
-        await sleep((outdated_timeout - outdated_timeout / 10) / 1000)
+        now = outdated_timeout
+        await sleep(outdated_timeout / 1000)
         awareness.remove_awareness_states([awareness.client_id], "local")
         await sleep(outdated_timeout / 1000)
         await awareness.stop()
@@ -386,62 +379,63 @@ async def test_awareness_periodic_updates():
             await awareness.stop()
         assert str(excinfo.value) == "Awareness not started"
 
-    assert len(changes) == 5
-    assert changes[0] == (
-        "change",
+    assert changes == [
         (
-            {
-                "added": [],
-                "removed": [remote_client_id],
-                "updated": [],
-            },
-            "timeout",
+            "update",
+            (
+                {
+                    "added": [],
+                    "removed": [],
+                    "updated": [awareness.client_id],
+                },
+                "timeout",
+            ),
         ),
-    )
-    assert changes[1] == (
-        "update",
         (
-            {
-                "added": [],
-                "removed": [remote_client_id],
-                "updated": [],
-            },
-            "timeout",
+            "change",
+            (
+                {
+                    "added": [],
+                    "removed": [remote_client_id],
+                    "updated": [],
+                },
+                "timeout",
+            ),
         ),
-    )
-    assert changes[2] == (
-        "update",
         (
-            {
-                "added": [],
-                "removed": [],
-                "updated": [awareness.client_id],
-            },
-            "local",
+            "update",
+            (
+                {
+                    "added": [],
+                    "removed": [remote_client_id],
+                    "updated": [],
+                },
+                "timeout",
+            ),
         ),
-    )
-    assert changes[3] == (
-        "change",
         (
-            {
-                "added": [],
-                "removed": [awareness.client_id],
-                "updated": [],
-            },
-            "local",
+            "change",
+            (
+                {
+                    "added": [],
+                    "removed": [awareness.client_id],
+                    "updated": [],
+                },
+                "local",
+            ),
         ),
-    )
-    assert changes[4] == (
-        "update",
         (
-            {
-                "added": [],
-                "removed": [awareness.client_id],
-                "updated": [],
-            },
-            "local",
+            "update",
+            (
+                {
+                    "added": [],
+                    "removed": [awareness.client_id],
+                    "updated": [],
+                },
+                "local",
+            ),
         ),
-    )
+    ]
 
 
 def test_awareness_disconnection():
@@ -452,3 +446,134 @@ def test_awareness_disconnection():
     # Should return False if it is not a disconnection message
     update = write_message(create_awareness_update(REMOTE_CLIENT_ID, "{}"))
     assert not is_awareness_disconnect_message(update)
+
+
+def test_awareness_local_state_clock():
+    ydoc = Doc()
+    awareness = Awareness(ydoc)
+    changes = []
+    awareness.observe(lambda topic, event: changes.append((topic, *event)))
+
+    assert awareness.meta[awareness.client_id]["clock"] == 0
+
+    awareness.set_local_state_field("user", {"name": "Test user", "count": 1})
+    assert awareness.get_local_state() == {"user": {"name": "Test user", "count": 1}}
+    awareness.set_local_state(awareness.get_local_state())
+    awareness.set_local_state(None)
+    assert awareness.get_local_state() is None
+    assert awareness.states == {}
+    assert awareness.meta[awareness.client_id]["clock"] == 3
+
+    client_id = awareness.client_id
+    assert changes == [
+        ("change", {"added": [], "updated": [client_id], "removed": []}, "local"),
+        ("update", {"added": [], "updated": [client_id], "removed": []}, "local"),
+        ("update", {"added": [], "updated": [client_id], "removed": []}, "local"),
+        ("change", {"added": [], "updated": [], "removed": [client_id]}, "local"),
+        ("update", {"added": [], "updated": [], "removed": [client_id]}, "local"),
+    ]
+
+
+def test_awareness_encode_all():
+    ydoc = Doc()
+    awareness = Awareness(ydoc)
+    awareness.set_local_state({"user": TEST_USER, "cursor": 3})
+    awareness_update = awareness.encode_awareness_update([awareness.client_id])
+    assert awareness_update == create_awareness_update(
+        awareness.client_id, {"user": TEST_USER, "cursor": 3}
+    )
+    assert awareness.encode_awareness_update() == awareness_update
+
+
+def test_awareness_apply_update():
+    ydoc = Doc()
+    awareness = Awareness(ydoc)
+    changes = []
+    sub = awareness.observe(
+        lambda topic, event: changes.append(event) if topic == "change" else None
+    )
+
+    awareness.apply_awareness_update(
+        create_awareness_update(REMOTE_CLIENT_ID, REMOTE_USER), "remote"
+    )
+    remote_user = deepcopy(REMOTE_USER)
+    remote_user["user"]["name"] = "New user name"
+    awareness.apply_awareness_update(
+        create_awareness_update(REMOTE_CLIENT_ID, remote_user, 2), "remote"
+    )
+    # outdated updates are ignored
+    awareness.apply_awareness_update(
+        create_awareness_update(REMOTE_CLIENT_ID, REMOTE_USER, 2), "remote"
+    )
+    assert awareness.states[REMOTE_CLIENT_ID] == remote_user
+
+    awareness.apply_awareness_update(
+        create_awareness_update(REMOTE_CLIENT_ID, "null", 3), "remote"
+    )
+    assert awareness.states == {ydoc.client_id: {}}
+    assert awareness.meta[REMOTE_CLIENT_ID]["clock"] == 3
+
+    assert changes == [
+        ({"added": [REMOTE_CLIENT_ID], "updated": [], "removed": []}, "remote"),
+        ({"added": [], "updated": [REMOTE_CLIENT_ID], "removed": []}, "remote"),
+        ({"added": [], "updated": [], "removed": [REMOTE_CLIENT_ID]}, "remote"),
+    ]
+
+    changes.clear()
+    awareness.unobserve(sub)
+    awareness.apply_awareness_update(
+        create_awareness_update(REMOTE_CLIENT_ID, REMOTE_USER, 4), "remote"
+    )
+    assert changes == []
+
+    with pytest.raises(ValueError):
+        awareness.apply_awareness_update(b"\x01", "remote")
+
+
+def test_awareness_remove_outdated_states():
+    ydoc = Doc()
+    now = 0
+    awareness = Awareness(ydoc, outdated_timeout=1000, timestamp=lambda: now)
+    awareness.apply_awareness_update(
+        create_awareness_update(REMOTE_CLIENT_ID, REMOTE_USER), "remote"
+    )
+    changes = []
+    awareness.observe(lambda topic, event: changes.append((topic, *event)))
+
+    now = 600
+    assert awareness.remove_outdated_states() == []
+    assert changes == [
+        ("update", {"added": [], "updated": [ydoc.client_id], "removed": []}, "timeout")
+    ]
+    assert awareness.meta[ydoc.client_id]["lastUpdated"] == 600
+
+    changes.clear()
+    now = 1000
+    assert awareness.remove_outdated_states() == [REMOTE_CLIENT_ID]
+    assert awareness.states == {ydoc.client_id: {}}
+    assert changes == [
+        ("change", {"added": [], "updated": [], "removed": [REMOTE_CLIENT_ID]}, "timeout"),
+        ("update", {"added": [], "updated": [], "removed": [REMOTE_CLIENT_ID]}, "timeout"),
+    ]
+
+
+def test_awareness_remove_local_state():
+    ydoc = Doc()
+    awareness = Awareness(ydoc)
+    awareness.apply_awareness_update(
+        create_awareness_update(REMOTE_CLIENT_ID, REMOTE_USER), "remote"
+    )
+    changes = []
+    awareness.observe(lambda topic, event: changes.append((topic, *event)))
+
+    awareness.remove_awareness_states([ydoc.client_id, REMOTE_CLIENT_ID], "local")
+    assert awareness.states == {}
+    # the local clock is increased once
+    assert awareness.meta[ydoc.client_id]["clock"] == 1
+    assert awareness.meta[REMOTE_CLIENT_ID]["clock"] == 1
+    assert changes == [
+        ("change", {"added": [], "updated": [], "removed": [REMOTE_CLIENT_ID]}, "local"),
+        ("update", {"added": [], "updated": [], "removed": [REMOTE_CLIENT_ID]}, "local"),
+        ("change", {"added": [], "updated": [], "removed": [ydoc.client_id]}, "local"),
+        ("update", {"added": [], "updated": [], "removed": [ydoc.client_id]}, "local"),
+    ]
@@ -1,9 +1,22 @@
+import sys
 from functools import partial
 
 import pytest
 from anyio import TASK_STATUS_IGNORED, Event, create_task_group, sleep
 from anyio.abc import TaskStatus
-from pycrdt import Array, Doc, Map, Text
+from pycrdt import (
+    Array,
+    Doc,
+    Map,
+    Snapshot,
+    Text,
+    TransactionError,
+    XmlElement,
+    XmlFragment,
+)
+
+if sys.version_info < (3, 11):
+    from exceptiongroup import ExceptionGroup  # pragma: no cover
 
 pytestmark = pytest.mark.anyio
 
@@ -12,6 +25,10 @@ def callback(events, event):
     events.append(event)
 
 
+def callback_by_name(events, name, event):
+    events[name] = event
+
+
 async def async_callback(events, event):
     events.append(event)
     await sleep(0)
@@ -127,6 +144,73 @@ async def test_subdoc():
     assert aevent.loaded == []
 
 
+def test_subdoc_lifecycle():
+    doc = Doc()
+    doc["docs"] = docs = Map()
+    events = []
+    sub = doc.observe_subdocs(partial(callback, events))  # noqa: F841
+    subdoc = Doc(guid="subdoc")
+    docs["subdoc"] = subdoc
+    assert subdoc.parent_doc is doc
+    assert subdoc.should_load
+    assert not subdoc.auto_load
+    assert doc.subdoc_guids() == {"subdoc"}
+    assert [d.guid for d in doc.subdocs()] == ["subdoc"]
+    assert [d.guid for d in events[0].added_docs] == ["subdoc"]
+    assert events[0].added_docs[0].parent_doc is doc
+
+    remote_doc = Doc()
+    remote_events = []
+    sub = remote_doc.observe_subdocs(partial(callback, remote_events))  # noqa: F841
+    remote_doc.apply_update(doc.get_update())
+    remote_subdoc = remote_doc.get("docs", type=Map)["subdoc"]
+    assert not remote_subdoc.should_load
+    assert remote_subdoc.parent_doc is remote_doc
+    assert remote_events[0].added == ["subdoc"]
+    assert remote_events[0].loaded == []
+    remote_subdoc.load()
+    assert remote_subdoc.should_load
+    assert remote_events[1].loaded == ["subdoc"]
+    assert [d.guid for d in remote_events[1].loaded_docs] == ["subdoc"]
+
+    transactions = []
+    subdoc.observe_after_transaction(transactions.append)
+    subdoc.destroy()
+    assert transactions == []
+    assert events[-1].removed == ["subdoc"]
+    assert events[-1].added == ["subdoc"]
+    assert not events[-1].added_docs[0].should_load
+    assert doc.subdoc_guids() == {"subdoc"}
+
+    with doc.transaction():
+        docs["other"] = other = Doc(guid="other")
+        other.load()
+        with other.transaction():
+            with pytest.raises(TransactionError):
+                other.destroy()
+    assert doc.subdoc_guids() == {"subdoc", "other"}
+
+
+def test_subdoc_options():
+    doc = Doc(should_load=False, auto_load=True)
+    assert not doc.should_load
+    assert doc.auto_load
+    assert doc.parent_doc is None
+    with pytest.raises(ValueError):
+        doc.load()
+    assert not doc.should_load
+
+    parent = Doc()
+    parent["array"] = array = Array([doc])
+    remote_doc = Doc()
+    remote_doc.apply_update(parent.get_update())
+    remote_subdoc = remote_doc.get("array", type=Array)[0]
+    assert remote_subdoc.auto_load
+    assert remote_subdoc.should_load
+    assert [d.guid for d in remote_doc.subdocs()] == [doc.guid]
+    assert array[0].parent_doc is parent
+
+
 def test_doc_in_event():
     doc = Doc()
     doc["array"] = array = Array()
@@ -157,6 +241,84 @@ def test_transaction_event():
     assert str(remote_text1) == ""
 
 
+def test_transaction_event_changed_types():
+    doc = Doc()
+    doc["map"] = map0 = Map()
+    doc["array"] = array = Array()
+    events = []
+    sub = doc.observe(partial(callback, events), track_changes=True)
+    with doc.transaction():
+        map0["key"] = "value"
+        map0["text"] = text = Text("Hello")
+        array.extend([1, 2, 3])
+    event = events[-1]
+    assert event.changed_types.keys() == {("map",), ("array",)}
+    assert sorted(event.changed_types[("map",)]) == ["key", "text"]
+    assert event.changed_types[("array",)] == [range(0, 3)]
+    assert event.changed_parent_types == {("map",), ("array",)}
+
+    with doc.transaction():
+        text += ", World!"
+        del array[1]
+    event = events[-1]
+    assert event.changed_types == {("map", "text"): [range(5, 13)], ("array",): [range(1, 1)]}
+    assert event.changed_parent_types == {("map", "text"), ("map",), ("array",)}
+
+    # including the root types of a remote document that were not accessed
+    remote_events = []
+    remote_doc = Doc()
+    remote_sub = remote_doc.observe(partial(callback, remote_events), track_changes=True)  # noqa: F841
+    remote_doc.apply_update(doc.get_update())
+    assert remote_events[-1].changed_types.keys() == {("map",), ("array",)}
+    assert sorted(remote_events[-1].changed_types[("map",)]) == ["key", "text"]
+    assert remote_events[-1].changed_types[("array",)] == [range(0, 2)]
+
+    doc.unobserve(sub)
+    sub = doc.observe(partial(callback, events))  # noqa: F841
+    map0["key"] = "other"
+    assert events[-1].changed_types is None
+    assert events[-1].changed_parent_types is None
+
+
+def test_observe_update():
+    doc = Doc()
+    doc["text"] = text = Text()
+    updates = []
+    updates_v2 = []
+    origins = []
+    doc.observe_update(lambda update, origin: updates.append((update, origin)))
+    doc.observe_update(lambda update, origin: updates_v2.append((update, origin)), v2=True)
+    doc.observe_after_transaction(origins.append)
+
+    with doc.transaction(origin="foo"):
+        text += "Hello"
+    text += ", World!"
+    with doc.transaction(origin=b"bar"):
+        pass
+
+    assert [origin for _, origin in updates] == ["foo", None]
+    assert [origin for _, origin in updates_v2] == ["foo", None]
+    assert origins == ["foo", None, b"bar"]
+    remote_doc = Doc()
+    for update, _ in updates:
+        remote_doc.apply_update(update)
+    assert str(remote_doc.get("text", type=Text)) == "Hello, World!"
+    remote_doc = Doc()
+    for update, _ in updates_v2:
+        remote_doc.apply_update_v2(update)
+    assert str(remote_doc.get("text", type=Text)) == "Hello, World!"
+
+    def callback(update, origin):
+        raise RuntimeError("foo")
+
+    sub = doc.observe_update(callback)
+    with pytest.raises(ExceptionGroup):
+        text += "!"
+    doc.unobserve(sub)
+    text += "!"
+    assert str(text) == "Hello, World!!!"
+
+
 def test_client_id():
     doc0 = Doc()
     doc1 = Doc()
@@ -185,6 +347,14 @@ def test_invalid_guid():
         Doc(guid=1)  # type: ignore[arg-type]
 
 
+def test_offset_kind():
+    assert Doc().offset_kind == "bytes"
+    assert Doc(offset_kind="bytes").offset_kind == "bytes"
+    assert Doc(offset_kind="utf16").offset_kind == "utf16"
+    with pytest.raises(ValueError, match="offset_kind must be"):
+        Doc(offset_kind="code_points")  # type: ignore[arg-type]
+
+
 def test_roots():
     remote_doc = Doc(
         {
@@ -248,6 +418,46 @@ def test_apply_update_exception():
     assert "Cannot decode update" in str(excinfo.value)
 
 
+def test_pending_update():
+    remote_doc = Doc(client_id=1)
+    remote_doc["text"] = text = Text("hello")
+    update0 = remote_doc.get_update()
+    state0 = remote_doc.get_state()
+    text += " world"
+    update1 = remote_doc.get_update(state0)
+    state1 = remote_doc.get_state()
+    del text[0:6]
+    update2 = remote_doc.get_update(state1)
+
+    doc = Doc()
+    assert not doc.has_pending_structs
+    assert not doc.has_pending_delete_set
+    assert doc.get_missing_state() is None
+    assert doc.get_pending_update() is None
+
+    # " world" is inserted after "hello", which is also deleted
+    doc.apply_update(update1)
+    doc.apply_update(update2)
+    assert doc.has_pending_structs
+    assert doc.has_pending_delete_set
+    # missing updates from client 1, clock 0
+    assert doc.get_missing_state() == b"\x01\x01\x00"
+    pending_update = doc.get_pending_update()
+    assert pending_update is not None
+
+    other_doc = Doc()
+    other_doc.apply_update(pending_update)
+    other_doc.apply_update(update0)
+    assert str(other_doc.get("text", type=Text)) == "world"
+
+    doc.apply_update(update0)
+    assert not doc.has_pending_structs
+    assert not doc.has_pending_delete_set
+    assert doc.get_missing_state() is None
+    assert doc.get_pending_update() is None
+    assert str(doc.get("text", type=Text)) == "world"
+
+
 def test_invalid_client_id():
     with pytest.raises(ValueError) as excinfo:
         Doc(client_id="not_an_int")
@@ -384,3 +594,85 @@ async def test_async_observer_exceptions():
     assert exc_info.group_contains(RuntimeError, match="error1")
     assert exc_info.group_contains(ValueError, match="error2")
     assert set(values) == set(["val2", "val1", "val0"])
+
+
+def test_get_changes():
+    doc = Doc(skip_gc=True)
+    doc["text"] = text = Text("Hello")
+    doc["array"] = array = Array([1, Map({"a": 1})])
+    doc["fragment"] = fragment = XmlFragment([XmlElement("p", {"x": "1"}, ["hi"])])
+    del text[0]
+    state = doc.get_state()
+    snapshot = Snapshot.from_doc(doc)
+    assert doc.get_changes(state, snapshot.delete_set) == {}
+
+    # the changes of a remote document observed live
+    remote_doc = Doc()
+    remote_doc.apply_update(doc.get_update())
+    remote_events = {}
+    remote_text = remote_doc.get("text", type=Text)
+    remote_text.observe(lambda event: remote_events.setdefault("text", [event]))
+    remote_roots = {
+        "array": remote_doc.get("array", type=Array),
+        "fragment": remote_doc.get("fragment", type=XmlFragment),
+        "map": remote_doc.get("map", type=Map),
+    }
+    for name, root_type in remote_roots.items():
+        root_type.observe_deep(partial(callback_by_name, remote_events, name))
+
+    text += ", World!"
+    array[1]["a"] = 2
+    array.append(3)
+    paragraph = fragment.children[0]
+    paragraph.attributes["x"] = "2"
+    fragment.children.append("tail")
+    doc["map"] = Map({"k": 1})
+    remote_doc.apply_update(doc.get_update(state))
+
+    changes = doc.get_changes(state, snapshot.delete_set)
+    assert list(changes) == ["array", "fragment", "map", "text"]
+    assert changes.keys() == remote_events.keys()
+    for name, events in changes.items():
+        for event, remote_event in zip(events, remote_events[name], strict=True):
+            assert type(event) is type(remote_event)
+            assert event.path == remote_event.path
+            assert str(event.target) == str(remote_event.target)
+            assert event.transaction is None
+    assert changes["text"][0].delta == [{"retain": 4}, {"insert": ", World!"}]
+    assert changes["array"][0].delta == [{"retain": 2}, {"insert": [3.0]}]
+    assert changes["array"][1].keys == {"a": {"action": "update", "oldValue": 1.0, "newValue": 2.0}}
+    assert changes["fragment"][1].keys == {
+        "x": {"action": "update", "oldValue": "1", "newValue": "2"}
+    }
+    assert changes["map"][0].keys == {"k": {"action": "add", "newValue": 1.0}}
+
+    # the root types of a remote document that were not accessed are inferred from their content
+    remote_doc = Doc(skip_gc=True)
+    remote_doc.apply_update(doc.get_update())
+    remote_changes = remote_doc.get_changes(state, snapshot.delete_set)
+    assert list(remote_changes) == list(changes)
+    assert remote_changes["text"][0].delta == changes["text"][0].delta
+
+    # without the deleted content, all of it is reported as deleted since the state
+    assert doc.get_changes(state)["text"][0].delta == [
+        {"delete": 1},
+        {"retain": 4},
+        {"insert": ", World!"},
+    ]
+
+    # insertions and deletions in the same transaction
+    doc = Doc(skip_gc=True)
+    doc["text"] = text = Text("ab")
+    state = doc.get_state()
+    remote_doc = Doc()
+    remote_doc.apply_update(doc.get_update())
+    remote_events.clear()
+    remote_text = remote_doc.get("text", type=Text)
+    sub = remote_text.observe(lambda event: remote_events.setdefault("text", [event]))  # noqa: F841
+    with doc.transaction():
+        text += "c"
+        del text[0]
+    remote_doc.apply_update(doc.get_update(state))
+    changes = doc.get_changes(state)
+    assert changes["text"][0].delta == remote_events["text"][0].delta
+    assert changes["text"][0].delta == [{"delete": 1}, {"retain": 1}, {"insert": "c"}]
@@ -0,0 +1,125 @@
+import pytest
+from pycrdt import (
+    Array,
+    ConversionError,
+    CrdtError,
+    DecodeError,
+    Doc,
+    IntegrationError,
+    Map,
+    OffsetError,
+    StickyIndex,
+    Text,
+    TransactionError,
+    XmlFragment,
+    XmlText,
+    get_state,
+    merge_updates,
+)
+
+
+def test_hierarchy():
+    assert issubclass(DecodeError, CrdtError)
+    assert issubclass(DecodeError, ValueError)
+    assert issubclass(IntegrationError, CrdtError)
+    assert issubclass(IntegrationError, RuntimeError)
+    assert issubclass(OffsetError, CrdtError)
+    assert issubclass(OffsetError, ValueError)
+    assert issubclass(TransactionError, CrdtError)
+    assert issubclass(TransactionError, RuntimeError)
+    assert issubclass(ConversionError, CrdtError)
+    assert issubclass(ConversionError, TypeError)
+
+
+def test_decode_error():
+    doc = Doc()
+    with pytest.raises(DecodeError) as excinfo:
+        doc.apply_update(b"\xff")
+    assert str(excinfo.value).startswith("Cannot decode update")
+    with pytest.raises(DecodeError):
+        doc.get_update(b"\xff")
+    with pytest.raises(DecodeError):
+        get_state(b"\xff")
+    with pytest.raises(DecodeError):
+        merge_updates(b"\xff", b"\xff")
+
+
+def test_merge_updates_wrong_type():
+    with pytest.raises(TypeError):
+        merge_updates(b"", 1)
+
+
+def test_sticky_index_decode_error():
+    with pytest.raises(DecodeError) as excinfo:
+        StickyIndex.decode(b"\xff")
+    assert str(excinfo.value).startswith("Cannot decode sticky index")
+    with pytest.raises(DecodeError):
+        StickyIndex.from_json({"foo": "bar"})
+
+
+def test_transaction_error():
+    doc = Doc()
+    txn = doc._doc.create_transaction()
+    with pytest.raises(TransactionError, match="Already in a transaction"):
+        doc._doc.create_transaction()
+    txn.commit()
+    txn.drop()
+    with pytest.raises(TransactionError, match="Transaction was dropped"):
+        txn.commit()
+    with pytest.raises(TransactionError, match="Transaction was dropped"):
+        doc._doc.get_or_insert_text(txn, "text")
+    with pytest.raises(TransactionError, match="Transaction was dropped"):
+        txn.origin()
+
+    doc["text"] = text = Text()
+    with doc.transaction(origin="foo"):
+        with pytest.raises(TransactionError):
+            with doc.transaction(origin="bar"):
+                pass  # pragma: nocover
+    assert str(text) == ""
+
+
+def test_conversion_error():
+    doc = Doc()
+    doc["map"] = map0 = Map()
+    doc["array"] = array0 = Array()
+    with pytest.raises(ConversionError, match="Keys must be str, not int"):
+        map0["key"] = {1: "one"}
+    with pytest.raises(ConversionError):
+        array0.append([{"a": {2: "two"}}])
+    with pytest.raises(ConversionError):
+        array0.append(2**64)
+    with pytest.raises(ConversionError, match="Type not supported"):
+        array0.append(object())
+    assert map0.to_py() == {}
+    assert array0.to_py() == []
+
+
+def test_offset_error():
+    doc = Doc()
+    doc["text"] = text = Text("héllo😀")
+    doc["fragment"] = XmlFragment([XmlText("a😀")])
+    xml_text = doc["fragment"].children[0]
+    # the bytes of a character cannot be split
+    with pytest.raises(OffsetError, match="Index 2 is not on a character boundary"):
+        text.insert(2, "x")
+    with pytest.raises(OffsetError):
+        del text[2:3]
+    with pytest.raises(OffsetError):
+        text.format(0, 2, {"bold": True})
+    with pytest.raises(OffsetError):
+        text.insert_embed(8, Map())
+    with pytest.raises(OffsetError):
+        xml_text.insert(2, "x")
+    with pytest.raises(OffsetError):
+        del xml_text[1:2]
+    assert str(text) == "héllo😀"
+    assert str(xml_text) == "a😀"
+    text.insert(3, "x")
+    del text[0:3]
+    assert str(text) == "xllo😀"
+
+    # the code units of a character can be split in the UTF-16 offset kind
+    doc = Doc(offset_kind="utf16")
+    doc["text"] = text = Text("a😀")
+    text.insert(2, "x")
@@ -1,12 +1,17 @@
 import pytest
 from pycrdt import (
+    AttributionRecorder,
     AttrRange,
     ContentAttribute,
     Doc,
     IdMap,
     IdSet,
+    Map,
+    Snapshot,
     Text,
     UndoManager,
+    XmlFragment,
+    XmlText,
 )
 
 # JSON-compatible attribute values used to exercise the value conversion / serialization paths.
@@ -549,3 +554,141 @@ def test_attribution_of_undo_insertions():
     assert attributed.as_id_set().encode() == insertions.encode()
     for _client, r in attributed.entries():
         assert [(a.name, a.value) for a in r.attributes] == [("author", "alice")]
+
+
+# attributed text diff
+
+
+def test_text_diff_attributed():
+    doc = Doc(skip_gc=True, client_id=1)
+    doc["text"] = text = Text("Hello world")
+    insertions = IdMap()
+    insertions.insert(1, 0, 5, [attr()])
+    assert text.diff_attributed(insertions) == [
+        ("Hello", None, {"insert": [attr()]}),
+        (" world", None, {"insert": []}),
+    ]
+    snapshot = Snapshot.from_doc(doc)
+
+    # "Hello " is deleted, and "😀!" (3 UTF-16 code units) is inserted at clock 12
+    # between the bold format markers
+    del text[:6]
+    text.insert(0, "😀!", {"bold": True})
+    text.insert_embed(0, {"image": "cat.png"})
+    text.insert_embed(0, Map())
+    insertions.insert(1, 12, 2, [attr(value="bob")])
+    deletions = IdMap()
+    deletions.insert(1, 2, 4, [attr("deleter", "carol")])
+    chunks = text.diff_attributed(insertions)
+    assert isinstance(chunks[0][0], Map)
+    assert chunks[0][1:] == (None, {"insert": []})
+    assert chunks[1:] == [
+        ({"image": "cat.png"}, None, None),
+        ("😀", {"bold": True}, {"insert": [attr(value="bob")]}),
+        ("!", {"bold": True}, {"insert": []}),
+        ("world", None, {"insert": []}),
+    ]
+
+    # the deleted content covered by the deletions is rendered as tombstones
+    assert text.diff_attributed(insertions, deletions)[2:] == [
+        ("llo", None, {"insert": [attr()], "delete": [attr("deleter", "carol")]}),
+        (" ", None, {"insert": [], "delete": [attr("deleter", "carol")]}),
+        ("😀", {"bold": True}, {"insert": [attr(value="bob")]}),
+        ("!", {"bold": True}, {"insert": []}),
+        ("world", None, {"insert": []}),
+    ]
+    assert text.diff_attributed(insertions, deletions, snapshot) == [
+        ("Hello", None, {"insert": [attr()]}),
+        (" world", None, {"insert": []}),
+    ]
+
+
+def test_xml_text_diff_attributed():
+    doc = Doc(client_id=1)
+    doc["fragment"] = fragment = XmlFragment()
+    text = fragment.children.append(XmlText("abc"))
+    insertions = IdMap()
+    # the XmlText itself has the clock 0
+    insertions.insert(1, 2, 1, [attr()])
+    assert text.diff_attributed(insertions) == [
+        ("a", None, {"insert": []}),
+        ("b", None, {"insert": [attr()]}),
+        ("c", None, {"insert": []}),
+    ]
+
+
+# attribution of transactions and updates
+
+
+def entries(id_map):
+    return [(client, r.start, r.end, r.attributes) for client, r in id_map.entries()]
+
+
+@pytest.mark.parametrize("v2", [False, True])
+def test_from_update(v2):
+    doc = Doc(client_id=1)
+    doc["text"] = Text("Hello")
+    state = doc.get_state()
+    remote = Doc(client_id=2)
+    remote.apply_update(doc.get_update())
+    remote_text = remote.get("text", type=Text)
+    remote_text += "!"
+    del remote_text[:1]
+    if v2:
+        update = remote.get_update_v2(state)
+    else:
+        update = remote.get_update(state)
+    insertions, deletions = IdMap.from_update(update, [attr()], v2=v2)
+    assert entries(insertions) == [(2, 0, 1, [attr()])]
+    assert entries(deletions) == [(1, 0, 1, [attr()])]
+
+
+def test_from_event():
+    doc = Doc(client_id=1)
+    doc["text"] = text = Text("Hello")
+    id_maps = []
+
+    def callback(event):
+        id_maps.append(IdMap.from_event(event, [attr()]))
+
+    doc.observe(callback)
+    with doc.transaction():
+        text += " world"
+        del text[:1]
+    insertions, deletions = id_maps[0]
+    assert entries(insertions) == [(1, 5, 11, [attr()])]
+    assert entries(deletions) == [(1, 0, 1, [attr()])]
+
+
+def test_attribution_recorder():
+    doc = Doc(skip_gc=True, client_id=1)
+    doc["text"] = text = Text()
+    recorder = AttributionRecorder(doc, lambda origin: [attr(value=origin)])
+    with doc.transaction(origin="alice"):
+        text += "Hello"
+    with doc.transaction(origin="bob"):
+        text += " world"
+        del text[:1]
+    with doc.transaction(origin="alice"):
+        text += "!"
+    assert {origin: entries(id_map) for origin, id_map in recorder.insertions.items()} == {
+        "alice": [(1, 0, 5, [attr()]), (1, 11, 12, [attr()])],
+        "bob": [(1, 5, 11, [attr(value="bob")])],
+    }
+    assert {origin: entries(id_map) for origin, id_map in recorder.deletions.items()} == {
+        "bob": [(1, 0, 1, [attr(value="bob")])],
+    }
+
+    insertions = IdMap.merge_many(list(recorder.insertions.values()))
+    deletions = IdMap.merge_many(list(recorder.deletions.values()))
+    assert text.diff_attributed(insertions, deletions) == [
+        ("H", None, {"insert": [attr()], "delete": [attr(value="bob")]}),
+        ("ello", None, {"insert": [attr()]}),
+        (" world", None, {"insert": [attr(value="bob")]}),
+        ("!", None, {"insert": [attr()]}),
+    ]
+
+    recorder.close()
+    with doc.transaction(origin="carol"):
+        text += "?"
+    assert "carol" not in recorder.insertions
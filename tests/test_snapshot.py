@@ -1,5 +1,18 @@
+from unittest.mock import ANY
+
 import pytest
-from pycrdt import Doc, Snapshot, Text
+from pycrdt import (
+    Array,
+    Doc,
+    IdSet,
+    Map,
+    Snapshot,
+    Text,
+    TransactionError,
+    XmlElement,
+    XmlFragment,
+    XmlText,
+)
 
 
 def test_snapshot_encode_roundtrip():
@@ -65,3 +78,297 @@ def test_doc_from_snapshot():
     with doc.transaction():
         text = doc["test"]
     assert str(text) == "new old"
+
+
+def test_snapshot_inspection():
+    doc = Doc(client_id=3, skip_gc=True)
+    doc["test"] = text = Text("abc")
+    snapshot = Snapshot.from_doc(doc)
+    assert snapshot.state == doc.get_state()
+    assert isinstance(snapshot.delete_set, IdSet)
+    assert snapshot.is_visible(3, 0)
+    assert not snapshot.is_visible(3, 3)
+    assert not snapshot.is_visible(4, 0)
+
+    # equal snapshots are equal and have the same hash
+    snapshot2 = Snapshot.from_doc(doc)
+    assert snapshot2 == snapshot
+    assert hash(snapshot2) == hash(snapshot)
+    assert len({snapshot, snapshot2}) == 1
+
+    del text[0]
+    text += "d"
+    snapshot3 = Snapshot.from_doc(doc)
+    assert snapshot3 != snapshot
+    assert not snapshot3.is_visible(3, 0)
+    assert snapshot3.is_visible(3, 1)
+    assert snapshot3.is_visible(3, 3)
+
+    # v2 encoding
+    assert Snapshot.decode_v2(snapshot3.encode_v2()) == snapshot3
+    with pytest.raises(ValueError):
+        Snapshot.decode_v2(b"notavalidsnapshot")
+
+    # construction from a state and a delete set
+    assert Snapshot.from_state(snapshot3.state, snapshot3.delete_set) == snapshot3
+    assert Snapshot.from_state(snapshot.state, snapshot3.delete_set) != snapshot
+    assert Snapshot.from_state(snapshot.state, IdSet()) == snapshot
+
+
+def test_snapshot_zero_clock():
+    doc = Doc(client_id=1, skip_gc=True)
+    doc["text"] = text = Text("Hello")
+    # the state of a client that had no content yet
+    snapshot = Snapshot.from_state(b"\x01\x01\x00", IdSet())
+    assert text.to_py(snapshot) == ""
+    doc.revert(snapshot)
+    assert str(text) == ""
+
+
+def test_snapshot_partial_block():
+    doc = Doc(client_id=1, skip_gc=True)
+    doc["array"] = array = Array([0])
+    array.insert(0, "a")
+    snapshot = Snapshot.from_doc(doc)
+    # merged with the previous insertion in a block that the snapshot only partly includes
+    array.insert(1, "b")
+    assert Doc.from_snapshot(snapshot, doc).get("array", type=Array).to_py() == ["a", 0]
+    assert array.diff_snapshots(snapshot) == [{"retain": 1}, {"insert": ["b"]}]
+
+
+def test_read_at_snapshot():
+    doc = Doc(skip_gc=True)
+    doc["text"] = text = Text("Hello")
+    doc["array"] = array = Array([0, 1])
+    doc["map"] = map0 = Map({"foo": 0})
+    doc["fragment"] = fragment = XmlFragment([XmlElement("p", {}, [XmlText("Hello")])])
+    text.format(0, 1, {"bold": True})
+    snapshot0 = Snapshot.from_doc(doc)
+
+    text += ", World!"
+    del text[0]
+    array.append(Map({"bar": 1}))
+    del array[0]
+    map0["foo"] = 1
+    map0["nested"] = nested = Array([2])
+    fragment.children[0].children[0].insert(5, ", World!")
+    fragment.children.append(XmlElement("div"))
+    snapshot1 = Snapshot.from_doc(doc)
+    nested.append(3)
+
+    assert text.to_py(snapshot0) == "Hello"
+    assert text.to_py(snapshot1) == "ello, World!"
+    assert text.diff(snapshot0) == [("H", {"bold": True}), ("ello", None)]
+    assert array.to_py(snapshot0) == [0, 1]
+    assert array.to_py(snapshot1) == [1, {"bar": 1}]
+    assert map0.to_py(snapshot0) == {"foo": 0}
+    assert map0.to_py(snapshot1) == {"foo": 1, "nested": [2]}
+    # a type that didn't exist yet is empty
+    assert nested.to_py(snapshot0) == []
+    assert nested.to_py(snapshot1) == [2]
+    assert fragment.get_string(snapshot0) == "<p>Hello</p>"
+    assert fragment.get_string(snapshot1) == "<p>Hello, World!</p><div></div>"
+    xml_text = fragment.children[0].children[0]
+    assert xml_text.to_py(snapshot0) == "Hello"
+    assert xml_text.diff(snapshot0) == [("Hello", None)]
+
+    # the current content is unchanged
+    assert str(text) == "ello, World!"
+    assert array.to_py() == [1, {"bar": 1}]
+    assert map0.to_py() == {"foo": 1, "nested": [2, 3]}
+
+    # values keep their type
+    doc["values"] = values = Array([1.0, b"bytes"])
+    assert values.to_py(Snapshot.from_doc(doc)) == [1.0, b"bytes"]
+    assert type(values.to_py(Snapshot.from_doc(doc))[0]) is float
+
+    # reading at a snapshot requires skip_gc
+    doc = Doc()
+    doc["text"] = text = Text("Hello")
+    snapshot = Snapshot.from_doc(doc)
+    with pytest.raises(ValueError, match="skip_gc"):
+        text.to_py(snapshot)
+    with pytest.raises(ValueError, match="skip_gc"):
+        text.diff(snapshot)
+    with pytest.raises(ValueError, match="skip_gc"):
+        text.diff_snapshots(snapshot)
+
+
+def test_read_at_snapshot_in_place():
+    doc0 = Doc(client_id=1, skip_gc=True)
+    doc1 = Doc(client_id=2, skip_gc=True)
+    array0 = doc0.get("array", type=Array)
+    array1 = doc1.get("array", type=Array)
+    array0.extend([0, 1])
+    doc1.apply_update(doc0.get_update())
+    # concurrent insertions at the same position
+    array0.insert(1, "a")
+    array1.insert(1, "b")
+    array1.append(Map({"doc": Doc()}))
+    doc0.apply_update(doc1.get_update(doc0.get_state()))
+    snapshot = Snapshot.from_doc(doc0)
+    del array0[0:2]
+    subdoc = array0[-1]["doc"]
+
+    with doc0.transaction():
+        array0.append(2)
+        values = array0.to_py(snapshot)
+    assert values[:4] == [0, "a", "b", 1]
+    # the subdocuments are the ones of the document
+    assert values[4]["doc"].guid == subdoc.guid
+
+
+def test_diff_snapshots():
+    doc = Doc(client_id=5, skip_gc=True)
+    doc["text"] = text = Text("Hello World")
+    snapshot0 = Snapshot.from_doc(doc)
+    del text[5:11]
+    text += ", you!"
+    text.format(0, 5, {"bold": True})
+    snapshot1 = Snapshot.from_doc(doc)
+    text += "!"
+
+    assert text.diff_snapshots(snapshot0, snapshot1) == [
+        ("Hello", {"bold": True}, None),
+        (" World", {"bold": True}, {"type": "removed", "client": 5, "clock": 5}),
+        (", you!", None, {"type": "added", "client": 5, "clock": 11}),
+    ]
+    # compared with the current version by default
+    assert text.diff_snapshots(snapshot1) == [
+        ("Hello", {"bold": True}, None),
+        (", you!", None, None),
+        ("!", None, {"type": "added", "client": 5, "clock": 19}),
+    ]
+
+    doc["fragment"] = fragment = XmlFragment([XmlText("abc")])
+    xml_text = fragment.children[0]
+    snapshot2 = Snapshot.from_doc(doc)
+    del xml_text[0]
+    assert xml_text.diff_snapshots(snapshot2) == [
+        ("a", None, {"type": "removed", "client": 5, "clock": 21}),
+        ("bc", None, None),
+    ]
+
+
+def test_revert():
+    doc = Doc(skip_gc=True)
+    doc["text"] = text = Text("Hello")
+    text.format(0, 2, {"bold": True})
+    text.insert_embed(5, Map({"x": 1}))
+    doc["array"] = array = Array([1, Map({"a": Text("x")}), "z"])
+    doc["map"] = map0 = Map({"k": 1, "nested": Array([1, 2])})
+    doc["fragment"] = fragment = XmlFragment([XmlElement("p", {"x": "1"}, [XmlText("hi")])])
+    remote_doc = Doc()
+    remote_doc.apply_update(doc.get_update())
+    snapshot = Snapshot.from_doc(doc)
+    content = (text.diff()[:2], array.to_py(), map0.to_py(), str(fragment))
+
+    text.insert(3, "ZZ", {"italic": True})
+    text.format(0, 5, {"bold": None})
+    text += "!"
+    array[1]["a"] += "y"
+    array.append(4)
+    del array[0]
+    map0["k"] = 2
+    map0["new"] = 3
+    map0["nested"].append(3)
+    paragraph = fragment.children[0]
+    paragraph.attributes["x"] = "2"
+    paragraph.children[0].insert(2, "!", {"bold": True})
+    fragment.children.append("tail")
+    doc["later"] = later = Text("later")
+    remote_doc.apply_update(doc.get_update(remote_doc.get_state()))
+
+    # the update can be computed without applying it
+    state = doc.get_state()
+    update = doc.revert(snapshot, apply=False)
+    assert doc.get_state() == state
+    other_doc = Doc()
+    other_doc.apply_update(doc.get_update())
+    other_doc.apply_update(update)
+    assert other_doc.get("array", type=Array).to_py() == content[1]
+
+    origins = []
+    doc.observe(lambda event: origins.append(event.origin))
+    update = doc.revert(snapshot, origin="revert")
+    assert origins == ["revert"]
+    assert (text.diff()[:2], array.to_py(), map0.to_py(), str(fragment)) == content
+    assert str(text.diff()[2][0]) == '{"x":1}'
+    # a root type that didn't exist at the snapshot is emptied
+    assert str(later) == ""
+    # the history is kept
+    assert text.to_py(snapshot) == "Hello"
+
+    # peers converge by applying the update
+    remote_doc.apply_update(update)
+    assert remote_doc.get_state() == doc.get_state()
+    assert remote_doc.get("text", type=Text).diff()[:2] == content[0]
+    # reverting again doesn't change anything
+    assert doc.revert(snapshot) == Doc().get_update()
+
+    with doc.transaction():
+        with pytest.raises(TransactionError, match="ongoing transaction"):
+            doc.revert(snapshot)
+
+
+def test_revert_roots_not_accessed():
+    doc = Doc()
+    doc["text"] = text = Text("Hello")
+    doc["array"] = array = Array([1, 2])
+    remote_doc = Doc(skip_gc=True)
+    remote_doc.apply_update(doc.get_update())
+    snapshot = Snapshot.from_doc(remote_doc)
+    text += ", World!"
+    array.clear()
+    remote_doc.apply_update(doc.get_update(remote_doc.get_state()))
+
+    # the root types were only received, their type is inferred from their content
+    # in the document or at the snapshot
+    remote_doc.revert(snapshot)
+    assert str(remote_doc.get("text", type=Text)) == "Hello"
+    assert remote_doc.get("array", type=Array).to_py() == [1, 2]
+
+
+def test_diff_snapshots_structure():
+    doc = Doc(skip_gc=True)
+    doc["array"] = array = Array([1, 2, Map({"a": 1}), 3])
+    doc["map"] = map0 = Map({"k": 1, "nested": Array([1])})
+    doc["text"] = Text("Hello")
+    snapshot0 = Snapshot.from_doc(doc)
+    del array[0]
+    array.insert(2, "new")
+    array[1]["a"] = 2
+    map0["k"] = 2
+    del map0["nested"]
+    map0["added"] = Array([5])
+    snapshot1 = Snapshot.from_doc(doc)
+    array.append(4)
+
+    map_changes = {
+        "k": {"action": "update", "oldValue": 1.0, "newValue": 2.0},
+        "nested": {"action": "delete", "oldValue": ANY},
+        "added": {"action": "add", "newValue": ANY},
+    }
+    changes = [
+        (["array"], [{"delete": 1}, {"retain": 2}, {"insert": ["new"]}]),
+        (["array", 1], {"a": {"action": "update", "oldValue": 1.0, "newValue": 2.0}}),
+        (["map"], map_changes),
+    ]
+    assert doc.diff_snapshots(snapshot0, snapshot1) == changes
+    added = map0.diff_snapshots(snapshot0, snapshot1)["added"]["newValue"]
+    assert isinstance(added, Array)
+    assert added.to_py() == [5.0]
+    assert array[1].diff_snapshots(snapshot0) == {
+        "a": {"action": "update", "oldValue": 1.0, "newValue": 2.0}
+    }
+    # compared with the current version by default
+    assert array.diff_snapshots(snapshot1) == [{"retain": 4}, {"insert": [4.0]}]
+    assert map0.diff_snapshots(snapshot1) == {}
+
+    # the root types of a remote document that were not accessed are inferred from their content
+    remote_doc = Doc(skip_gc=True)
+    remote_doc.apply_update(doc.get_update())
+    assert remote_doc.diff_snapshots(snapshot0, snapshot1) == changes
+
+    with pytest.raises(ValueError, match="must precede"):
+        doc.diff_snapshots(snapshot1, snapshot0)
@@ -4,8 +4,14 @@ from anyio.abc import TaskStatus
 from pycrdt import (
     Array,
     Doc,
+    Message,
+    YMessageType,
+    YSyncMessageType,
+    create_awareness_message,
     create_sync_message,
     create_update_message,
+    decode_messages,
+    encode_messages,
     handle_sync_message,
 )
 from pycrdt._sync import Decoder, write_var_uint
@@ -96,3 +102,76 @@ def test_decoder():
     assert list(Decoder(b"\x00").read_messages()) == [b""]
     assert Decoder(b"").read_var_string() == ""
     assert Decoder(b"\x05Hello").read_var_string() == "Hello"
+
+
+def test_decode_messages():
+    doc = Doc()
+    doc["array"] = Array([0, 1])
+    state = doc.get_state()
+    update = doc.get_update()
+    awareness_update = b"\x01\x01\x00\x02{}"
+    data = (
+        create_sync_message(doc)
+        + create_update_message(update)
+        + create_awareness_message(awareness_update)
+        + Message.auth("denied").encode()
+        + Message.query_awareness().encode()
+        + Message.custom(7, b"foo").encode()
+    )
+    messages = decode_messages(data)
+    assert [message.message_type for message in messages] == [
+        YMessageType.SYNC,
+        YMessageType.SYNC,
+        YMessageType.AWARENESS,
+        YMessageType.AUTH,
+        YMessageType.QUERY_AWARENESS,
+        7,
+    ]
+    assert messages[0].sync_message_type == YSyncMessageType.SYNC_STEP1
+    assert messages[0].payload == state
+    assert messages[1].sync_message_type == YSyncMessageType.SYNC_UPDATE
+    assert messages[1].payload == update
+    assert messages[2].sync_message_type is None
+    assert messages[2].payload == awareness_update
+    assert messages[3].reason == "denied"
+    assert messages[3].payload is None
+    assert messages[4].payload is None
+    assert messages[5].payload == b"foo"
+    assert encode_messages(*messages) == data
+    assert decode_messages(b"") == []
+
+    # a truncated trailing message must not be dropped silently
+    with pytest.raises(ValueError, match="Cannot decode message"):
+        decode_messages(b"\x00\x01\x05ab")
+    with pytest.raises(ValueError, match="Cannot decode message"):
+        decode_messages(data + b"\x00\x02")
+
+
+def test_encode_messages():
+    doc = Doc()
+    doc["array"] = Array([0])
+    update = doc.get_update()
+    assert Message.sync_step1(doc.get_state()).encode() == create_sync_message(doc)
+    assert Message.sync_update(update).encode() == create_update_message(update)
+    assert Message.sync_update(update) == Message.sync_update(update)
+    assert Message.sync_update(update) != Message.sync_step2(update)
+    assert Message.auth().reason is None
+
+    with pytest.raises(ValueError, match="Cannot decode message"):
+        decode_messages(b"\x00\x05")
+    with pytest.raises(ValueError, match="reserved"):
+        Message.custom(1, b"")
+
+
+def test_handle_sync_message():
+    doc0 = Doc()
+    doc0["array"] = array0 = Array([0, 1])
+    doc1 = Doc()
+    array1 = doc1.get("array", type=Array)
+
+    reply = handle_sync_message(create_sync_message(doc1)[1:], doc0)
+    assert reply is not None
+    (message,) = decode_messages(reply)
+    assert message.sync_message_type == YSyncMessageType.SYNC_STEP2
+    assert handle_sync_message(reply[1:], doc1) is None
+    assert array1.to_py() == array0.to_py() == [0, 1]
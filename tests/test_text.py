@@ -114,6 +114,12 @@ def test_slice():
     for i, c in enumerate(hello):
         assert text[i] == c
 
+    with pytest.raises(IndexError) as excinfo:
+        text[len(hello)]
+    assert str(excinfo.value) == "Text index out of range"
+    with pytest.raises(IndexError):
+        del text[-len(hello) - 1]
+
     with pytest.raises(RuntimeError) as excinfo:
         text[1::2] = "a"
     assert str(excinfo.value) == "Step not supported"
@@ -259,6 +265,55 @@ def test_sticky_index(serialize: str):
     assert text1[new_idx] == "*"
 
 
+@pytest.mark.parametrize("offset_kind,emoji_len", [("bytes", 4), ("utf16", 2)])
+def test_offset_kind(offset_kind: str, emoji_len: int):
+    doc = Doc(offset_kind=offset_kind)
+    doc["text"] = text = Text("😀")
+    deltas = []
+    text.observe(lambda event: deltas.append(event.delta))
+    assert len(text) == emoji_len
+    text.insert(emoji_len, "a")
+    assert str(text) == "😀a"
+    assert deltas[-1] == [{"retain": emoji_len}, {"insert": "a"}]
+    assert text[:emoji_len] == "😀"
+    assert text[emoji_len] == text[-1] == "a"
+    # an index gets the whole character it falls in
+    assert text[0] == text[1] == text[-emoji_len - 1] == "😀"
+    with pytest.raises(RuntimeError):
+        text[1:]
+    with pytest.raises(IndexError):
+        text[emoji_len + 1]
+    with pytest.raises(IndexError):
+        text[-emoji_len - 2]
+    text += "😀"
+    assert text[-1] == "😀"
+    text[-1] = "b"
+    assert str(text) == "😀ab"
+    text[1] = "😃"
+    assert str(text) == "😃ab"
+    del text[-2]
+    assert str(text) == "😃b"
+    del text[1]
+    assert str(text) == "b"
+    text[0] = "a"
+    text.insert(0, "😀")
+    text.insert(0, "😀")
+    text.format(emoji_len, 2 * emoji_len, {"bold": True})
+    assert text.diff() == [("😀", None), ("😀", {"bold": True}), ("a", None)]
+    del text[: 2 * emoji_len]
+    assert str(text) == "a"
+
+
+def test_sticky_index_utf16():
+    doc = Doc(offset_kind="utf16")
+    doc["text"] = text = Text("😀*")
+    sticky_index = text.sticky_index(2, Assoc.AFTER)
+    text.insert(0, "👋 ")
+    assert sticky_index.get_index() == 5
+    assert text[5] == "*"
+    assert str(text) == "👋 😀*"
+
+
 def test_sticky_index_transaction():
     doc = Doc()
     text = doc.get("text", type=Text)
@@ -1,8 +1,10 @@
 import gc
+import sys
+from threading import Event, Thread
 
 import pytest
 from anyio import CapacityLimiter, to_thread
-from pycrdt import Doc, Text
+from pycrdt import Doc, Snapshot, Text, merge_updates
 
 pytestmark = pytest.mark.anyio
 
@@ -35,3 +37,62 @@ async def test_multi_threading():
 
     with pytest.raises(UnboundLocalError):
         doc
+
+
+def test_release_gil():
+    doc = Doc(allow_multithreading=True)
+    doc["text"] = text = Text()
+    held = Event()
+
+    def hold():
+        with doc.transaction():
+            text.insert(0, "abc")
+            held.set()
+            # ending the transaction runs Python code, so the GIL must be free for this
+            # thread to make progress while the main thread waits for the transaction
+
+    thread = Thread(target=hold)
+    thread.start()
+    held.wait()
+    # blocks (without the GIL) until the other thread has committed its transaction
+    snapshot = Snapshot.from_doc(doc)
+    thread.join()
+    assert snapshot == Snapshot.from_doc(doc)
+    assert str(text) == "abc"
+
+    updates = []
+    sub = doc.observe(lambda event: updates.append(event.update))  # noqa: F841
+    for _ in range(10):
+        text.insert(0, "abc")
+    update = doc.get_update()
+    remote_doc = Doc()
+    assert not releases_gil(lambda: sum(range(1000)))
+    assert releases_gil(lambda: merge_updates(*updates))
+    # in a transaction, so that only the call releases the GIL, not the transaction acquisition
+    with doc.transaction():
+        assert releases_gil(doc.get_update)
+    with remote_doc.transaction():
+        assert releases_gil(lambda: remote_doc.apply_update(update))
+    assert str(remote_doc.get("text", type=Text)) == str(text)
+
+
+def releases_gil(f) -> bool:
+    # whether calling a function lets another thread run: the interpreter doesn't switch threads
+    # by itself with a long switch interval, so the function must release the GIL for that
+    go = Event()
+    ran = []
+    thread = Thread(target=lambda: (go.wait(), ran.append(True)))
+    thread.start()
+    interval = sys.getswitchinterval()
+    sys.setswitchinterval(1000)
+    try:
+        go.set()
+        for _ in range(1000):
+            f()
+            if ran:
+                break
+    finally:
+        sys.setswitchinterval(interval)
+    released = bool(ran)
+    thread.join()
+    return released
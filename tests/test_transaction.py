@@ -1,11 +1,13 @@
 import gc
 import sys
+import threading
 import time
+from concurrent.futures import ThreadPoolExecutor
 from functools import partial
 
 import pytest
 from anyio import create_task_group, fail_after, sleep, to_thread
-from pycrdt import Array, Doc, Map, Text, XmlFragment
+from pycrdt import Array, Doc, IdSet, Map, Text, TransactionError, UndoManager, XmlFragment
 
 if sys.version_info < (3, 11):
     from exceptiongroup import ExceptionGroup  # pragma: no cover
@@ -110,6 +112,43 @@ def test_origin():
     assert str(excinfo.value) == "Nested transactions must have same origin as root transaction"
 
 
+def test_bytes_origin():
+    doc = Doc()
+    doc["text"] = text = Text()
+    origins = []
+    doc_origins = []
+    text.observe(lambda event, txn: origins.append(txn.origin))
+    doc.observe(lambda event: doc_origins.append(event.origin))
+
+    with doc.transaction(origin=b"remote") as txn:
+        assert txn._txn.origin() == b"remote"
+        text += "Hello"
+    assert origins == [b"remote"]
+    assert doc_origins == [b"remote"]
+
+    class Origin:
+        pass
+
+    origin = Origin()
+    with doc.transaction(origin=origin):
+        text += ", World!"
+    assert origins[1] is origin
+    assert doc_origins[1] is origin
+
+    text += "!"
+    assert origins[2] is None
+    assert doc_origins[2] is None
+    assert doc._origins == {}
+
+    # origins that were not set through a document are returned as bytes
+    txn = doc._doc.create_transaction_with_origin(b"native")
+    text.integrated.insert(txn, 0, ">")
+    txn.commit()
+    txn.drop()
+    assert origins[3] == b"native"
+    assert doc_origins[3] == b"native"
+
+
 def test_observe_callback_params():
     doc = Doc()
     doc["text"] = text = Text()
@@ -214,6 +253,15 @@ def test_new_transaction_while_transaction():
     assert str(excinfo.value) == "Could not acquire transaction"
 
 
+def test_new_transaction_infinite_timeout():
+    doc = Doc(allow_multithreading=True)
+    text = doc.get("text", type=Text)
+
+    with doc.new_transaction(timeout=float("inf")):
+        text += "foo"
+    assert str(text) == "foo"
+
+
 async def test_new_transaction_while_async_transaction():
     doc = Doc(allow_multithreading=True)
 
@@ -278,6 +326,34 @@ async def test_new_async_transaction_concurrent_no_multithreading():
     assert map0.to_py() == {"key0": "val0", "key1": "val1"}
 
 
+async def test_new_transaction_queue():
+    doc = Doc(allow_multithreading=True)
+    doc["array0"] = array0 = Array()
+
+    def append_in_thread(value):
+        with doc.new_transaction():
+            array0.append(value)
+
+    async def append_in_task(value):
+        async with doc.new_transaction():
+            array0.append(value)
+
+    async with create_task_group() as tg:
+        with doc.transaction():
+            # threads and tasks wait for the transaction to be released, in order
+            tg.start_soon(to_thread.run_sync, append_in_thread, "thread0")
+            await sleep(0.1)
+            tg.start_soon(append_in_task, "task0")
+            await sleep(0.1)
+            tg.start_soon(to_thread.run_sync, append_in_thread, "thread1")
+            await sleep(0.1)
+            tg.start_soon(append_in_task, "task1")
+            await sleep(0.1)
+            array0.append("main")
+
+    assert array0.to_py() == ["main", "thread0", "task0", "thread1", "task1"]
+
+
 def test_get_root_type_in_transaction():
     doc = Doc()
     with doc.transaction():
@@ -372,3 +448,333 @@ async def test_concurrent_async_transactions():
     async with create_task_group() as tg:
         await tg.start(create_async_transaction, 0.2)
         await tg.start(create_async_transaction, 0.1)
+
+
+def test_read_transaction():
+    doc = Doc(allow_multithreading=True)
+    doc["text"] = text = Text("Hello")
+    doc["map0"] = map0 = Map({"key": "val"})
+    state = doc.get_state()
+    update = doc.get_update()
+
+    def read(text: Text, map0: Map, event: threading.Event):
+        with text.doc.read_transaction() as txn:
+            event.set()
+            time.sleep(0.1)
+            return str(text), map0.to_py(), txn.origin
+
+    event = threading.Event()
+    with ThreadPoolExecutor() as executor:
+        future = executor.submit(read, text, map0, event)
+        event.wait()
+        # another reader while the first one is open
+        with doc.read_transaction():
+            assert str(text) == "Hello"
+            assert doc.get_state() == state
+            assert doc.get_update() == update
+            with pytest.raises(RuntimeError) as excinfo:
+                text += ", World!"
+            assert str(excinfo.value) == (
+                "Read-only transaction cannot be used to modify document structure"
+            )
+        assert future.result() == ("Hello", {"key": "val"}, None)
+
+    with doc.read_transaction():
+        with pytest.raises(TimeoutError) as excinfo:
+            with doc.new_transaction(timeout=0.1):
+                pass  # pragma: no cover
+    assert str(excinfo.value) == "Could not acquire transaction"
+
+    with doc.transaction() as txn:
+        assert doc.read_transaction() is txn
+
+
+def test_abort():
+    doc = Doc()
+    doc["text"] = text = Text("Hello")
+    doc["map0"] = map0 = Map({"key0": "val0"})
+    doc["array0"] = array0 = Array([0])
+    remote_doc = Doc()
+    remote_doc.apply_update(doc.get_update())
+    type_events = []
+    updates = []
+    text.observe(lambda event: type_events.append(event))
+    map0.observe_deep(lambda events: type_events.append(events))  # pragma: no cover
+    doc.observe(lambda event: updates.append(event.update))
+
+    with doc.transaction() as txn:
+        text += ", World!"
+        map0["key1"] = Map({"key2": "val2"})
+        array0.append(1)
+        txn.abort()
+        with pytest.raises(RuntimeError) as excinfo:
+            array0.append(2)
+        assert str(excinfo.value) == (
+            "Aborted transaction cannot be used to modify document structure"
+        )
+
+    assert str(text) == "Hello"
+    assert map0.to_py() == {"key0": "val0"}
+    assert array0.to_py() == [0]
+    assert type_events == []
+    # the update is sent right away, for the remote document to stay in sync
+    assert len(updates) == 1
+    remote_doc.apply_update(updates[0])
+    assert str(remote_doc.get("text", type=Text)) == "Hello"
+    text += "!"
+    assert len(updates) == 2
+    remote_doc.apply_update(updates[1])
+    assert str(remote_doc.get("text", type=Text)) == "Hello!"
+    type_events.clear()
+
+    with doc.transaction() as txn:
+        map0["key0"] = "val1"
+        del text[0:2]
+        del array0[0]
+        txn.abort()
+
+    assert str(text) == "Hello!"
+    assert map0.to_py() == {"key0": "val0"}
+    assert array0.to_py() == [0]
+    assert type_events == []
+    assert len(updates) == 3
+    remote_doc.apply_update(updates[2])
+    assert remote_doc.get("map0", type=Map).to_py() == {"key0": "val0"}
+    assert remote_doc.get("array0", type=Array).to_py() == [0]
+    assert str(remote_doc.get("text", type=Text)) == "Hello!"
+
+
+def test_abort_remote_changes():
+    doc = Doc()
+    doc["text"] = text = Text("Hello")
+    remote_doc = Doc()
+    remote_doc.apply_update(doc.get_update())
+    remote_text = remote_doc.get("text", type=Text)
+    remote_text += ", World!"
+    del remote_text[0]
+    update = remote_doc.get_update(doc.get_state())
+    events = []
+    doc.observe(lambda event: events.append(event.changed_types), track_changes=True)
+
+    # the content inserted and deleted by the updates applied in the transaction is rolled back
+    with doc.transaction() as txn:
+        doc.apply_update(update)
+        assert str(text) == "ello, World!"
+        txn.abort()
+
+    assert str(text) == "Hello"
+    assert events == [{}]
+
+
+def test_abort_doc_wrappers():
+    doc = Doc()
+    doc["text"] = text = Text("Hello")
+    remote_doc = Doc()
+    remote_doc.apply_update(doc.get_update())
+    # another wrapper of the same document, whose observers must not be called either
+    other_doc = Doc(doc=doc._doc)
+    other_text = other_doc.get("text", type=Text)
+    type_events = []
+    after_events = []
+    updates = []
+    other_text.observe(lambda event: type_events.append(event))  # pragma: no cover
+    other_doc.observe_after_transaction(lambda origin: after_events.append(origin))
+    other_doc.observe_update(lambda update, origin: updates.append(update))
+
+    with doc.transaction() as txn:
+        text += ", World!"
+        txn.abort()
+
+    assert type_events == []
+    assert after_events == []
+    assert len(updates) == 1
+    assert str(other_text) == "Hello"
+    remote_doc.apply_update(updates[0])
+    assert str(remote_doc.get("text", type=Text)) == "Hello"
+
+    after_events.clear()
+    text += "!"
+    assert len(type_events) == 1
+    assert len(after_events) == 1
+    assert len(updates) == 2
+
+
+def test_rollback_on_error():
+    doc = Doc()
+    doc["text"] = text = Text("Hello")
+
+    with pytest.raises(ValueError):
+        with doc.transaction(rollback_on_error=True):
+            text += ", World!"
+            raise ValueError("Invalid edit")
+    assert str(text) == "Hello"
+
+    with pytest.raises(ValueError):
+        with doc.transaction(rollback_on_error=True):
+            del text[0:2]
+            text += "X"
+            raise ValueError("Invalid edit")
+    assert str(text) == "Hello"
+
+    with pytest.raises(ValueError):
+        with doc.transaction():
+            text += ", World!"
+            raise ValueError("Invalid edit")
+    assert str(text) == "Hello, World!"
+
+
+def test_validator():
+    doc = Doc()
+    doc["map"] = map0 = Map({"name": "foo", "nested": Map({"key": Array()})})
+    doc["text"] = text = Text("Hello")
+    remote_doc = Doc()
+    doc.observe(lambda event: remote_doc.apply_update(event.update))
+    remote_doc.apply_update(doc.get_update())
+    changes = []
+
+    def validate(pending_changes):
+        changes.append(
+            (
+                pending_changes.origin,
+                pending_changes.changed_types,
+                pending_changes.update,
+                pending_changes.delete_set,
+                pending_changes.events,
+            )
+        )
+        if "name" not in map0:
+            raise ValueError("Missing name")
+        if len(text) > 12:
+            raise ValueError("Text too long")
+
+    doc.add_validator(validate)
+
+    with doc.transaction(origin="origin"):
+        text += ", World"
+        map0["nested"]["key"].append(1)
+    origin, changed_types, update, delete_set, events = changes.pop()
+    assert origin == "origin"
+    assert changed_types == {("text",), ("map", "nested", "key")}
+    assert isinstance(update, bytes)
+    assert isinstance(delete_set, IdSet)
+    assert events["text"][0].delta == [{"retain": 5}, {"insert": ", World"}]
+    assert events["map"][0].path == ["nested", "key"]
+    assert events["map"][0].delta == [{"insert": [1.0]}]
+
+    updates = []
+    doc.observe(lambda event: updates.append(event.update))
+    with pytest.raises(TransactionError) as excinfo:
+        text += "!"
+    assert isinstance(excinfo.value.__cause__, ValueError)
+    assert str(text) == "Hello, World"
+    # the update of the rolled back transaction is still sent
+    assert len(updates) == 1
+    assert changes.pop()[1] == {("text",)}
+
+    with pytest.raises(TransactionError) as excinfo:
+        del map0["name"]
+    assert isinstance(excinfo.value.__cause__, ValueError)
+    assert changes.pop()[1] == {("map",)}
+    assert map0["name"] == "foo"
+
+    # transactions without changes are not validated
+    with doc.transaction():
+        pass
+    assert not changes
+
+    doc.remove_validator(validate)
+    text += "!"
+    assert str(remote_doc.get("text", type=Text)) == str(text) == "Hello, World!"
+
+
+def test_validator_events():
+    doc = Doc()
+    doc["text"] = text = Text("ab")
+    live_events = []
+    text.observe(lambda event: live_events.append(event.delta))
+    validated_events = []
+    doc.add_validator(
+        lambda changes: validated_events.append([event.delta for event in changes.events["text"]])
+    )
+
+    # the validators get the events that the observers get
+    with doc.transaction():
+        text.insert(2, "c")
+        del text[0]
+    assert validated_events.pop() == live_events
+    assert live_events.pop() == [{"delete": 1}, {"retain": 1}, {"insert": "c"}]
+
+    # including for remote updates into a document that didn't have the content yet
+    remote_doc = Doc()
+    remote_doc.add_validator(lambda changes: validated_events.append(changes.events))
+    remote_doc.apply_update(doc.get_update())
+    assert [event.delta for event in validated_events.pop()["text"]] == [[{"insert": "bc"}]]
+
+
+def test_defer_transaction():
+    doc = Doc()
+    doc["array"] = array = Array()
+    doc["stats"] = stats = Map()
+    origins = []
+    doc.observe(lambda event: origins.append(event.origin))
+
+    def callback(event):
+        if event.transaction.origin != "count":
+            # writes are forbidden in observers, but can be deferred
+            with pytest.raises(TransactionError):
+                stats["count"] = len(array)
+            doc.defer_transaction(lambda txn: stats.update({"count": len(array)}), origin="count")
+
+    array.observe(callback)
+    stats.observe(lambda event: doc.defer_transaction(lambda txn: origins.append(txn.origin)))
+
+    with doc.transaction(origin="append"):
+        array.append(0)
+        array.append(1)
+        assert "count" not in stats
+    assert stats["count"] == 2
+    assert origins == ["append", "count", None]
+
+    # follow-up transactions are not run if the transaction is rejected
+    def validate(changes):
+        raise ValueError()
+
+    doc.add_validator(validate)
+    with pytest.raises(TransactionError):
+        with doc.transaction():
+            array.append(2)
+            doc.defer_transaction(lambda txn: origins.append("rejected"))
+    doc.remove_validator(validate)
+    assert "rejected" not in origins
+    assert stats["count"] == 2
+
+    # but they are run if the transaction is committed despite an error
+    with pytest.raises(RuntimeError):
+        with doc.transaction():
+            array.append(2)
+            raise RuntimeError()
+    assert stats["count"] == 3
+
+    # follow-up transactions are run after undo
+    undo_manager = UndoManager(scopes=[array], capture_timeout_millis=0)
+    array.append(3)
+    assert stats["count"] == 4
+    undo_manager.undo()
+    assert stats["count"] == 3
+
+    # outside of a transaction, the follow-up transaction is run right away
+    doc.defer_transaction(lambda txn: origins.append(txn.origin), origin="now")
+    assert origins[-1] == "now"
+
+    # the errors of follow-up transactions are raised together, once they have all run
+    def fail(txn):
+        raise RuntimeError("deferred")
+
+    with pytest.raises(ExceptionGroup) as excinfo:
+        with doc.transaction():
+            array.append(4)
+            doc.defer_transaction(fail)
+            doc.defer_transaction(lambda txn: origins.append("after"))
+    assert excinfo.group_contains(RuntimeError, match="deferred")
+    assert "after" in origins
+    assert stats["count"] == 4
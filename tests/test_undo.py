@@ -157,6 +157,32 @@ def test_origin():
     assert not undo_manager.can_undo()
 
 
+def test_bytes_origin():
+    doc = Doc()
+    doc["text"] = text = Text()
+    undo_manager = UndoManager(scopes=[text], capture_timeout_millis=0)
+    undo_manager.include_origin(b"origin")
+    text += "Hello"
+    assert not undo_manager.can_undo()
+    with doc.transaction(origin=b"other"):
+        text += ", World"
+    assert not undo_manager.can_undo()
+    with doc.transaction(origin=b"origin"):
+        text += "!"
+    assert undo_manager.can_undo()
+    undo_manager.undo()
+    assert str(text) == "Hello, World"
+
+    # a natively created transaction with the same origin is tracked too
+    txn = doc._doc.create_transaction_with_origin(b"origin")
+    text.integrated.insert(txn, 0, ">")
+    txn.commit()
+    txn.drop()
+    assert undo_manager.can_undo()
+    undo_manager.undo()
+    assert str(text) == "Hello, World"
+
+
 def test_timestamp():
     timestamp = 0
     timestamp_called = 0
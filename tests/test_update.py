@@ -1,4 +1,23 @@
-from pycrdt import Doc, Map, Text, get_state, get_update, merge_updates
+import pytest
+from pycrdt import (
+    Array,
+    DecodeError,
+    Doc,
+    IdMap,
+    Map,
+    Snapshot,
+    Text,
+    convert_update_v1_to_v2,
+    convert_update_v2_to_v1,
+    decode_update,
+    decode_update_v2,
+    get_state,
+    get_state_v2,
+    get_update,
+    get_update_v2,
+    merge_updates,
+    merge_updates_v2,
+)
 
 
 def test_update():
@@ -53,3 +72,146 @@ def test_update_transaction():
         doc1.apply_update(update1)
 
     assert str(doc1.get("test", type=Text)) == "Hello World!"
+
+
+def test_update_v2():
+    data0 = Map({"key0": "val0"})
+    doc0 = Doc()
+    doc0["data"] = data0
+
+    data1 = Map({"key1": "val1"})
+    doc1 = Doc()
+    doc1["data"] = data1
+
+    update0 = doc0.get_update_v2()
+    update1 = doc1.get_update_v2()
+    assert get_state_v2(update0) == doc0.get_state()
+    assert get_state_v2(update1) == doc1.get_state()
+
+    del doc0
+    del doc1
+    state0 = get_state_v2(update0)
+    state1 = get_state_v2(update1)
+
+    update01 = get_update_v2(update0, state1)
+    update10 = get_update_v2(update1, state0)
+
+    # sync clients
+    update0 = merge_updates_v2(update0, update10)
+    update1 = merge_updates_v2(update1, update01)
+    assert update0 == update1
+
+    doc0 = Doc()
+    data0 = doc0.get("data", type=Map)
+    doc0.apply_update_v2(update0)
+    doc1 = Doc()
+    data1 = doc1.get("data", type=Map)
+    doc1.apply_update(convert_update_v2_to_v1(update1))
+
+    assert data0.to_py() == data1.to_py() == {"key0": "val0", "key1": "val1"}
+
+
+def test_convert_update():
+    doc0 = Doc()
+    text0 = doc0.get("test", type=Text)
+    text0 += "Hello, World!"
+
+    update_v1 = doc0.get_update()
+    update_v2 = doc0.get_update_v2()
+    assert convert_update_v1_to_v2(update_v1) == update_v2
+    assert convert_update_v2_to_v1(update_v2) == update_v1
+
+    with pytest.raises(ValueError, match="Cannot decode update"):
+        convert_update_v2_to_v1(b"\xff")
+    with pytest.raises(ValueError, match="Cannot decode update"):
+        Doc().apply_update_v2(b"\xff")
+
+
+@pytest.mark.parametrize(
+    "decode",
+    [
+        lambda update: merge_updates_v2(update, update),
+        get_state_v2,
+        lambda update: get_update_v2(update, Doc().get_state()),
+        convert_update_v2_to_v1,
+        decode_update_v2,
+        lambda update: Doc().apply_update_v2(update),
+        lambda update: IdMap.from_update(update, [], v2=True),
+        Snapshot.decode_v2,
+    ],
+)
+def test_malformed_update_v2(decode):
+    # this input makes the v2 decoder of yrs read past the end of a column
+    with pytest.raises(DecodeError):
+        decode(b"\x08\x8f")
+
+
+def test_transaction_event_update_v2():
+    doc0 = Doc()
+    text0 = doc0.get("test", type=Text)
+    updates = []
+    doc0.observe(lambda event: updates.append(event.update_v2))
+    text0 += "Hello"
+    text0 += " World!"
+
+    doc1 = Doc()
+    with doc1.transaction():
+        for update in updates:
+            doc1.apply_update_v2(update)
+
+    assert str(doc1.get("test", type=Text)) == "Hello World!"
+
+
+def test_decode_update():
+    doc = Doc(client_id=1)
+    text = doc.get("text", type=Text)
+    map = doc.get("map", type=Map)
+    text += "hello"
+    text.insert(2, "X")
+    del text[0]
+    map["array"] = Array(["foo"])
+
+    decoded = decode_update(doc.get_update())
+    assert decoded == decode_update_v2(doc.get_update_v2())
+    assert decoded["delete_set"] == {1: [(0, 1)]}
+    structs = decoded["structs"]
+    assert [(s["client"], s["clock"], s["length"], s["content"], s["value"]) for s in structs] == [
+        (1, 0, 1, "deleted", None),
+        (1, 1, 1, "string", "e"),
+        (1, 2, 3, "string", "llo"),
+        (1, 5, 1, "string", "X"),
+        (1, 6, 1, "type", "Array"),
+        (1, 7, 1, "any", ["foo"]),
+    ]
+    assert structs[0]["type"] == "item"
+    assert structs[0]["parent"] == "text"
+    assert structs[3]["origin"] == (1, 1)
+    assert structs[3]["right_origin"] == (1, 2)
+    assert structs[3]["parent"] is None
+    assert structs[4]["parent"] == "map"
+    assert structs[4]["parent_sub"] == "array"
+    assert structs[5]["parent"] == (1, 6)
+
+    with pytest.raises(ValueError):
+        decode_update(b"\x01")
+    # a block whose clock overflows
+    with pytest.raises(ValueError):
+        decode_update(bytes([1, 2, 1, 0, 10, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 10, 5, 0]))
+
+
+@pytest.mark.parametrize(
+    "update",
+    [
+        # the name of the parent
+        bytes([1, 1, 1, 0, 4, 1, 1, 0xFF, 1, ord("a"), 0]),
+        # the content of a string
+        bytes([1, 1, 1, 0, 4, 1, 1, ord("t"), 1, 0xFF, 0]),
+        # the key in the parent
+        bytes([1, 1, 1, 0, 0x24, 1, 1, ord("t"), 1, 0xFF, 1, ord("a"), 0]),
+        # a string value
+        bytes([1, 1, 1, 0, 8, 1, 1, ord("t"), 1, 119, 1, 0xFF, 0]),
+    ],
+)
+def test_decode_update_invalid_utf8(update):
+    with pytest.raises(DecodeError, match="Invalid UTF-8 string"):
+        decode_update(update)
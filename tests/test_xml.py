@@ -166,6 +166,16 @@ def test_text():
     doc["test2"] = XmlFragment([XmlText()])
 
 
+def test_text_offset_kind():
+    doc = Doc(offset_kind="utf16")
+    doc["fragment"] = fragment = XmlFragment()
+    text = fragment.children.append(XmlText("😀"))
+    assert len(text) == 2
+    text.insert(2, "a")
+    text.format(2, 3, {"bold": True})
+    assert str(text) == "😀<bold>a</bold>"
+
+
 def test_xmltext_embed_in_xmltext():
     doc = Doc()
     doc["root"] = XmlFragment()